@@ -1,6 +1,7 @@
 //! Event types.
 
 use crate::Node;
+use std::ops::{Deref, DerefMut};
 
 #[cfg(feature = "webdom")]
 use {
@@ -9,15 +10,17 @@ use {
     std::{
         default::Default,
         fmt::{Debug, Formatter, Result as FmtResult},
-        ops::{Deref, DerefMut},
     },
     wasm_bindgen::{prelude::*, JsCast},
     web_sys as sys,
 };
 
+#[cfg(not(feature = "webdom"))]
+use {crate::rsdom::event as sys, std::fmt::Debug};
+
 /// An event that can be received as the first argument to a handler callback.
 #[cfg(feature = "webdom")]
-pub trait Event: AsRef<web_sys::Event> + JsCast {
+pub trait Event: AsRef<web_sys::Event> + Clone + JsCast + 'static {
     /// The name used to register for this event in `addEventListener`.
     const NAME: &'static str;
 
@@ -37,75 +40,165 @@ pub trait Event: AsRef<web_sys::Event> + JsCast {
 
 /// An event that can be received as the first argument to a handler callback.
 #[cfg(not(feature = "webdom"))]
-pub trait Event {
+pub trait Event: AsRef<sys::Event> + Clone + Debug + 'static {
     /// The name used to register for this event in `addEventListener`.
     const NAME: &'static str;
+
+    /// The builder type returned by `new()`.
+    type Builder: Default + EventBuilder<Output = Self>;
+
+    /// Make a new event, returning a builder.
+    fn new() -> Self::Builder {
+        Self::Builder::default()
+    }
 }
 
 /// A binding of a particular event listener to a DOM node. The listener is
 /// removed when this value is dropped.
-#[cfg(feature = "webdom")]
 #[must_use]
 pub struct EventHandle {
-    target: Option<web_sys::EventTarget>,
-    callback: webdom::Callback,
+    #[cfg(feature = "webdom")]
+    concrete: Option<(web_sys::EventTarget, webdom::Callback)>,
+    #[cfg(feature = "rsdom")]
+    _virtual: Option<crate::rsdom::ListenerHandle>,
+    #[cfg(feature = "webdom")]
     name: &'static str,
+    #[cfg(feature = "webdom")]
+    capture: bool,
 }
 
-#[cfg(not(feature = "webdom"))]
-pub(crate) struct EventHandle;
-
 impl EventHandle {
     /// Construct a new `EventHandle`, binding the provided callback to its
-    /// target if the target is able to receive events.
-    pub fn new<Ev>(_target: &Node, _callback: impl FnMut(Ev) + 'static) -> Self
+    /// target if the target is able to receive events. The callback is called
+    /// when the event reaches the target or bubbles up from its descendants.
+    pub fn new<Ev>(target: &Node, callback: impl FnMut(Ev) + 'static) -> Self
     where
         Ev: Event,
     {
-        #[cfg(not(feature = "webdom"))]
-        {
-            Self
-        }
+        Self::bind(target, false, callback)
+    }
+
+    /// Construct a new `EventHandle` like [`EventHandle::new`], except that the
+    /// callback is called while the event travels down towards its target,
+    /// before any non-capturing listeners.
+    pub fn new_capturing<Ev>(target: &Node, callback: impl FnMut(Ev) + 'static) -> Self
+    where
+        Ev: Event,
+    {
+        Self::bind(target, true, callback)
+    }
 
-        #[cfg(feature = "webdom")]
-        {
-            let name = Ev::NAME;
-            let callback = webdom::Callback::new(_callback);
-            let target = match _target {
-                Node::Concrete(n) => {
-                    let target: &web_sys::EventTarget = n.as_ref();
-                    target.add_event_listener_with_callback(name, callback.as_fn()).unwrap();
-                    Some(target.to_owned())
-                }
-                #[cfg(feature = "rsdom")]
-                _ => None,
-            };
-
-            Self { target, callback, name }
+    fn bind<Ev>(target: &Node, capture: bool, callback: impl FnMut(Ev) + 'static) -> Self
+    where
+        Ev: Event,
+    {
+        let mut handle = Self {
+            #[cfg(feature = "webdom")]
+            concrete: None,
+            #[cfg(feature = "rsdom")]
+            _virtual: None,
+            #[cfg(feature = "webdom")]
+            name: Ev::NAME,
+            #[cfg(feature = "webdom")]
+            capture,
+        };
+
+        match target {
+            #[cfg(feature = "webdom")]
+            Node::Concrete(n) => {
+                let callback = webdom::Callback::new(callback);
+                let target: &web_sys::EventTarget = n.as_ref();
+                target
+                    .add_event_listener_with_callback_and_bool(Ev::NAME, callback.as_fn(), capture)
+                    .unwrap();
+                handle.concrete = Some((target.to_owned(), callback));
+            }
+            #[cfg(feature = "rsdom")]
+            Node::Virtual(n) => {
+                handle._virtual = Some(crate::rsdom::listen(n, capture, callback));
+            }
         }
+
+        handle
     }
 }
 
 #[cfg(feature = "webdom")]
 impl Drop for EventHandle {
     fn drop(&mut self) {
-        if let Some(target) = self.target.take() {
-            target.remove_event_listener_with_callback(self.name, self.callback.as_fn()).unwrap();
+        if let Some((target, callback)) = self.concrete.take() {
+            target
+                .remove_event_listener_with_callback_and_bool(
+                    self.name,
+                    callback.as_fn(),
+                    self.capture,
+                )
+                .unwrap();
         }
     }
 }
 
 #[cfg(not(feature = "webdom"))]
 macro_rules! event_ty {
-    ($(#[$attr:meta])* $name:ident, $ty_str:expr, $parent_ty:ty) => {
+    (
+        $(#[$attr:meta])* $name:ident,
+        $ty_str:expr, $parent_ty:ty,
+        $builder:ident, $init_ty:ty
+    ) => {
         $(#[$attr])*
-        #[derive(Debug)]
-        pub struct $name;
+        #[derive(Clone, Debug)]
+        pub struct $name($parent_ty);
+
+        /// A builder for events.
+        #[derive(Default)]
+        pub struct $builder($init_ty);
+
+        impl AsRef<sys::Event> for $name {
+            fn as_ref(&self) -> &sys::Event {
+                self.0.as_ref()
+            }
+        }
+
+        impl Deref for $name {
+            type Target = $parent_ty;
+            fn deref(&self) -> &Self::Target {
+                &self.0
+            }
+        }
 
         impl Event for $name {
             const NAME: &'static str = $ty_str;
+            type Builder = $builder;
+        }
+
+        impl EventBuilder for $builder {
+            type Output = $name;
+            fn build(&mut self) -> $name {
+                $name(($name::NAME, &mut self.0).build())
+            }
+        }
+
+        impl Deref for $builder {
+            type Target = $init_ty;
+            fn deref(&self) -> &Self::Target {
+                &self.0
+            }
+        }
+
+        impl DerefMut for $builder {
+            fn deref_mut(&mut self) -> &mut Self::Target {
+                &mut self.0
+            }
         }
     };
+    ($(#[$attr:meta])* $name:ident, $ty_str:expr, sys::$parent_ty:ty) => {paste::paste! {
+        event_ty! {
+            $(#[$attr])* $name, $ty_str,
+            sys::$parent_ty,
+            [<$name Builder>],
+            sys::[<$parent_ty Init>]
+        }
+    }};
 }
 
 #[cfg(feature = "webdom")]
@@ -134,7 +227,13 @@ macro_rules! event_ty {
             }
         }
 
-        impl std::ops::Deref for $name {
+        impl Clone for $name {
+            fn clone(&self) -> Self {
+                $name(self.0.clone())
+            }
+        }
+
+        impl Deref for $name {
             type Target = $parent_ty;
             fn deref(&self) -> &Self::Target {
                 &self.0
@@ -229,6 +328,7 @@ macro_rules! impl_event_builder_for_dict {
     }};
 }
 
+#[cfg(feature = "webdom")]
 impl_event_builder_for_dict![
     AnimationEvent,
     BlobEvent,
@@ -244,6 +344,7 @@ impl_event_builder_for_dict![
     GamepadEvent,
     HashChangeEvent,
     IdbVersionChangeEvent,
+    InputEvent,
     KeyboardEvent::new_with_keyboard_event_init_dict,
     MessageEvent,
     MouseEvent::new_with_mouse_event_init_dict,
@@ -265,6 +366,29 @@ impl_event_builder_for_dict![
     WheelEvent,
 ];
 
+#[cfg(not(feature = "webdom"))]
+impl_event_builder_for_dict![
+    AnimationEvent,
+    CompositionEvent,
+    DragEvent,
+    Event,
+    FocusEvent::new_with_focus_event_init_dict,
+    GamepadEvent,
+    HashChangeEvent,
+    InputEvent,
+    KeyboardEvent::new_with_keyboard_event_init_dict,
+    MessageEvent,
+    MouseEvent::new_with_mouse_event_init_dict,
+    PageTransitionEvent,
+    PointerEvent,
+    ProgressEvent,
+    SpeechRecognitionEvent,
+    StorageEvent,
+    TouchEvent,
+    UiEvent,
+    WheelEvent,
+];
+
 event_ty! {
     /// The loading of a resource has been aborted. [MDN documentation][mdn]
     ///
@@ -850,7 +974,7 @@ event_ty! {
     /// [contenteditable]: https://developer.mozilla.org/en-US/docs/DOM/Element.contentEditable
     Input,
     "input",
-    sys::Event
+    sys::InputEvent
 }
 
 event_ty! {
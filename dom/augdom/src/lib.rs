@@ -30,6 +30,7 @@ use futures::Stream;
 use quick_xml::Writer as XmlWriter;
 use std::{
     fmt::{Debug, Display, Formatter, Result as FmtResult},
+    hash::{Hash, Hasher},
     io::{prelude::*, Cursor},
     pin::Pin,
     task::{Context, Poll},
@@ -51,7 +52,7 @@ pub fn document() -> Document {
         _e => {
             #[cfg(not(feature = "webdom"))]
             {
-                _e.unwrap()
+                _e.unwrap().clone()
             }
             #[cfg(feature = "webdom")]
             {
@@ -136,15 +137,38 @@ pub trait Dom: Sized {
     /// Ensure the provided attribute has been removed from this DOM node.
     fn remove_attribute(&self, name: &str);
 
+    /// Returns the parent of this node, if it has one.
+    fn parent_node(&self) -> Option<Self>;
+
     /// Returns the next child of this node's parent after this node itself.
     fn next_sibling(&self) -> Option<Self>;
 
+    /// Returns the previous child of this node's parent before this node
+    /// itself.
+    fn previous_sibling(&self) -> Option<Self>;
+
     /// Returns the first child of this node.
     fn first_child(&self) -> Option<Self>;
 
-    /// Adds a new child to the end of this node's children.
+    /// Returns the last child of this node.
+    fn last_child(&self) -> Option<Self>;
+
+    /// Returns a static (not live) list of this node's children.
+    fn child_nodes(&self) -> Self::Nodes;
+
+    /// Returns true if `other` is this node or one of its descendants.
+    fn contains(&self, other: &Self) -> bool;
+
+    /// Adds a new child to the end of this node's children. If the child is
+    /// already in the tree it is moved from its current position.
     fn append_child(&self, child: &Self);
 
+    /// Inserts a new child before `reference`, which must be a child of this
+    /// node, or at the end of this node's children if `reference` is `None`.
+    /// If the child is already in the tree it is moved from its current
+    /// position.
+    fn insert_before(&self, new_child: &Self, reference: Option<&Self>);
+
     /// Replaces the provided child of this node with a new one.
     fn replace_child(&self, new_child: &Self, existing: &Self);
 
@@ -157,6 +181,20 @@ pub trait Dom: Sized {
     /// clipboard.
     fn get_inner_text(&self) -> String;
 
+    /// Returns the kind of this node.
+    fn node_type(&self) -> NodeType;
+
+    /// Returns the contents of this node if it is a text or comment node.
+    fn get_node_value(&self) -> Option<String>;
+
+    /// Replaces the contents of this node if it is a text or comment node, has
+    /// no effect otherwise.
+    fn set_node_value(&self, value: &str);
+
+    /// Returns the document fragment holding this node's contents if it is a
+    /// `<template>` element.
+    fn template_content(&self) -> Option<Self>;
+
     /// Synchronously invokes the affected EventListeners in the appropriate
     /// order. The normal event processing rules (including the capturing
     /// and optional bubbling phase) also apply to events dispatched
@@ -164,21 +202,66 @@ pub trait Dom: Sized {
     fn dispatch<E: event::Event>(&self, event: E);
 
     /// Returns the first descendant of `self` which matches the specified
-    /// [selectors].
+    /// [selectors], or an error if they aren't valid.
     ///
     /// [selectors]: https://developer.mozilla.org/en-US/docs/Web/CSS/CSS_Selectors
-    fn query_selector(&self, selectors: &str) -> Option<Self>;
+    fn query_selector(&self, selectors: &str) -> Result<Option<Self>, InvalidSelector>;
 
     /// Returns a static (not live) Vec of descendents of `self` which match the
-    /// specified [selectors].
+    /// specified [selectors], or an error if they aren't valid.
     ///
     /// [selectors]: https://developer.mozilla.org/en-US/docs/Web/CSS/CSS_Selectors
-    fn query_selector_all(&self, selectors: &str) -> Self::Nodes;
+    fn query_selector_all(&self, selectors: &str) -> Result<Self::Nodes, InvalidSelector>;
 
     /// Return a stream of mutations related to the subtree under this node.
     fn observe_mutations(&self) -> Self::Observer;
 }
 
+/// The error returned by [`Dom::query_selector`] and
+/// [`Dom::query_selector_all`] for selectors which can't be parsed,
+/// corresponding to the `SyntaxError` thrown on the web.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InvalidSelector {
+    selectors: String,
+    reason: String,
+}
+
+impl InvalidSelector {
+    pub(crate) fn new(selectors: &str, reason: impl Display) -> Self {
+        Self { selectors: selectors.to_owned(), reason: reason.to_string() }
+    }
+}
+
+impl Display for InvalidSelector {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "invalid selector `{}`: {}", self.selectors, self.reason)
+    }
+}
+
+impl std::error::Error for InvalidSelector {}
+
+/// The kind of a DOM node, corresponding to [`Node.nodeType`][mdn] on the web.
+///
+/// [mdn]: https://developer.mozilla.org/en-US/docs/Web/API/Node/nodeType
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NodeType {
+    /// An element like `<p>` or `<div>`.
+    Element,
+    /// The text inside an element or attribute.
+    Text,
+    /// A comment like `<!-- ... -->`.
+    Comment,
+    /// A document.
+    Document,
+    /// A document type declaration like `<!DOCTYPE html>`.
+    DocumentType,
+    /// A document fragment.
+    DocumentFragment,
+    /// A kind of node which augdom doesn't create, like a CDATA section or a
+    /// processing instruction, with its numeric `nodeType`.
+    Other(u16),
+}
+
 /// The current document.
 #[derive(Clone)]
 pub enum Document {
@@ -272,6 +355,56 @@ impl Document {
             Document::Virtual { .. } => Node::Virtual(VirtNode::create_text_node(contents)),
         }
     }
+
+    /// Create a new comment node in this document.
+    pub fn create_comment(&self, contents: &str) -> Node {
+        match self {
+            #[cfg(feature = "webdom")]
+            Document::Concrete(d) => {
+                let comment = d.create_comment(contents);
+                let node: &sys::Node = comment.as_ref();
+                Node::Concrete(node.clone())
+            }
+            #[cfg(feature = "rsdom")]
+            Document::Virtual { .. } => Node::Virtual(VirtNode::create_comment(contents)),
+        }
+    }
+
+    /// Create a new, empty document fragment in this document. Inserting the
+    /// fragment into a node moves the fragment's children into that node.
+    pub fn create_document_fragment(&self) -> Node {
+        match self {
+            #[cfg(feature = "webdom")]
+            Document::Concrete(d) => {
+                let fragment = d.create_document_fragment();
+                let node: &sys::Node = fragment.as_ref();
+                Node::Concrete(node.clone())
+            }
+            #[cfg(feature = "rsdom")]
+            Document::Virtual { .. } => Node::Virtual(VirtNode::create_document_fragment()),
+        }
+    }
+
+    /// Create a new document type node in this document, e.g.
+    /// `create_doctype("html", "", "")` for `<!DOCTYPE html>`.
+    pub fn create_doctype(&self, name: &str, public_id: &str, system_id: &str) -> Node {
+        match self {
+            #[cfg(feature = "webdom")]
+            Document::Concrete(d) => {
+                let doctype = d
+                    .implementation()
+                    .unwrap()
+                    .create_document_type(name, public_id, system_id)
+                    .unwrap();
+                let node: &sys::Node = doctype.as_ref();
+                Node::Concrete(node.clone())
+            }
+            #[cfg(feature = "rsdom")]
+            Document::Virtual { .. } => {
+                Node::Virtual(VirtNode::create_doctype(name, public_id, system_id))
+            }
+        }
+    }
 }
 
 impl Debug for Document {
@@ -323,6 +456,21 @@ impl PartialEq for Node {
     }
 }
 
+impl Eq for Node {}
+
+/// Nodes are hashed by identity, like they're compared.
+impl Hash for Node {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        match self {
+            #[cfg(feature = "webdom")]
+            Node::Concrete(n) => webdom::identity(n).hash(state),
+
+            #[cfg(feature = "rsdom")]
+            Node::Virtual(n) => Rc::as_ptr(n).hash(state),
+        }
+    }
+}
+
 impl Dom for Node {
     type MutationRecord = MutationRecord;
     type Nodes = Vec<Self>;
@@ -366,6 +514,54 @@ impl Dom for Node {
         }
     }
 
+    fn insert_before(&self, new_child: &Self, reference: Option<&Self>) {
+        match self {
+            #[cfg(feature = "webdom")]
+            Node::Concrete(n) => {
+                <sys::Node as Dom>::insert_before(
+                    n,
+                    new_child.expect_concrete(),
+                    reference.map(Node::expect_concrete),
+                );
+            }
+
+            #[cfg(feature = "rsdom")]
+            Node::Virtual(n) => {
+                n.insert_before(new_child.expect_virtual(), reference.map(Node::expect_virtual));
+            }
+        }
+    }
+
+    fn last_child(&self) -> Option<Self> {
+        match self {
+            #[cfg(feature = "webdom")]
+            Node::Concrete(n) => <sys::Node as Dom>::last_child(n).map(Node::Concrete),
+
+            #[cfg(feature = "rsdom")]
+            Node::Virtual(n) => n.last_child().map(Node::Virtual),
+        }
+    }
+
+    fn child_nodes(&self) -> Self::Nodes {
+        match self {
+            #[cfg(feature = "webdom")]
+            Node::Concrete(n) => <sys::Node as Dom>::child_nodes(n).map(Node::Concrete).collect(),
+
+            #[cfg(feature = "rsdom")]
+            Node::Virtual(n) => n.child_nodes().into_iter().map(Node::Virtual).collect(),
+        }
+    }
+
+    fn parent_node(&self) -> Option<Self> {
+        match self {
+            #[cfg(feature = "webdom")]
+            Node::Concrete(n) => <sys::Node as Dom>::parent_node(n).map(Node::Concrete),
+
+            #[cfg(feature = "rsdom")]
+            Node::Virtual(n) => n.parent_node().map(Node::Virtual),
+        }
+    }
+
     fn next_sibling(&self) -> Option<Self> {
         match self {
             #[cfg(feature = "webdom")]
@@ -376,6 +572,26 @@ impl Dom for Node {
         }
     }
 
+    fn previous_sibling(&self) -> Option<Self> {
+        match self {
+            #[cfg(feature = "webdom")]
+            Node::Concrete(n) => <sys::Node as Dom>::previous_sibling(n).map(Node::Concrete),
+
+            #[cfg(feature = "rsdom")]
+            Node::Virtual(n) => n.previous_sibling().map(Node::Virtual),
+        }
+    }
+
+    fn contains(&self, other: &Self) -> bool {
+        match self {
+            #[cfg(feature = "webdom")]
+            Node::Concrete(n) => <sys::Node as Dom>::contains(n, other.expect_concrete()),
+
+            #[cfg(feature = "rsdom")]
+            Node::Virtual(n) => <Rc<VirtNode> as Dom>::contains(n, other.expect_virtual()),
+        }
+    }
+
     fn remove_child(&self, to_remove: &Self) -> Option<Self> {
         match self {
             #[cfg(feature = "webdom")]
@@ -442,33 +658,69 @@ impl Dom for Node {
         }
     }
 
-    fn dispatch<E: event::Event>(&self, event: E) {
+    fn node_type(&self) -> NodeType {
         match self {
             #[cfg(feature = "webdom")]
-            Node::Concrete(n) => <sys::Node as Dom>::dispatch(n, event),
+            Node::Concrete(n) => <sys::Node as Dom>::node_type(n),
             #[cfg(feature = "rsdom")]
-            Node::Virtual(n) => <Rc<VirtNode> as Dom>::dispatch(n, event),
+            Node::Virtual(n) => <Rc<VirtNode> as Dom>::node_type(n),
         }
     }
 
-    fn query_selector(&self, selectors: &str) -> Option<Self> {
+    fn get_node_value(&self) -> Option<String> {
         match self {
             #[cfg(feature = "webdom")]
-            Node::Concrete(n) => n.query_selector(selectors).map(Node::Concrete),
+            Node::Concrete(n) => <sys::Node as Dom>::get_node_value(n),
             #[cfg(feature = "rsdom")]
-            Node::Virtual(n) => n.query_selector(selectors).map(Node::Virtual),
+            Node::Virtual(n) => <Rc<VirtNode> as Dom>::get_node_value(n),
         }
     }
 
-    fn query_selector_all(&self, selectors: &str) -> Self::Nodes {
+    fn set_node_value(&self, value: &str) {
         match self {
             #[cfg(feature = "webdom")]
-            Node::Concrete(n) => n.query_selector_all(selectors).map(Node::Concrete).collect(),
+            Node::Concrete(n) => <sys::Node as Dom>::set_node_value(n, value),
+            #[cfg(feature = "rsdom")]
+            Node::Virtual(n) => <Rc<VirtNode> as Dom>::set_node_value(n, value),
+        }
+    }
+
+    fn template_content(&self) -> Option<Self> {
+        match self {
+            #[cfg(feature = "webdom")]
+            Node::Concrete(n) => <sys::Node as Dom>::template_content(n).map(Node::Concrete),
+            #[cfg(feature = "rsdom")]
+            Node::Virtual(n) => <Rc<VirtNode> as Dom>::template_content(n).map(Node::Virtual),
+        }
+    }
+
+    fn dispatch<E: event::Event>(&self, event: E) {
+        match self {
+            #[cfg(feature = "webdom")]
+            Node::Concrete(n) => <sys::Node as Dom>::dispatch(n, event),
+            #[cfg(feature = "rsdom")]
+            Node::Virtual(n) => <Rc<VirtNode> as Dom>::dispatch(n, event),
+        }
+    }
+
+    fn query_selector(&self, selectors: &str) -> Result<Option<Self>, InvalidSelector> {
+        Ok(match self {
+            #[cfg(feature = "webdom")]
+            Node::Concrete(n) => n.query_selector(selectors)?.map(Node::Concrete),
+            #[cfg(feature = "rsdom")]
+            Node::Virtual(n) => n.query_selector(selectors)?.map(Node::Virtual),
+        })
+    }
+
+    fn query_selector_all(&self, selectors: &str) -> Result<Self::Nodes, InvalidSelector> {
+        Ok(match self {
+            #[cfg(feature = "webdom")]
+            Node::Concrete(n) => n.query_selector_all(selectors)?.map(Node::Concrete).collect(),
             #[cfg(feature = "rsdom")]
             Node::Virtual(n) => {
-                n.query_selector_all(selectors).into_iter().map(Node::Virtual).collect()
+                n.query_selector_all(selectors)?.into_iter().map(Node::Virtual).collect()
             }
-        }
+        })
     }
 
     fn observe_mutations(&self) -> Self::Observer {
@@ -528,7 +780,7 @@ pub enum MutationRecord {
     Virtual(rsdom::Mutation),
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "webdom"))]
 mod tests {
     use super::{
         testing::{Query, TargetExt},
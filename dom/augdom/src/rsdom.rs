@@ -13,29 +13,216 @@ use std::{
     rc::{Rc, Weak},
 };
 
+mod dispatch;
+#[cfg(not(feature = "webdom"))]
+pub mod event;
+mod html;
+mod inner_text;
+mod mutation;
+mod parse;
+mod selector;
+
+pub(crate) use dispatch::{listen, ListenerHandle};
+pub use html::HtmlOptions;
+pub use mutation::{Mutation, MutationObserverInit};
+pub use parse::parse_fragment;
+
 /// A node in the "virtual DOM" implemented in `rsdom`.
 pub struct VirtNode {
     parent: Cell<Option<Weak<VirtNode>>>,
     children: RefCell<Vec<Rc<VirtNode>>>,
     data: VirtData,
+    listeners: dispatch::Listeners,
+    observers: RefCell<Vec<mutation::Registration>>,
 }
 
 impl VirtNode {
-    pub(crate) fn create_element(ty: &str) -> Rc<VirtNode> {
+    fn new(data: VirtData) -> Rc<VirtNode> {
         Rc::new(VirtNode {
             parent: Cell::new(None),
             children: RefCell::new(vec![]),
-            data: VirtData::Elem { tag: ty.to_string(), attrs: RefCell::new(vec![]) },
+            data,
+            listeners: Default::default(),
+            observers: RefCell::new(vec![]),
         })
     }
 
+    pub(crate) fn create_element(ty: &str) -> Rc<VirtNode> {
+        // templates hold their contents in a fragment rather than as children
+        let content = if ty == "template" { Some(Self::create_document_fragment()) } else { None };
+        Self::new(VirtData::Elem { tag: ty.to_string(), attrs: RefCell::new(vec![]), content })
+    }
+
     pub(crate) fn create_text_node(contents: &str) -> Rc<VirtNode> {
-        Rc::new(VirtNode {
-            parent: Cell::new(None),
-            children: RefCell::new(vec![]),
-            data: VirtData::Text(contents.to_string()),
+        Self::new(VirtData::Text(RefCell::new(contents.to_string())))
+    }
+
+    pub(crate) fn create_comment(contents: &str) -> Rc<VirtNode> {
+        Self::new(VirtData::Comment(RefCell::new(contents.to_string())))
+    }
+
+    pub(crate) fn create_document_fragment() -> Rc<VirtNode> {
+        Self::new(VirtData::DocumentFragment)
+    }
+
+    pub(crate) fn create_doctype(name: &str, public_id: &str, system_id: &str) -> Rc<VirtNode> {
+        Self::new(VirtData::Doctype {
+            name: name.to_string(),
+            public_id: public_id.to_string(),
+            system_id: system_id.to_string(),
         })
     }
+
+    /// Replace the contents of this text or comment node, corresponding to
+    /// setting [`CharacterData.data`][mdn] on the web.
+    ///
+    /// [mdn]: https://developer.mozilla.org/en-US/docs/Web/API/CharacterData/data
+    ///
+    /// # Panics
+    ///
+    /// If this node is not a text or comment node.
+    pub fn set_data(self: &Rc<Self>, data: &str) {
+        let old_value = match &self.data {
+            VirtData::Text(text) | VirtData::Comment(text) => text.replace(data.to_string()),
+            other => panic!("expected VirtData::Text or VirtData::Comment, found {:?}", other),
+        };
+        self.queue_mutation(Mutation::CharacterData {
+            target: self.clone(),
+            old_value: Some(old_value),
+        });
+    }
+
+    /// Returns the value of the named attribute if this is an element which has
+    /// it.
+    pub(crate) fn attribute(&self, name: &str) -> Option<String> {
+        match &self.data {
+            VirtData::Elem { attrs, .. } => {
+                attrs.borrow().iter().find(|(attr, _)| attr == name).map(|(_, value)| value.clone())
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns this node's tag if it is an element.
+    pub(crate) fn tag(&self) -> Option<&str> {
+        match &self.data {
+            VirtData::Elem { tag, .. } => Some(tag),
+            _ => None,
+        }
+    }
+
+    /// Returns the fragment holding this node's contents if it is a
+    /// `<template>`.
+    pub(crate) fn content(&self) -> Option<&Rc<VirtNode>> {
+        match &self.data {
+            VirtData::Elem { content, .. } => content.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// Returns the nodes to insert into `parent` in place of `node`: its
+    /// children if it is a document fragment, which are removed from it, or
+    /// otherwise the node itself after removing it from its current parent.
+    ///
+    /// # Panics
+    ///
+    /// If `node` is `parent` or one of its ancestors.
+    fn take_insertable(parent: &Rc<VirtNode>, node: &Rc<VirtNode>) -> Vec<Rc<VirtNode>> {
+        assert!(!node.contains(parent), "can't insert a node into its own subtree");
+        if let VirtData::DocumentFragment = node.data {
+            let children = node.children.replace(vec![]);
+            for child in &children {
+                child.parent.set(None);
+            }
+            children
+        } else {
+            if let Some(current_parent) = node.parent() {
+                current_parent.remove_child(node);
+            }
+            vec![node.clone()]
+        }
+    }
+
+    /// Returns the index of `child` among this node's children.
+    fn child_index(&self, child: &Rc<VirtNode>) -> Option<usize> {
+        self.children.borrow().iter().position(|c| Rc::ptr_eq(c, child))
+    }
+
+    /// Returns true if this node is an element.
+    pub(crate) fn is_element(&self) -> bool {
+        matches!(self.data, VirtData::Elem { .. })
+    }
+
+    /// Returns the children before `start` and at `end`, i.e. the siblings
+    /// around a range of children which has been added or removed.
+    fn children_around(
+        &self,
+        start: usize,
+        end: usize,
+    ) -> (Option<Rc<VirtNode>>, Option<Rc<VirtNode>>) {
+        let children = self.children.borrow();
+        let previous = start.checked_sub(1).and_then(|i| children.get(i).cloned());
+        (previous, children.get(end).cloned())
+    }
+
+    /// Returns this node's parent, if it has one.
+    pub(crate) fn parent(&self) -> Option<Rc<VirtNode>> {
+        let parent = self.parent.replace(None);
+        let upgraded = parent.as_ref().and_then(Weak::upgrade);
+        self.parent.set(parent);
+        upgraded
+    }
+
+    /// Returns the previous child of this node's parent before this node.
+    pub(crate) fn prev_sibling(self: &Rc<Self>) -> Option<Rc<VirtNode>> {
+        let parent = self.parent()?;
+        let children = parent.children.borrow();
+        let idx = children.iter().position(|child| Rc::ptr_eq(child, self))?;
+        idx.checked_sub(1).and_then(|i| children.get(i).cloned())
+    }
+
+    /// Performs a depth-first pre-order traversal of this node's descendants,
+    /// returning each one for which `filter` returns true.
+    fn descendants_where(&self, filter: &impl Fn(&Rc<VirtNode>) -> bool) -> Vec<Rc<VirtNode>> {
+        let mut found = vec![];
+        for child in self.children.borrow().iter() {
+            if filter(child) {
+                found.push(child.clone());
+            }
+            found.extend(child.descendants_where(filter));
+        }
+        found
+    }
+
+    /// Performs a depth-first pre-order traversal of this node's descendants,
+    /// returning the first for which `filter` returns true.
+    fn first_descendant_where(
+        &self,
+        filter: &impl Fn(&Rc<VirtNode>) -> bool,
+    ) -> Option<Rc<VirtNode>> {
+        self.children.borrow().iter().find_map(|child| {
+            if filter(child) {
+                Some(child.clone())
+            } else {
+                child.first_descendant_where(filter)
+            }
+        })
+    }
+
+    fn write_xml_children<W: Write>(&self, writer: &mut XmlWriter<W>) {
+        for child in self.children.borrow().iter() {
+            child.write_xml(writer);
+        }
+    }
+}
+
+impl Debug for VirtNode {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.debug_struct("VirtNode")
+            .field("data", &self.data)
+            .field("children", &self.children.borrow())
+            .finish()
+    }
 }
 
 impl crate::Dom for Rc<VirtNode> {
@@ -45,7 +232,7 @@ impl crate::Dom for Rc<VirtNode> {
 
     fn write_xml<W: Write>(&self, writer: &mut XmlWriter<W>) {
         match &self.data {
-            VirtData::Elem { tag, attrs } => {
+            VirtData::Elem { tag, attrs, content } => {
                 writer
                     .write_event(Event::Start(
                         BytesStart::borrowed_name(tag.as_bytes()).with_attributes(
@@ -54,9 +241,7 @@ impl crate::Dom for Rc<VirtNode> {
                     ))
                     .expect("writing start of element");
 
-                for child in self.children.borrow().iter() {
-                    child.write_xml(writer);
-                }
+                content.as_ref().unwrap_or(self).write_xml_children(writer);
 
                 writer
                     .write_event(Event::End(BytesEnd::borrowed(tag.as_bytes())))
@@ -64,9 +249,20 @@ impl crate::Dom for Rc<VirtNode> {
             }
             VirtData::Text(t) => {
                 writer
-                    .write_event(Event::Text(BytesText::from_plain_str(t)))
+                    .write_event(Event::Text(BytesText::from_plain_str(&t.borrow())))
                     .expect("writing text node");
             }
+            VirtData::Comment(c) => {
+                writer
+                    .write_event(Event::Comment(BytesText::from_plain_str(&c.borrow())))
+                    .expect("writing comment node");
+            }
+            VirtData::DocumentFragment => self.write_xml_children(writer),
+            VirtData::Doctype { name, .. } => {
+                writer
+                    .write_event(Event::DocType(BytesText::from_plain_str(&format!(" {}", name))))
+                    .expect("writing doctype node");
+            }
         }
     }
 
@@ -74,6 +270,33 @@ impl crate::Dom for Rc<VirtNode> {
         self.children.borrow().get(0).cloned()
     }
 
+    fn last_child(&self) -> Option<Self> {
+        self.children.borrow().last().cloned()
+    }
+
+    fn child_nodes(&self) -> Self::Nodes {
+        self.children.borrow().clone()
+    }
+
+    fn parent_node(&self) -> Option<Self> {
+        self.parent()
+    }
+
+    fn previous_sibling(&self) -> Option<Self> {
+        self.prev_sibling()
+    }
+
+    fn contains(&self, other: &Self) -> bool {
+        let mut current = Some(other.clone());
+        while let Some(node) = current {
+            if Rc::ptr_eq(&node, self) {
+                return true;
+            }
+            current = node.parent();
+        }
+        false
+    }
+
     fn next_sibling(&self) -> Option<Self> {
         let parent = self.parent.replace(None);
         parent.and_then(|w| w.upgrade()).and_then(|parent| {
@@ -92,55 +315,92 @@ impl crate::Dom for Rc<VirtNode> {
     }
 
     fn append_child(&self, new_child: &Self) {
-        self.children.borrow_mut().push(new_child.clone());
-        new_child.parent.set(Some(Rc::downgrade(self)));
+        self.insert_before(new_child, None);
     }
 
-    fn remove_child(&self, to_remove: &Self) -> Option<Self> {
-        let parent = self.parent.replace(None);
-        parent.and_then(|w| w.upgrade()).and_then(|parent| {
-            self.parent.replace(Some(Rc::downgrade(&parent)));
+    fn insert_before(&self, new_child: &Self, reference: Option<&Self>) {
+        if let Some(r) = reference {
+            // check before detaching `new_child` so a failed insert has no effect
+            assert!(self.child_index(r).is_some(), "can only insert before a child of this node");
+        }
 
-            let mut remove_idx = None;
-            for (i, child) in parent.children.borrow().iter().enumerate() {
-                if Rc::ptr_eq(child, to_remove) {
-                    remove_idx = Some(i);
-                    break;
-                }
-            }
+        // inserting a node before itself leaves it in place
+        let reference = match reference {
+            Some(r) if Rc::ptr_eq(r, new_child) => new_child.next_sibling(),
+            r => r.cloned(),
+        };
+        let added_nodes = VirtNode::take_insertable(self, new_child);
+        if added_nodes.is_empty() {
+            return;
+        }
 
-            remove_idx.map(|i| parent.children.borrow_mut().remove(i))
-        })
+        let idx = match &reference {
+            Some(r) => self.child_index(r).expect("reference is still a child after detaching"),
+            None => self.children.borrow().len(),
+        };
+        self.children.borrow_mut().splice(idx..idx, added_nodes.iter().cloned());
+        for added in &added_nodes {
+            added.parent.set(Some(Rc::downgrade(self)));
+        }
+
+        let (previous_sibling, next_sibling) = self.children_around(idx, idx + added_nodes.len());
+        self.queue_mutation(Mutation::ChildList {
+            target: self.clone(),
+            added_nodes,
+            removed_nodes: vec![],
+            previous_sibling,
+            next_sibling,
+        });
     }
 
-    fn replace_child(&self, new_child: &Self, existing: &Self) {
-        if let Some(parent) = self.parent.replace(None).and_then(|w| w.upgrade()) {
-            self.parent.replace(Some(Rc::downgrade(&parent)));
+    fn remove_child(&self, to_remove: &Self) -> Option<Self> {
+        let idx = self.child_index(to_remove)?;
+        let removed = self.children.borrow_mut().remove(idx);
+        removed.parent.set(None);
 
-            let mut replace_idx = None;
-            for (i, child) in parent.children.borrow().iter().enumerate() {
-                if Rc::ptr_eq(child, existing) {
-                    replace_idx = Some(i);
-                    break;
-                }
-            }
+        let (previous_sibling, next_sibling) = self.children_around(idx, idx);
+        self.queue_mutation(Mutation::ChildList {
+            target: self.clone(),
+            added_nodes: vec![],
+            removed_nodes: vec![removed.clone()],
+            previous_sibling,
+            next_sibling,
+        });
+        Some(removed)
+    }
+
+    fn replace_child(&self, new_child: &Self, existing: &Self) {
+        assert!(self.child_index(existing).is_some(), "can only replace a child of this node");
+        if Rc::ptr_eq(new_child, existing) {
+            return;
+        }
 
-            parent.children.borrow_mut()[replace_idx.unwrap()] = new_child.clone();
-            new_child.parent.set(Some(Rc::downgrade(&parent)));
+        // removing the new child from this node may move the existing one
+        let added_nodes = VirtNode::take_insertable(self, new_child);
+        let idx = self.child_index(existing).unwrap();
+        let replaced = self
+            .children
+            .borrow_mut()
+            .splice(idx..idx + 1, added_nodes.iter().cloned())
+            .next()
+            .expect("replaced child was found above");
+        replaced.parent.set(None);
+        for added in &added_nodes {
+            added.parent.set(Some(Rc::downgrade(self)));
         }
+
+        let (previous_sibling, next_sibling) = self.children_around(idx, idx + added_nodes.len());
+        self.queue_mutation(Mutation::ChildList {
+            target: self.clone(),
+            added_nodes,
+            removed_nodes: vec![replaced],
+            previous_sibling,
+            next_sibling,
+        });
     }
 
     fn get_attribute(&self, name: &str) -> Option<String> {
-        match &self.data {
-            VirtData::Text(_) => None,
-            VirtData::Elem { tag: _, attrs } => attrs.borrow().iter().find_map(|(attr, value)| {
-                if attr == name {
-                    Some(value.clone())
-                } else {
-                    None
-                }
-            }),
-        }
+        self.attribute(name)
     }
 
     fn set_attribute(&self, name: &str, value: &str) {
@@ -149,11 +409,19 @@ impl crate::Dom for Rc<VirtNode> {
             data => panic!("expected VirtData::Elem, found {:?}", data),
         };
 
-        if let Some(existing) = attrs.iter_mut().find(|(n, _)| n == name) {
-            existing.1 = value.to_string();
+        let old_value = if let Some(existing) = attrs.iter_mut().find(|(n, _)| n == name) {
+            Some(std::mem::replace(&mut existing.1, value.to_string()))
         } else {
             attrs.push((name.to_string(), value.to_string()));
-        }
+            None
+        };
+        drop(attrs);
+
+        self.queue_mutation(Mutation::Attributes {
+            target: self.clone(),
+            attribute_name: name.to_string(),
+            old_value,
+        });
     }
 
     fn remove_attribute(&self, name: &str) {
@@ -161,29 +429,66 @@ impl crate::Dom for Rc<VirtNode> {
             VirtData::Elem { ref attrs, .. } => attrs.borrow_mut(),
             data => panic!("expected VirtData::Elem, found {:?}", data),
         };
-        attrs.retain(|(n, _)| n != name);
+
+        if let Some(idx) = attrs.iter().position(|(n, _)| n == name) {
+            let (_, old_value) = attrs.remove(idx);
+            drop(attrs);
+
+            self.queue_mutation(Mutation::Attributes {
+                target: self.clone(),
+                attribute_name: name.to_string(),
+                old_value: Some(old_value),
+            });
+        }
     }
 
     fn get_inner_text(&self) -> String {
-        todo!("traverse the virtnode tree and accumulate text");
+        inner_text::inner_text(self)
     }
 
-    fn dispatch<E: crate::event::Event>(&self, _event: E) {
-        todo!("...need to add event handling to rsdom");
+    fn node_type(&self) -> NodeType {
+        match &self.data {
+            VirtData::Elem { .. } => NodeType::Element,
+            VirtData::Text(_) => NodeType::Text,
+            VirtData::Comment(_) => NodeType::Comment,
+            VirtData::DocumentFragment => NodeType::DocumentFragment,
+            VirtData::Doctype { .. } => NodeType::DocumentType,
+        }
+    }
+
+    fn get_node_value(&self) -> Option<String> {
+        match &self.data {
+            VirtData::Text(data) | VirtData::Comment(data) => Some(data.borrow().clone()),
+            _ => None,
+        }
+    }
+
+    fn set_node_value(&self, value: &str) {
+        if let VirtData::Text(_) | VirtData::Comment(_) = &self.data {
+            self.set_data(value);
+        }
+    }
+
+    fn template_content(&self) -> Option<Self> {
+        self.content().cloned()
     }
 
-    fn query_selector(&self, _selectors: &str) -> Option<Self> {
-        // TODO(#119) implement
-        todo!("still need to integrate selectors crate")
+    fn dispatch<E: crate::event::Event>(&self, event: E) {
+        dispatch::dispatch(self, event);
     }
 
-    fn query_selector_all(&self, _selectors: &str) -> Self::Nodes {
-        // TODO(#119) implement
-        todo!("still need to integrate selectors crate")
+    fn query_selector(&self, selectors: &str) -> Result<Option<Self>, InvalidSelector> {
+        let selectors = selector::Selectors::parse(selectors)?;
+        Ok(self.first_descendant_where(&|node| selectors.matches(node)))
+    }
+
+    fn query_selector_all(&self, selectors: &str) -> Result<Self::Nodes, InvalidSelector> {
+        let selectors = selector::Selectors::parse(selectors)?;
+        Ok(self.descendants_where(&|node| selectors.matches(node)))
     }
 
     fn observe_mutations(&self) -> Self::Observer {
-        todo!("still need to do...this")
+        self.observe_mutations_with_options(&mutation::all_mutations())
     }
 }
 
@@ -196,9 +501,25 @@ pub enum VirtData {
         tag: String,
         /// The element's attributes.
         attrs: RefCell<Vec<(String, String)>>,
+        /// The fragment holding a `<template>` element's contents.
+        content: Option<Rc<VirtNode>>,
     },
     /// A virtual text node.
-    Text(String),
+    Text(RefCell<String>),
+    /// A virtual comment.
+    Comment(RefCell<String>),
+    /// A virtual document fragment. When inserted into another node its
+    /// children are moved into that node in its place.
+    DocumentFragment,
+    /// A virtual document type declaration.
+    Doctype {
+        /// The doctype's name, e.g. `html`.
+        name: String,
+        /// The doctype's public identifier, usually empty.
+        public_id: String,
+        /// The doctype's system identifier, usually empty.
+        system_id: String,
+    },
 }
 
 impl From<Rc<VirtNode>> for Node {
@@ -207,6 +528,33 @@ impl From<Rc<VirtNode>> for Node {
     }
 }
 
+impl Document {
+    /// Parse `html` into a new virtual document. Content which isn't wrapped in
+    /// `<head>` or `<body>` is placed the way a browser would: metadata like
+    /// `<title>` and `<meta>` in the head until the first body content.
+    pub fn parse_virtual(html: &str) -> Self {
+        let (head, body) = parse::parse_document(html);
+        Document::Virtual { head, body }
+    }
+
+    /// Returns this virtual document serialized as HTML, starting with
+    /// `<!DOCTYPE html>`. Panics if this is a concrete document.
+    pub fn to_html(&self, options: &HtmlOptions) -> String {
+        match self {
+            #[cfg(feature = "webdom")]
+            Document::Concrete(_) => {
+                panic!("expected a Document::Virtual, found a Document::Concrete")
+            }
+            Document::Virtual { head, body } => {
+                let mut buf = Vec::new();
+                html::write_document(&mut buf, head, body, options)
+                    .expect("writing to a Vec can't fail");
+                String::from_utf8(buf).expect("html is always valid utf-8")
+            }
+        }
+    }
+}
+
 impl Node {
     /// Returns a reference to a virtual node, panics if this is a concrete
     /// node.
@@ -219,5 +567,194 @@ impl Node {
     }
 }
 
-/// Tracks a mutation in the virtual DOM tree. Currently unimplemented.
-pub struct Mutation {}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn children(node: &Node) -> Vec<String> {
+        node.expect_virtual()
+            .children
+            .borrow()
+            .iter()
+            .map(|c| c.to_html(&HtmlOptions::new()))
+            .collect()
+    }
+
+    #[test]
+    fn node_kinds() {
+        let document = Document::new_virtual();
+        let element = document.create_element("p");
+        let text = document.create_text_node("text");
+        let comment = document.create_comment("comment");
+        let fragment = document.create_document_fragment();
+        let doctype = document.create_doctype("html", "", "");
+
+        assert_eq!(element.node_type(), NodeType::Element);
+        assert_eq!(text.node_type(), NodeType::Text);
+        assert_eq!(comment.node_type(), NodeType::Comment);
+        assert_eq!(fragment.node_type(), NodeType::DocumentFragment);
+        assert_eq!(doctype.node_type(), NodeType::DocumentType);
+
+        assert_eq!(element.get_node_value(), None);
+        assert_eq!(text.get_node_value().unwrap(), "text");
+        assert_eq!(comment.get_node_value().unwrap(), "comment");
+
+        comment.set_node_value("changed");
+        element.set_node_value("ignored");
+        assert_eq!(comment.get_node_value().unwrap(), "changed");
+        assert_eq!(comment.outer_html(), "<!--changed-->");
+        assert_eq!(doctype.outer_html(), "<!DOCTYPE html>");
+    }
+
+    #[test]
+    fn inserting_fragments_moves_children() {
+        let document = Document::new_virtual();
+        let parent = document.create_element("ul");
+        let existing = document.create_element("li");
+        parent.append_child(&existing);
+
+        let fragment = document.create_document_fragment();
+        for item in &["a", "b"] {
+            let li = document.create_element("li");
+            li.append_child(&document.create_text_node(item));
+            fragment.append_child(&li);
+        }
+        parent.append_child(&fragment);
+        assert_eq!(children(&parent), ["<li></li>", "<li>a</li>", "<li>b</li>"]);
+        assert!(fragment.first_child().is_none());
+        assert_eq!(parent.first_child().unwrap().next_sibling().unwrap().get_inner_text(), "a");
+
+        fragment.append_child(&document.create_comment("x"));
+        fragment.append_child(&document.create_text_node("y"));
+        parent.replace_child(&fragment, &existing);
+        assert_eq!(children(&parent), ["<!--x-->", "y", "<li>a</li>", "<li>b</li>"]);
+        assert!(existing.expect_virtual().parent().is_none());
+    }
+
+    #[test]
+    fn navigation() {
+        let document = Document::new_virtual();
+        let parent = document.create_element("ol");
+        let items = (0..3).map(|_| document.create_element("li")).collect::<Vec<_>>();
+        for item in &items {
+            parent.append_child(item);
+        }
+
+        assert_eq!(parent.first_child().as_ref(), Some(&items[0]));
+        assert_eq!(parent.last_child().as_ref(), Some(&items[2]));
+        assert_eq!(parent.child_nodes(), items);
+        assert_eq!(items[1].parent_node().as_ref(), Some(&parent));
+        assert_eq!(items[1].previous_sibling().as_ref(), Some(&items[0]));
+        assert_eq!(items[0].previous_sibling(), None);
+        assert_eq!(parent.parent_node(), None);
+
+        let nested = document.create_element("span");
+        items[1].append_child(&nested);
+        assert!(parent.contains(&parent));
+        assert!(parent.contains(&nested));
+        assert!(!nested.contains(&parent));
+        assert!(!items[0].contains(&nested));
+    }
+
+    #[test]
+    fn insert_before_moves_nodes() {
+        let document = Document::new_virtual();
+        let parent = document.create_element("ol");
+        let items = (0..4).map(|_| document.create_element("li")).collect::<Vec<_>>();
+        for item in &items[..3] {
+            parent.append_child(item);
+        }
+        let order = |parent: &Node| {
+            let children = parent.child_nodes().into_iter();
+            children.map(|c| items.iter().position(|i| *i == c).unwrap()).collect::<Vec<_>>()
+        };
+
+        parent.insert_before(&items[3], Some(&items[0]));
+        assert_eq!(order(&parent), [3, 0, 1, 2]);
+
+        // moving within the same parent
+        parent.insert_before(&items[2], Some(&items[0]));
+        assert_eq!(order(&parent), [3, 2, 0, 1]);
+        parent.insert_before(&items[3], None);
+        assert_eq!(order(&parent), [2, 0, 1, 3]);
+        parent.insert_before(&items[0], Some(&items[0]));
+        assert_eq!(order(&parent), [2, 0, 1, 3]);
+
+        // moving to another parent
+        let other = document.create_element("ul");
+        other.append_child(&items[1]);
+        assert_eq!(order(&parent), [2, 0, 3]);
+        assert_eq!(items[1].parent_node().as_ref(), Some(&other));
+
+        // replacing with a sibling
+        parent.replace_child(&items[3], &items[2]);
+        assert_eq!(order(&parent), [3, 0]);
+        assert_eq!(items[2].parent_node(), None);
+    }
+
+    #[test]
+    fn moves_are_observed_as_removal_and_insertion() {
+        use futures::{FutureExt, StreamExt};
+
+        let document = Document::new_virtual();
+        let parent = document.create_element("ol");
+        let (a, b) = (document.create_element("li"), document.create_element("li"));
+        parent.append_child(&a);
+        parent.append_child(&b);
+
+        let mut records = parent.observe_mutations();
+        parent.insert_before(&b, Some(&a));
+
+        let mut added_and_removed = vec![];
+        while let Some(Some(batch)) = records.next().now_or_never() {
+            for record in batch {
+                if let MutationRecord::Virtual(Mutation::ChildList {
+                    added_nodes,
+                    removed_nodes,
+                    ..
+                }) = record
+                {
+                    added_and_removed.push((added_nodes.len(), removed_nodes.len()));
+                }
+            }
+        }
+        assert_eq!(added_and_removed, [(0, 1), (1, 0)]);
+    }
+
+    #[test]
+    #[should_panic(expected = "can't insert a node into its own subtree")]
+    fn cannot_insert_ancestor() {
+        let document = Document::new_virtual();
+        let (parent, child) = (document.create_element("div"), document.create_element("div"));
+        parent.append_child(&child);
+        child.append_child(&parent);
+    }
+
+    #[test]
+    fn failed_inserts_leave_the_tree_unchanged() {
+        let document = Document::new_virtual();
+        let (old_parent, parent) = (document.create_element("ul"), document.create_element("ol"));
+        let (moved, stranger) = (document.create_element("li"), document.create_element("li"));
+        old_parent.append_child(&moved);
+
+        let inserted = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            parent.insert_before(&moved, Some(&stranger));
+        }));
+        assert!(inserted.is_err(), "the reference isn't a child of the parent");
+        assert_eq!(moved.parent_node().as_ref(), Some(&old_parent));
+        assert!(parent.first_child().is_none());
+    }
+
+    #[test]
+    fn template_content() {
+        let document = Document::new_virtual();
+        let template = document.create_element("template");
+        let content = template.template_content().unwrap();
+        assert_eq!(content.node_type(), NodeType::DocumentFragment);
+        assert!(document.create_element("div").template_content().is_none());
+
+        content.append_child(&document.create_element("slot"));
+        assert!(template.first_child().is_none());
+        assert_eq!(template.outer_html(), "<template><slot></slot></template>");
+    }
+}
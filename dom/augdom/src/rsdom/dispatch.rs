@@ -0,0 +1,388 @@
+//! Event listeners and dispatch for the virtual DOM, following the DOM
+//! standard's [dispatch algorithm][spec].
+//!
+//! [spec]: https://dom.spec.whatwg.org/#concept-event-dispatch
+
+use super::VirtNode;
+use crate::event::Event;
+use std::{
+    any::{type_name, Any},
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    rc::{Rc, Weak},
+};
+use tracing::error;
+
+#[cfg(feature = "webdom")]
+use web_sys::Event as BaseEvent;
+#[cfg(not(feature = "webdom"))]
+use {super::event::Event as BaseEvent, crate::Node};
+
+/// The event listeners registered on a virtual node, keyed by event name.
+#[derive(Default)]
+pub(crate) struct Listeners {
+    by_name: RefCell<HashMap<&'static str, Vec<Rc<Listener>>>>,
+}
+
+impl Listeners {
+    /// Returns the capturing or non-capturing listeners currently registered
+    /// for `name`. The returned list is a snapshot, so callbacks are free to add or
+    /// remove listeners while it is iterated.
+    fn snapshot(&self, name: &str, capture: bool) -> Vec<Rc<Listener>> {
+        self.by_name
+            .borrow()
+            .get(name)
+            .map(|listeners| listeners.iter().filter(|l| l.capture == capture).cloned().collect())
+            .unwrap_or_default()
+    }
+}
+
+type Callback = Box<dyn FnMut(&dyn Any)>;
+
+struct Listener {
+    capture: bool,
+    removed: Cell<bool>,
+    callback: RefCell<Callback>,
+}
+
+/// A listener bound to a virtual node. The listener is removed when this value
+/// is dropped.
+pub(crate) struct ListenerHandle {
+    node: Weak<VirtNode>,
+    name: &'static str,
+    listener: Rc<Listener>,
+}
+
+impl Drop for ListenerHandle {
+    fn drop(&mut self) {
+        // a dispatch in progress may still hold this listener in its snapshot
+        self.listener.removed.set(true);
+        if let Some(node) = self.node.upgrade() {
+            let mut by_name = node.listeners.by_name.borrow_mut();
+            if let Some(listeners) = by_name.get_mut(self.name) {
+                listeners.retain(|l| !Rc::ptr_eq(l, &self.listener));
+                if listeners.is_empty() {
+                    by_name.remove(self.name);
+                }
+            }
+        }
+    }
+}
+
+/// Register `callback` to be called when `Ev` is dispatched to `node` or to
+/// one of its descendants. Capturing listeners are called on the way down to
+/// the target, others on the way back up if the event bubbles.
+pub(crate) fn listen<Ev: Event>(
+    node: &Rc<VirtNode>,
+    capture: bool,
+    mut callback: impl FnMut(Ev) + 'static,
+) -> ListenerHandle {
+    let listener = Rc::new(Listener {
+        capture,
+        removed: Cell::new(false),
+        callback: RefCell::new(Box::new(move |event: &dyn Any| match event.downcast_ref::<Ev>() {
+            Some(event) => callback(event.clone()),
+            None => error!(
+                failed_cast_to = %type_name::<Ev>(),
+                "received unexpected event type in callback",
+            ),
+        })),
+    });
+
+    node.listeners.by_name.borrow_mut().entry(Ev::NAME).or_default().push(listener.clone());
+    ListenerHandle { node: Rc::downgrade(node), name: Ev::NAME, listener }
+}
+
+/// Dispatch `event` to `target`, first calling capturing listeners from the
+/// root of the tree down to the target, then the target's own listeners, and
+/// finally -- if the event bubbles -- non-capturing listeners back up to the
+/// root.
+pub(crate) fn dispatch<Ev: Event>(target: &Rc<VirtNode>, event: Ev) {
+    let base: &BaseEvent = event.as_ref();
+    set_target(base, target);
+
+    let mut ancestors = vec![];
+    let mut current = target.parent();
+    while let Some(node) = current {
+        current = node.parent();
+        ancestors.push(node);
+    }
+
+    set_phase(base, BaseEvent::CAPTURING_PHASE);
+    for node in ancestors.iter().rev() {
+        invoke(node, &event, true);
+    }
+
+    set_phase(base, BaseEvent::AT_TARGET);
+    invoke(target, &event, true);
+    invoke(target, &event, false);
+
+    if base.bubbles() {
+        set_phase(base, BaseEvent::BUBBLING_PHASE);
+        for node in &ancestors {
+            invoke(node, &event, false);
+        }
+    }
+
+    finish_dispatch(base);
+}
+
+fn invoke<Ev: Event>(node: &Rc<VirtNode>, event: &Ev, capture: bool) {
+    let base: &BaseEvent = event.as_ref();
+    if base.cancel_bubble() {
+        return;
+    }
+
+    set_current_target(base, node);
+    for listener in node.listeners.snapshot(Ev::NAME, capture) {
+        if listener.removed.get() {
+            continue;
+        }
+
+        match listener.callback.try_borrow_mut() {
+            Ok(mut callback) => callback(event),
+            Err(_) => error!(event = Ev::NAME, "listener re-entered while handling an event"),
+        }
+
+        if immediate_propagation_stopped(base) {
+            return;
+        }
+    }
+}
+
+#[cfg(not(feature = "webdom"))]
+fn set_target(event: &BaseEvent, target: &Rc<VirtNode>) {
+    event.set_target(Node::Virtual(target.clone()));
+}
+
+#[cfg(not(feature = "webdom"))]
+fn set_current_target(event: &BaseEvent, current_target: &Rc<VirtNode>) {
+    event.set_current_target(Some(Node::Virtual(current_target.clone())));
+}
+
+#[cfg(not(feature = "webdom"))]
+fn set_phase(event: &BaseEvent, phase: u16) {
+    event.set_event_phase(phase);
+}
+
+#[cfg(not(feature = "webdom"))]
+fn immediate_propagation_stopped(event: &BaseEvent) -> bool {
+    event.immediate_propagation_stopped()
+}
+
+#[cfg(not(feature = "webdom"))]
+fn finish_dispatch(event: &BaseEvent) {
+    event.finish_dispatch();
+}
+
+// web_sys events only track their targets, phase, and immediate propagation
+// when dispatched by a browser, so virtual dispatch can't observe or update them
+
+#[cfg(feature = "webdom")]
+fn set_target(_event: &BaseEvent, _target: &Rc<VirtNode>) {}
+
+#[cfg(feature = "webdom")]
+fn set_current_target(_event: &BaseEvent, _current_target: &Rc<VirtNode>) {}
+
+#[cfg(feature = "webdom")]
+fn set_phase(_event: &BaseEvent, _phase: u16) {}
+
+#[cfg(feature = "webdom")]
+fn immediate_propagation_stopped(_event: &BaseEvent) -> bool {
+    false
+}
+
+#[cfg(feature = "webdom")]
+fn finish_dispatch(event: &BaseEvent) {
+    event.set_cancel_bubble(false);
+}
+
+#[cfg(all(test, not(feature = "webdom")))]
+mod tests {
+    use super::*;
+    use crate::{
+        event::{Click, EventBuilder, EventHandle, KeyDown},
+        Dom, Node,
+    };
+
+    fn tree() -> (Rc<VirtNode>, Rc<VirtNode>, Rc<VirtNode>) {
+        let root = VirtNode::create_element("div");
+        let parent = VirtNode::create_element("p");
+        let child = VirtNode::create_element("button");
+        root.append_child(&parent);
+        parent.append_child(&child);
+        (root, parent, child)
+    }
+
+    fn click() -> Click {
+        let mut click = Click::new();
+        click.bubbles(true).cancelable(true);
+        click.build()
+    }
+
+    fn record(
+        log: &Rc<RefCell<Vec<String>>>,
+        node: &Rc<VirtNode>,
+        label: &'static str,
+        capture: bool,
+    ) -> ListenerHandle {
+        let log = log.clone();
+        listen(node, capture, move |ev: Click| {
+            log.borrow_mut().push(format!("{} {}", label, ev.event_phase()));
+        })
+    }
+
+    #[test]
+    fn capture_target_bubble_order() {
+        let (root, parent, child) = tree();
+        let log = Rc::new(RefCell::new(vec![]));
+        let _handles = [
+            record(&log, &root, "root bubble", false),
+            record(&log, &root, "root capture", true),
+            record(&log, &parent, "parent bubble", false),
+            record(&log, &parent, "parent capture", true),
+            record(&log, &child, "child bubble", false),
+            record(&log, &child, "child capture", true),
+        ];
+
+        child.dispatch(click());
+        assert_eq!(
+            *log.borrow(),
+            [
+                "root capture 1",
+                "parent capture 1",
+                "child capture 2",
+                "child bubble 2",
+                "parent bubble 3",
+                "root bubble 3",
+            ]
+        );
+    }
+
+    #[test]
+    fn targets_are_set_during_dispatch() {
+        let (root, parent, child) = tree();
+        let seen = Rc::new(RefCell::new(vec![]));
+        let _handles = [&root, &parent, &child].map(|node| {
+            let seen = seen.clone();
+            listen(node, false, move |ev: Click| {
+                seen.borrow_mut().push((ev.target().unwrap(), ev.current_target().unwrap()));
+            })
+        });
+
+        let ev = click();
+        child.dispatch(ev.clone());
+
+        let (child, parent, root) =
+            (Node::Virtual(child), Node::Virtual(parent), Node::Virtual(root));
+        assert_eq!(
+            *seen.borrow(),
+            [(child.clone(), child.clone()), (child.clone(), parent), (child.clone(), root)]
+        );
+        assert_eq!(ev.target(), Some(child));
+        assert_eq!(ev.current_target(), None);
+    }
+
+    #[test]
+    fn non_bubbling_events_stop_at_target() {
+        let (root, _parent, child) = tree();
+        let log = Rc::new(RefCell::new(vec![]));
+        let _handles =
+            [record(&log, &root, "root bubble", false), record(&log, &child, "child", false)];
+
+        child.dispatch(Click::new().build());
+        assert_eq!(*log.borrow(), ["child 2"]);
+    }
+
+    #[test]
+    fn stop_propagation_finishes_current_node() {
+        let (root, parent, child) = tree();
+        let log = Rc::new(RefCell::new(vec![]));
+        let _stop = listen(&parent, false, |ev: Click| ev.stop_propagation());
+        let _handles = [
+            record(&log, &root, "root", false),
+            record(&log, &parent, "parent", false),
+            record(&log, &child, "child", false),
+        ];
+
+        child.dispatch(click());
+        assert_eq!(*log.borrow(), ["child 2", "parent 3"]);
+    }
+
+    #[test]
+    fn stop_immediate_propagation_skips_remaining_listeners() {
+        let (_root, parent, child) = tree();
+        let log = Rc::new(RefCell::new(vec![]));
+        let _stop = listen(&child, false, |ev: Click| ev.stop_immediate_propagation());
+        let _handles =
+            [record(&log, &child, "child", false), record(&log, &parent, "parent", false)];
+
+        child.dispatch(click());
+        assert!(log.borrow().is_empty());
+    }
+
+    #[test]
+    fn prevent_default_is_visible_after_dispatch() {
+        let (_root, _parent, child) = tree();
+        let _cancel = listen(&child, false, |ev: Click| ev.prevent_default());
+
+        let ev = click();
+        child.dispatch(ev.clone());
+        assert!(ev.default_prevented());
+        assert_eq!(ev.event_phase(), BaseEvent::NONE);
+        assert!(!ev.cancel_bubble());
+
+        let mut uncancelable = Click::new();
+        uncancelable.bubbles(true);
+        let ev = uncancelable.build();
+        child.dispatch(ev.clone());
+        assert!(!ev.default_prevented());
+    }
+
+    #[test]
+    fn listeners_only_receive_their_event() {
+        let (_root, _parent, child) = tree();
+        let log = Rc::new(RefCell::new(vec![]));
+        let _click = record(&log, &child, "click", false);
+
+        child.dispatch(KeyDown::new().build());
+        assert!(log.borrow().is_empty());
+    }
+
+    #[test]
+    fn dropping_handle_removes_listener() {
+        let (_root, _parent, child) = tree();
+        let clicks = Rc::new(Cell::new(0));
+        let counter = clicks.clone();
+        let handle = EventHandle::new(&Node::Virtual(child.clone()), move |_: Click| {
+            counter.set(counter.get() + 1);
+        });
+
+        child.dispatch(click());
+        assert_eq!(clicks.get(), 1);
+
+        drop(handle);
+        child.dispatch(click());
+        assert_eq!(clicks.get(), 1);
+        assert!(child.listeners.by_name.borrow().is_empty());
+    }
+
+    #[test]
+    fn listeners_removed_during_dispatch_are_skipped() {
+        let (_root, _parent, child) = tree();
+        let clicks = Rc::new(Cell::new(0));
+        let later = Rc::new(RefCell::new(None));
+
+        let remove_later = later.clone();
+        let _first = listen(&child, false, move |_: Click| {
+            remove_later.borrow_mut().take();
+        });
+        let counter = clicks.clone();
+        *later.borrow_mut() = Some(listen(&child, false, move |_: Click| {
+            counter.set(counter.get() + 1);
+        }));
+
+        child.dispatch(click());
+        assert_eq!(clicks.get(), 0);
+    }
+}
@@ -0,0 +1,619 @@
+//! Pure-Rust emulation of the web's event interfaces, used in place of
+//! `web_sys` when the `webdom` feature is disabled.
+//!
+//! Types and methods are named after their `web_sys` counterparts so that the
+//! event types in [`crate::event`] can be declared once for both backends.
+
+use crate::Node;
+use std::{
+    cell::{Cell, RefCell},
+    convert::Infallible,
+    fmt::Debug,
+    ops::{Deref, DerefMut},
+    rc::Rc,
+};
+
+/// The base of all events, corresponding to the web's [`Event`][mdn]
+/// interface.
+///
+/// Clones share their propagation and cancellation state, so every listener
+/// invoked during a dispatch sees the effects of the others.
+///
+/// [mdn]: https://developer.mozilla.org/en-US/docs/Web/API/Event
+#[derive(Clone, Debug)]
+pub struct Event {
+    inner: Rc<EventState>,
+}
+
+#[derive(Debug)]
+struct EventState {
+    ty: String,
+    bubbles: bool,
+    cancelable: bool,
+    composed: bool,
+    default_prevented: Cell<bool>,
+    stop_propagation: Cell<bool>,
+    stop_immediate_propagation: Cell<bool>,
+    phase: Cell<u16>,
+    target: RefCell<Option<Node>>,
+    current_target: RefCell<Option<Node>>,
+}
+
+impl Event {
+    /// The event is not being dispatched.
+    pub const NONE: u16 = 0;
+    /// The event is propagating through the target's ancestors.
+    pub const CAPTURING_PHASE: u16 = 1;
+    /// The event has arrived at its target.
+    pub const AT_TARGET: u16 = 2;
+    /// The event is propagating back up through the target's ancestors.
+    pub const BUBBLING_PHASE: u16 = 3;
+
+    /// Create a new event of the given type.
+    pub fn new(ty: &str) -> Result<Self, Infallible> {
+        Self::new_with_event_init_dict(ty, &EventInit::default())
+    }
+
+    /// Create a new event of the given type with the provided options.
+    pub fn new_with_event_init_dict(ty: &str, init: &EventInit) -> Result<Self, Infallible> {
+        Ok(Self {
+            inner: Rc::new(EventState {
+                ty: ty.to_owned(),
+                bubbles: init.bubbles,
+                cancelable: init.cancelable,
+                composed: init.composed,
+                default_prevented: Cell::new(false),
+                stop_propagation: Cell::new(false),
+                stop_immediate_propagation: Cell::new(false),
+                phase: Cell::new(Self::NONE),
+                target: RefCell::new(None),
+                current_target: RefCell::new(None),
+            }),
+        })
+    }
+
+    /// The name of the event.
+    pub fn type_(&self) -> String {
+        self.inner.ty.clone()
+    }
+
+    /// Whether the event bubbles up through its target's ancestors.
+    pub fn bubbles(&self) -> bool {
+        self.inner.bubbles
+    }
+
+    /// Whether the event can be canceled with [`Event::prevent_default`].
+    pub fn cancelable(&self) -> bool {
+        self.inner.cancelable
+    }
+
+    /// Whether the event will propagate across a shadow DOM boundary.
+    pub fn composed(&self) -> bool {
+        self.inner.composed
+    }
+
+    /// The node to which the event was dispatched.
+    pub fn target(&self) -> Option<Node> {
+        self.inner.target.borrow().clone()
+    }
+
+    /// The node whose listener is currently handling the event, if the event
+    /// is being dispatched.
+    pub fn current_target(&self) -> Option<Node> {
+        self.inner.current_target.borrow().clone()
+    }
+
+    /// Which phase of dispatch the event is in, see [`Event::NONE`],
+    /// [`Event::CAPTURING_PHASE`], [`Event::AT_TARGET`], and
+    /// [`Event::BUBBLING_PHASE`].
+    pub fn event_phase(&self) -> u16 {
+        self.inner.phase.get()
+    }
+
+    /// Whether a listener has canceled the event.
+    pub fn default_prevented(&self) -> bool {
+        self.inner.default_prevented.get()
+    }
+
+    /// Cancel the event if it is cancelable.
+    pub fn prevent_default(&self) {
+        if self.inner.cancelable {
+            self.inner.default_prevented.set(true);
+        }
+    }
+
+    /// Prevent the event from reaching any nodes after the current one.
+    pub fn stop_propagation(&self) {
+        self.inner.stop_propagation.set(true);
+    }
+
+    /// Prevent the event from reaching any listeners after the current one.
+    pub fn stop_immediate_propagation(&self) {
+        self.inner.stop_propagation.set(true);
+        self.inner.stop_immediate_propagation.set(true);
+    }
+
+    /// Whether [`Event::stop_propagation`] has been called during the current
+    /// dispatch.
+    pub fn cancel_bubble(&self) -> bool {
+        self.inner.stop_propagation.get()
+    }
+
+    pub(crate) fn immediate_propagation_stopped(&self) -> bool {
+        self.inner.stop_immediate_propagation.get()
+    }
+
+    pub(crate) fn set_event_phase(&self, phase: u16) {
+        self.inner.phase.set(phase);
+    }
+
+    pub(crate) fn set_target(&self, target: Node) {
+        self.inner.target.replace(Some(target));
+    }
+
+    pub(crate) fn set_current_target(&self, current_target: Option<Node>) {
+        self.inner.current_target.replace(current_target);
+    }
+
+    /// Reset the per-dispatch state so the event can be dispatched again.
+    pub(crate) fn finish_dispatch(&self) {
+        self.inner.phase.set(Self::NONE);
+        self.inner.current_target.replace(None);
+        self.inner.stop_propagation.set(false);
+        self.inner.stop_immediate_propagation.set(false);
+    }
+}
+
+impl AsRef<Event> for Event {
+    fn as_ref(&self) -> &Event {
+        self
+    }
+}
+
+/// Options for constructing an [`Event`].
+#[derive(Clone, Debug, Default)]
+pub struct EventInit {
+    bubbles: bool,
+    cancelable: bool,
+    composed: bool,
+}
+
+impl EventInit {
+    /// Returns the default options.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether the event bubbles, defaults to `false`.
+    pub fn bubbles(&mut self, val: bool) -> &mut Self {
+        self.bubbles = val;
+        self
+    }
+
+    /// Whether the event can be canceled, defaults to `false`.
+    pub fn cancelable(&mut self, val: bool) -> &mut Self {
+        self.cancelable = val;
+        self
+    }
+
+    /// Whether the event will cross shadow DOM boundaries, defaults to `false`.
+    pub fn composed(&mut self, val: bool) -> &mut Self {
+        self.composed = val;
+        self
+    }
+}
+
+/// Converts the arguments accepted by the option setters, which match
+/// `web_sys`, into the types stored by the options.
+trait IntoField<T> {
+    fn into_field(self) -> T;
+}
+
+impl<T: ToOwned + ?Sized> IntoField<T::Owned> for &T {
+    fn into_field(self) -> T::Owned {
+        self.to_owned()
+    }
+}
+
+impl<T: ToOwned + ?Sized> IntoField<Option<T::Owned>> for Option<&T> {
+    fn into_field(self) -> Option<T::Owned> {
+        self.map(ToOwned::to_owned)
+    }
+}
+
+macro_rules! into_field_by_copy {
+    ($($ty:ty),+) => {
+        $(impl IntoField<$ty> for $ty {
+            fn into_field(self) -> $ty {
+                self
+            }
+        })+
+    };
+}
+
+into_field_by_copy!(bool, f64, i16, i32, u16, u32);
+
+/// Declares an event interface which extends another, along with its
+/// dictionary of construction options. Each field has a getter on the event and
+/// a setter on the options which accepts the same argument type as `web_sys`.
+macro_rules! event_interface {
+    (
+        $(#[$attr:meta])*
+        $name:ident : $parent:ident,
+        $init:ident : $parent_init:ident,
+        $new:ident {
+            $( $(#[$field_attr:meta])* $field:ident : $field_ty:ty = $arg_ty:ty, )*
+        }
+    ) => {
+        $(#[$attr])*
+        #[derive(Clone, Debug)]
+        pub struct $name {
+            parent: $parent,
+            $( $field: $field_ty, )*
+        }
+
+        impl $name {
+            /// Create a new event of the given type.
+            pub fn new(ty: &str) -> Result<Self, Infallible> {
+                Self::$new(ty, &$init::default())
+            }
+
+            /// Create a new event of the given type with the provided options.
+            pub fn $new(ty: &str, init: &$init) -> Result<Self, Infallible> {
+                Ok(Self {
+                    parent: <$parent as WithInit>::with_init(ty, &init.parent)?,
+                    $( $field: init.$field.clone(), )*
+                })
+            }
+
+            $(
+                $(#[$field_attr])*
+                pub fn $field(&self) -> $field_ty {
+                    self.$field.clone()
+                }
+            )*
+        }
+
+        impl WithInit for $name {
+            type Init = $init;
+            fn with_init(ty: &str, init: &$init) -> Result<Self, Infallible> {
+                Self::$new(ty, init)
+            }
+        }
+
+        impl Deref for $name {
+            type Target = $parent;
+            fn deref(&self) -> &Self::Target {
+                &self.parent
+            }
+        }
+
+        impl AsRef<Event> for $name {
+            fn as_ref(&self) -> &Event {
+                self.parent.as_ref()
+            }
+        }
+
+        #[doc = concat!("Options for constructing a [`", stringify!($name), "`].")]
+        #[derive(Clone, Debug, Default)]
+        pub struct $init {
+            parent: $parent_init,
+            $( $field: $field_ty, )*
+        }
+
+        impl $init {
+            /// Returns the default options.
+            pub fn new() -> Self {
+                Self::default()
+            }
+
+            $(
+                $(#[$field_attr])*
+                pub fn $field(&mut self, val: $arg_ty) -> &mut Self {
+                    self.$field = IntoField::into_field(val);
+                    self
+                }
+            )*
+        }
+
+        impl Deref for $init {
+            type Target = $parent_init;
+            fn deref(&self) -> &Self::Target {
+                &self.parent
+            }
+        }
+
+        impl DerefMut for $init {
+            fn deref_mut(&mut self) -> &mut Self::Target {
+                &mut self.parent
+            }
+        }
+    };
+}
+
+/// Constructs an event interface from its options, for use by the constructors
+/// of the interfaces which extend it.
+trait WithInit: Sized {
+    type Init;
+    fn with_init(ty: &str, init: &Self::Init) -> Result<Self, Infallible>;
+}
+
+impl WithInit for Event {
+    type Init = EventInit;
+    fn with_init(ty: &str, init: &EventInit) -> Result<Self, Infallible> {
+        Event::new_with_event_init_dict(ty, init)
+    }
+}
+
+event_interface! {
+    /// An event from the user interface. [MDN documentation][mdn]
+    ///
+    /// [mdn]: https://developer.mozilla.org/en-US/docs/Web/API/UIEvent
+    UiEvent: Event, UiEventInit: EventInit, new_with_event_init_dict {
+        /// Event-specific details, e.g. the click count of a mouse event.
+        detail: i32 = i32,
+    }
+}
+
+event_interface! {
+    /// An event from a pointing device. [MDN documentation][mdn]
+    ///
+    /// [mdn]: https://developer.mozilla.org/en-US/docs/Web/API/MouseEvent
+    MouseEvent: UiEvent, MouseEventInit: UiEventInit, new_with_mouse_event_init_dict {
+        /// Horizontal coordinate in the screen's space.
+        screen_x: i32 = i32,
+        /// Vertical coordinate in the screen's space.
+        screen_y: i32 = i32,
+        /// Horizontal coordinate in the viewport's space.
+        client_x: i32 = i32,
+        /// Vertical coordinate in the viewport's space.
+        client_y: i32 = i32,
+        /// Whether the control key was held.
+        ctrl_key: bool = bool,
+        /// Whether the shift key was held.
+        shift_key: bool = bool,
+        /// Whether the alt (or option) key was held.
+        alt_key: bool = bool,
+        /// Whether the meta (or command, or windows) key was held.
+        meta_key: bool = bool,
+        /// The button which changed state, `0` for the main button.
+        button: i16 = i16,
+        /// A bitmask of the buttons held, `1` for the main button.
+        buttons: u16 = u16,
+        /// The secondary target of the event, e.g. the node being left by a
+        /// `mouseenter`.
+        related_target: Option<Node> = Option<&Node>,
+    }
+}
+
+event_interface! {
+    /// The state of a pointer. [MDN documentation][mdn]
+    ///
+    /// [mdn]: https://developer.mozilla.org/en-US/docs/Web/API/PointerEvent
+    PointerEvent: MouseEvent, PointerEventInit: MouseEventInit, new_with_event_init_dict {
+        /// A unique identifier for the pointer.
+        pointer_id: i32 = i32,
+        /// The kind of device, e.g. `mouse`, `pen`, or `touch`.
+        pointer_type: String = &str,
+        /// Whether this is the primary pointer of its type.
+        is_primary: bool = bool,
+    }
+}
+
+event_interface! {
+    /// A drag and drop interaction. [MDN documentation][mdn]
+    ///
+    /// [mdn]: https://developer.mozilla.org/en-US/docs/Web/API/DragEvent
+    DragEvent: MouseEvent, DragEventInit: MouseEventInit, new_with_event_init_dict {}
+}
+
+event_interface! {
+    /// The rotation of a wheel. [MDN documentation][mdn]
+    ///
+    /// [mdn]: https://developer.mozilla.org/en-US/docs/Web/API/WheelEvent
+    WheelEvent: MouseEvent, WheelEventInit: MouseEventInit, new_with_event_init_dict {
+        /// The horizontal scroll amount.
+        delta_x: f64 = f64,
+        /// The vertical scroll amount.
+        delta_y: f64 = f64,
+        /// The scroll amount on the z-axis.
+        delta_z: f64 = f64,
+        /// The unit of the deltas: pixels, lines, or pages.
+        delta_mode: u32 = u32,
+    }
+}
+
+event_interface! {
+    /// A change in focus. [MDN documentation][mdn]
+    ///
+    /// [mdn]: https://developer.mozilla.org/en-US/docs/Web/API/FocusEvent
+    FocusEvent: UiEvent, FocusEventInit: UiEventInit, new_with_focus_event_init_dict {
+        /// The node losing focus for `focus`, or receiving it for `blur`.
+        related_target: Option<Node> = Option<&Node>,
+    }
+}
+
+event_interface! {
+    /// An interaction with the keyboard. [MDN documentation][mdn]
+    ///
+    /// [mdn]: https://developer.mozilla.org/en-US/docs/Web/API/KeyboardEvent
+    KeyboardEvent: UiEvent, KeyboardEventInit: UiEventInit, new_with_keyboard_event_init_dict {
+        /// The value of the key pressed.
+        key: String = &str,
+        /// The physical key pressed, independent of keyboard layout.
+        code: String = &str,
+        /// The location of the key on the keyboard.
+        location: u32 = u32,
+        /// Whether the control key was held.
+        ctrl_key: bool = bool,
+        /// Whether the shift key was held.
+        shift_key: bool = bool,
+        /// Whether the alt (or option) key was held.
+        alt_key: bool = bool,
+        /// Whether the meta (or command, or windows) key was held.
+        meta_key: bool = bool,
+        /// Whether the key is being held down such that it is auto-repeating.
+        repeat: bool = bool,
+        /// Whether the event is part of a composition session.
+        is_composing: bool = bool,
+    }
+}
+
+event_interface! {
+    /// A change to the contents of an editable element. [MDN documentation][mdn]
+    ///
+    /// [mdn]: https://developer.mozilla.org/en-US/docs/Web/API/InputEvent
+    InputEvent: UiEvent, InputEventInit: UiEventInit, new_with_event_init_dict {
+        /// The inserted characters, if any.
+        data: Option<String> = Option<&str>,
+        /// The kind of change, e.g. `insertText` or `deleteContentBackward`.
+        input_type: String = &str,
+        /// Whether the event is part of a composition session.
+        is_composing: bool = bool,
+    }
+}
+
+event_interface! {
+    /// Indirect text entry. [MDN documentation][mdn]
+    ///
+    /// [mdn]: https://developer.mozilla.org/en-US/docs/Web/API/CompositionEvent
+    CompositionEvent: UiEvent, CompositionEventInit: UiEventInit, new_with_event_init_dict {}
+}
+
+event_interface! {
+    /// A change in the contacts on a touch surface. [MDN documentation][mdn]
+    ///
+    /// [mdn]: https://developer.mozilla.org/en-US/docs/Web/API/TouchEvent
+    TouchEvent: UiEvent, TouchEventInit: UiEventInit, new_with_event_init_dict {}
+}
+
+event_interface! {
+    /// Progress of an underlying process. [MDN documentation][mdn]
+    ///
+    /// [mdn]: https://developer.mozilla.org/en-US/docs/Web/API/ProgressEvent
+    ProgressEvent: Event, ProgressEventInit: EventInit, new_with_event_init_dict {}
+}
+
+event_interface! {
+    /// A message received by a target object. [MDN documentation][mdn]
+    ///
+    /// [mdn]: https://developer.mozilla.org/en-US/docs/Web/API/MessageEvent
+    MessageEvent: Event, MessageEventInit: EventInit, new_with_event_init_dict {}
+}
+
+event_interface! {
+    /// A CSS animation. [MDN documentation][mdn]
+    ///
+    /// [mdn]: https://developer.mozilla.org/en-US/docs/Web/API/AnimationEvent
+    AnimationEvent: Event, AnimationEventInit: EventInit, new_with_event_init_dict {}
+}
+
+event_interface! {
+    /// A result from a speech recognition service. [MDN documentation][mdn]
+    ///
+    /// [mdn]: https://developer.mozilla.org/en-US/docs/Web/API/SpeechRecognitionEvent
+    SpeechRecognitionEvent: Event,
+    SpeechRecognitionEventInit: EventInit,
+    new_with_event_init_dict {}
+}
+
+event_interface! {
+    /// A document being loaded or unloaded. [MDN documentation][mdn]
+    ///
+    /// [mdn]: https://developer.mozilla.org/en-US/docs/Web/API/PageTransitionEvent
+    PageTransitionEvent: Event,
+    PageTransitionEventInit: EventInit,
+    new_with_event_init_dict {}
+}
+
+event_interface! {
+    /// A gamepad being connected or disconnected. [MDN documentation][mdn]
+    ///
+    /// [mdn]: https://developer.mozilla.org/en-US/docs/Web/API/GamepadEvent
+    GamepadEvent: Event, GamepadEventInit: EventInit, new_with_event_init_dict {}
+}
+
+event_interface! {
+    /// A change to a storage area. [MDN documentation][mdn]
+    ///
+    /// [mdn]: https://developer.mozilla.org/en-US/docs/Web/API/StorageEvent
+    StorageEvent: Event, StorageEventInit: EventInit, new_with_event_init_dict {}
+}
+
+event_interface! {
+    /// A change to the fragment identifier of the URL. [MDN documentation][mdn]
+    ///
+    /// [mdn]: https://developer.mozilla.org/en-US/docs/Web/API/HashChangeEvent
+    HashChangeEvent: Event, HashChangeEventInit: EventInit, new_with_event_init_dict {}
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        create_virtual_element,
+        event::{Blur, Click, Event as _, EventBuilder, Input, KeyDown},
+    };
+
+    #[test]
+    fn keyboard_fields() {
+        let mut keydown = KeyDown::new();
+        keydown.key("a").code("KeyA").shift_key(true).repeat(true).bubbles(true);
+        let keydown = keydown.build();
+
+        assert_eq!(keydown.type_(), "keydown");
+        assert_eq!(keydown.key(), "a");
+        assert_eq!(keydown.code(), "KeyA");
+        assert!(keydown.shift_key());
+        assert!(!keydown.ctrl_key());
+        assert!(keydown.repeat());
+        assert!(keydown.bubbles());
+        assert!(!keydown.cancelable());
+    }
+
+    #[test]
+    fn mouse_fields() {
+        let mut click = Click::new();
+        click.client_x(12).client_y(34).button(2).buttons(2).detail(1).cancelable(true);
+        let click = click.build();
+
+        assert_eq!((click.client_x(), click.client_y()), (12, 34));
+        assert_eq!((click.button(), click.buttons()), (2, 2));
+        assert_eq!(click.detail(), 1);
+        assert_eq!(click.related_target(), None);
+        assert!(click.cancelable());
+    }
+
+    #[test]
+    fn input_fields() {
+        let mut input = Input::new();
+        input.data(Some("b")).input_type("insertText");
+        let input = input.build();
+
+        assert_eq!(input.data().as_deref(), Some("b"));
+        assert_eq!(input.input_type(), "insertText");
+        assert!(!input.is_composing());
+    }
+
+    #[test]
+    fn focus_fields() {
+        let next = create_virtual_element("input");
+        let mut blur = Blur::new();
+        blur.related_target(Some(&next));
+
+        assert_eq!(blur.build().related_target(), Some(next));
+    }
+
+    #[test]
+    fn clones_share_state() {
+        let mut click = Click::new();
+        click.cancelable(true);
+        let click = click.build();
+        let other = click.clone();
+
+        other.prevent_default();
+        other.stop_propagation();
+        assert!(click.default_prevented());
+        assert!(click.cancel_bubble());
+        assert_eq!(click.target(), None);
+    }
+}
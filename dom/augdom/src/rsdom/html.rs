@@ -0,0 +1,347 @@
+//! HTML serialization for the virtual DOM, following the spec's [fragment
+//! serializing algorithm][spec] so that output can be parsed by browsers.
+//!
+//! [spec]: https://html.spec.whatwg.org/multipage/parsing.html#serialising-html-fragments
+
+use super::{VirtData, VirtNode};
+use std::io::{Result as IoResult, Write};
+
+/// Elements which never have children or end tags.
+pub(super) const VOID: &[&str] = &[
+    "area", "base", "basefont", "bgsound", "br", "col", "embed", "frame", "hr", "img", "input",
+    "keygen", "link", "meta", "param", "source", "track", "wbr",
+];
+
+/// Elements whose text contents are written without escaping.
+pub(super) const RAW_TEXT: &[&str] =
+    &["iframe", "noembed", "noframes", "noscript", "plaintext", "script", "style", "xmp"];
+
+/// Elements whose whitespace is significant.
+const PREFORMATTED: &[&str] = &["listing", "pre", "textarea"];
+
+/// Attributes whose presence alone determines their value.
+const BOOLEAN_ATTRIBUTES: &[&str] = &[
+    "allowfullscreen",
+    "async",
+    "autofocus",
+    "autoplay",
+    "checked",
+    "controls",
+    "default",
+    "defer",
+    "disabled",
+    "formnovalidate",
+    "hidden",
+    "inert",
+    "ismap",
+    "itemscope",
+    "loop",
+    "multiple",
+    "muted",
+    "nomodule",
+    "novalidate",
+    "open",
+    "playsinline",
+    "readonly",
+    "required",
+    "reversed",
+    "selected",
+];
+
+/// Options for serializing virtual nodes as HTML.
+#[derive(Clone, Debug, Default)]
+pub struct HtmlOptions {
+    minify: bool,
+}
+
+impl HtmlOptions {
+    /// Returns the default options, which produce the same output as a
+    /// browser's `outerHTML`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Collapse runs of whitespace in text which isn't preformatted and omit
+    /// the values of empty and boolean attributes. Defaults to `false`.
+    pub fn minify(&mut self, val: bool) -> &mut Self {
+        self.minify = val;
+        self
+    }
+}
+
+impl VirtNode {
+    /// Write this node and its descendants to `writer` as HTML.
+    pub fn write_html(&self, writer: &mut impl Write, options: &HtmlOptions) -> IoResult<()> {
+        Serializer { writer, options }.node(self, None, false)
+    }
+
+    /// Returns this node and its descendants serialized as HTML.
+    pub fn to_html(&self, options: &HtmlOptions) -> String {
+        let mut buf = Vec::new();
+        self.write_html(&mut buf, options).expect("writing to a Vec can't fail");
+        String::from_utf8(buf).expect("html is always valid utf-8")
+    }
+}
+
+/// Write a document with the provided `head` and `body` to `writer` as HTML.
+pub(crate) fn write_document(
+    writer: &mut impl Write,
+    head: &VirtNode,
+    body: &VirtNode,
+    options: &HtmlOptions,
+) -> IoResult<()> {
+    writer.write_all(b"<!DOCTYPE html><html>")?;
+    head.write_html(writer, options)?;
+    body.write_html(writer, options)?;
+    writer.write_all(b"</html>")
+}
+
+struct Serializer<'a, W> {
+    writer: &'a mut W,
+    options: &'a HtmlOptions,
+}
+
+impl<W: Write> Serializer<'_, W> {
+    /// Write `node`, whose parent has the tag `parent`. Whitespace is kept in
+    /// text which is `preformatted` because it's inside a preformatted
+    /// element at any depth.
+    fn node(&mut self, node: &VirtNode, parent: Option<&str>, preformatted: bool) -> IoResult<()> {
+        match &node.data {
+            VirtData::Elem { tag, attrs, content } => {
+                write!(self.writer, "<{}", tag)?;
+                for (name, value) in attrs.borrow().iter() {
+                    self.attribute(name, value)?;
+                }
+                self.writer.write_all(b">")?;
+
+                if VOID.contains(&tag.as_str()) {
+                    return Ok(());
+                }
+
+                // a template's contents are serialized in place of its children
+                let children = content.as_deref().unwrap_or(node).children.borrow();
+                let preformatted = preformatted || PREFORMATTED.contains(&tag.as_str());
+                for child in children.iter() {
+                    self.node(child, Some(tag), preformatted)?;
+                }
+                write!(self.writer, "</{}>", tag)
+            }
+            VirtData::Comment(comment) => write!(self.writer, "<!--{}-->", comment.borrow()),
+            VirtData::DocumentFragment => {
+                for child in node.children.borrow().iter() {
+                    self.node(child, parent, preformatted)?;
+                }
+                Ok(())
+            }
+            VirtData::Doctype { name, .. } => write!(self.writer, "<!DOCTYPE {}>", name),
+            VirtData::Text(text) => {
+                let text = text.borrow();
+                match parent {
+                    Some(p) if RAW_TEXT.contains(&p) => self.writer.write_all(text.as_bytes()),
+                    _ if preformatted => self.escaped(&text, false),
+                    _ if self.options.minify => self.escaped(&collapse_whitespace(&text), false),
+                    _ => self.escaped(&text, false),
+                }
+            }
+        }
+    }
+
+    fn attribute(&mut self, name: &str, value: &str) -> IoResult<()> {
+        write!(self.writer, " {}", name)?;
+        let omit_value = value.is_empty() || (BOOLEAN_ATTRIBUTES.contains(&name) && value == name);
+        if self.options.minify && omit_value {
+            return Ok(());
+        }
+
+        self.writer.write_all(b"=\"")?;
+        self.escaped(value, true)?;
+        self.writer.write_all(b"\"")
+    }
+
+    /// Escape `text` for a text node or, if `in_attribute`, a quoted attribute
+    /// value.
+    fn escaped(&mut self, text: &str, in_attribute: bool) -> IoResult<()> {
+        let mut last = 0;
+        for (i, c) in text.char_indices() {
+            let escaped = match c {
+                '&' => "&amp;",
+                '\u{a0}' => "&nbsp;",
+                '<' => "&lt;",
+                '>' => "&gt;",
+                '"' if in_attribute => "&quot;",
+                _ => continue,
+            };
+            self.writer.write_all(&text.as_bytes()[last..i])?;
+            self.writer.write_all(escaped.as_bytes())?;
+            last = i + c.len_utf8();
+        }
+        self.writer.write_all(&text.as_bytes()[last..])
+    }
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    let mut collapsed = String::with_capacity(text.len());
+    let mut in_whitespace = false;
+    for c in text.chars() {
+        if matches!(c, ' ' | '\t' | '\n' | '\r' | '\x0C') {
+            if !in_whitespace {
+                collapsed.push(' ');
+            }
+            in_whitespace = true;
+        } else {
+            collapsed.push(c);
+            in_whitespace = false;
+        }
+    }
+    collapsed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Document, Dom};
+    use std::rc::Rc;
+
+    fn elem(tag: &str, attrs: &[(&str, &str)], children: Vec<Rc<VirtNode>>) -> Rc<VirtNode> {
+        let elem = VirtNode::create_element(tag);
+        for (name, value) in attrs {
+            elem.set_attribute(name, value);
+        }
+        for child in children {
+            elem.append_child(&child);
+        }
+        elem
+    }
+
+    fn text(contents: &str) -> Rc<VirtNode> {
+        VirtNode::create_text_node(contents)
+    }
+
+    fn minified() -> HtmlOptions {
+        let mut options = HtmlOptions::new();
+        options.minify(true);
+        options
+    }
+
+    #[test]
+    fn void_elements() {
+        let form = elem(
+            "form",
+            &[],
+            vec![
+                elem("input", &[("id", "username")], vec![]),
+                elem("br", &[], vec![]),
+                elem("img", &[("src", "a.png"), ("alt", "")], vec![]),
+            ],
+        );
+        assert_eq!(
+            form.to_html(&HtmlOptions::new()),
+            r#"<form><input id="username"><br><img src="a.png" alt=""></form>"#
+        );
+    }
+
+    #[test]
+    fn escaping() {
+        let p = elem(
+            "p",
+            &[("title", "\"quoted\" & <tagged>")],
+            vec![text("1 < 2 && 3 > 2\u{a0}\"ok\"")],
+        );
+        assert_eq!(
+            p.to_html(&HtmlOptions::new()),
+            "<p title=\"&quot;quoted&quot; &amp; &lt;tagged&gt;\">\
+             1 &lt; 2 &amp;&amp; 3 &gt; 2&nbsp;\"ok\"</p>"
+        );
+    }
+
+    #[test]
+    fn raw_text() {
+        let script = elem("script", &[], vec![text("if (a < b && c) { x = \"</p>\"; }")]);
+        let style = elem("style", &[], vec![text("a > b { content: \"&\" }")]);
+        let div = elem("div", &[], vec![script, style]);
+        assert_eq!(
+            div.to_html(&minified()),
+            "<div><script>if (a < b && c) { x = \"</p>\"; }</script>\
+             <style>a > b { content: \"&\" }</style></div>"
+        );
+    }
+
+    #[test]
+    fn boolean_attributes() {
+        let input = elem(
+            "input",
+            &[("type", "checkbox"), ("checked", ""), ("disabled", "disabled"), ("value", "")],
+            vec![],
+        );
+        assert_eq!(
+            input.to_html(&HtmlOptions::new()),
+            r#"<input type="checkbox" checked="" disabled="disabled" value="">"#
+        );
+        assert_eq!(input.to_html(&minified()), r#"<input type="checkbox" checked disabled value>"#);
+    }
+
+    #[test]
+    fn minify_collapses_whitespace() {
+        let div = elem(
+            "div",
+            &[],
+            vec![
+                text("\n  hello\n\n  world  "),
+                elem("pre", &[], vec![text("  keep\n  this")]),
+                elem("textarea", &[], vec![text("  and\n  this")]),
+            ],
+        );
+        assert_eq!(
+            div.to_html(&minified()),
+            "<div> hello world <pre>  keep\n  this</pre><textarea>  and\n  this</textarea></div>"
+        );
+        assert_eq!(
+            div.to_html(&HtmlOptions::new()),
+            "<div>\n  hello\n\n  world  <pre>  keep\n  this</pre>\
+             <textarea>  and\n  this</textarea></div>"
+        );
+    }
+
+    #[test]
+    fn minify_keeps_whitespace_nested_in_preformatted() {
+        let code = elem("code", &[], vec![text("  a\n    b")]);
+        let pre = elem("pre", &[], vec![elem("span", &[], vec![code])]);
+        let div = elem("div", &[], vec![pre, elem("p", &[], vec![text("  c\n  d")])]);
+        assert_eq!(
+            div.to_html(&minified()),
+            "<div><pre><span><code>  a\n    b</code></span></pre><p> c d</p></div>"
+        );
+    }
+
+    #[test]
+    fn comments_doctypes_and_fragments() {
+        let doctype = VirtNode::create_doctype("html", "", "");
+        assert_eq!(doctype.to_html(&HtmlOptions::new()), "<!DOCTYPE html>");
+
+        let fragment = VirtNode::create_document_fragment();
+        fragment.append_child(&VirtNode::create_comment(" a <b> & c "));
+        fragment.append_child(&text("d"));
+        assert_eq!(fragment.to_html(&HtmlOptions::new()), "<!-- a <b> & c -->d");
+
+        let template = elem("template", &[], vec![]);
+        template.content().unwrap().append_child(&elem("p", &[], vec![text("hi")]));
+        assert_eq!(template.to_html(&HtmlOptions::new()), "<template><p>hi</p></template>");
+    }
+
+    #[test]
+    fn document() {
+        let document = Document::new_virtual();
+        let title = document.create_element("title");
+        title.append_child(&document.create_text_node("moxie"));
+        document.head().append_child(&title);
+        let main = document.create_element("main");
+        main.append_child(&document.create_text_node("hello"));
+        document.body().append_child(&main);
+
+        assert_eq!(
+            document.to_html(&HtmlOptions::new()),
+            "<!DOCTYPE html><html><head><title>moxie</title></head>\
+             <body><main>hello</main></body></html>"
+        );
+    }
+}
@@ -0,0 +1,317 @@
+//! An approximation of [`innerText`][spec] for the virtual DOM, which has no
+//! stylesheets and so assumes the default display of each element.
+//!
+//! [spec]: https://html.spec.whatwg.org/multipage/dom.html#the-innertext-idl-attribute
+
+use super::{VirtData, VirtNode};
+
+/// Elements which are `display: none` by default.
+const HIDDEN: &[&str] = &[
+    "area", "base", "basefont", "datalist", "head", "link", "meta", "noembed", "noframes", "param",
+    "rp", "script", "style", "template", "title",
+];
+
+/// Elements which are `display: block` (or similar) by default.
+const BLOCK: &[&str] = &[
+    "address",
+    "article",
+    "aside",
+    "blockquote",
+    "body",
+    "caption",
+    "center",
+    "dd",
+    "details",
+    "dialog",
+    "dir",
+    "div",
+    "dl",
+    "dt",
+    "fieldset",
+    "figcaption",
+    "figure",
+    "footer",
+    "form",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "header",
+    "hgroup",
+    "hr",
+    "html",
+    "legend",
+    "li",
+    "listing",
+    "main",
+    "menu",
+    "nav",
+    "ol",
+    "plaintext",
+    "pre",
+    "section",
+    "summary",
+    "table",
+    "ul",
+    "xmp",
+];
+
+/// Elements which preserve whitespace by default.
+const PREFORMATTED: &[&str] = &["listing", "plaintext", "pre", "textarea", "xmp"];
+
+/// Returns the text `node` would render, following the spec's [rendered text
+/// collection steps][spec] with each element's default display.
+///
+/// [spec]: https://html.spec.whatwg.org/multipage/dom.html#rendered-text-collection-steps
+pub(crate) fn inner_text(node: &VirtNode) -> String {
+    match &node.data {
+        VirtData::Elem { .. } if !is_rendered(node) => text_content(node),
+        VirtData::Elem { .. } | VirtData::DocumentFragment => {
+            let mut text = Collector::default();
+            for child in node.children.borrow().iter() {
+                text.collect(child, false);
+            }
+            text.out
+        }
+        // other nodes don't have innerText on the web
+        _ => String::new(),
+    }
+}
+
+fn is_rendered(node: &VirtNode) -> bool {
+    match node.tag() {
+        Some(tag) => !HIDDEN.contains(&tag) && node.attribute("hidden").is_none(),
+        None => true,
+    }
+}
+
+fn text_content(node: &VirtNode) -> String {
+    match &node.data {
+        VirtData::Text(text) => text.borrow().clone(),
+        VirtData::Elem { .. } | VirtData::DocumentFragment => {
+            node.children.borrow().iter().map(|c| text_content(c)).collect()
+        }
+        VirtData::Comment(_) | VirtData::Doctype { .. } => String::new(),
+    }
+}
+
+/// Accumulates rendered text, collapsing whitespace and line breaks.
+#[derive(Default)]
+struct Collector {
+    out: String,
+    /// Collapsed whitespace to emit before the next visible character, unless
+    /// a line ends first.
+    pending_space: bool,
+    /// The largest number of line breaks required before the next visible
+    /// character.
+    pending_breaks: usize,
+}
+
+impl Collector {
+    fn collect(&mut self, node: &VirtNode, preformatted: bool) {
+        let tag = match &node.data {
+            VirtData::Text(text) => {
+                let text = text.borrow();
+                if preformatted {
+                    self.preformatted(&text);
+                } else {
+                    self.collapsible(&text);
+                }
+                return;
+            }
+            VirtData::Elem { tag, .. } => tag.as_str(),
+            _ => return,
+        };
+
+        if !is_rendered(node) {
+            return;
+        }
+
+        let breaks = match tag {
+            "p" => 2,
+            _ if BLOCK.contains(&tag) => 1,
+            _ => 0,
+        };
+        self.require_breaks(breaks);
+
+        if tag == "br" {
+            self.line_break();
+        }
+
+        let preformatted = preformatted || PREFORMATTED.contains(&tag);
+        for child in node.children.borrow().iter() {
+            self.collect(child, preformatted);
+        }
+
+        match tag {
+            "td" | "th" if next_element_sibling_matches(node, &["td", "th"]) => self.tab(),
+            "tr" if next_element_sibling_matches(node, &["tr"]) => self.require_breaks(1),
+            _ => (),
+        }
+        self.require_breaks(breaks);
+    }
+
+    fn collapsible(&mut self, text: &str) {
+        for c in text.chars() {
+            if is_collapsible_whitespace(c) {
+                // whitespace at the start of a line is removed
+                self.pending_space |= !self.out.is_empty() && !self.out.ends_with('\n');
+            } else {
+                self.visible(c);
+            }
+        }
+    }
+
+    fn preformatted(&mut self, text: &str) {
+        for c in text.chars() {
+            self.visible(c);
+        }
+    }
+
+    fn visible(&mut self, c: char) {
+        if self.pending_breaks > 0 {
+            if !self.out.is_empty() {
+                self.out.push_str(&"\n".repeat(self.pending_breaks));
+            }
+            self.pending_breaks = 0;
+        } else if self.pending_space {
+            self.out.push(' ');
+        }
+        self.pending_space = false;
+        self.out.push(c);
+    }
+
+    fn require_breaks(&mut self, count: usize) {
+        if count > 0 {
+            // whitespace at the end of a line is removed
+            self.pending_space = false;
+            self.pending_breaks = self.pending_breaks.max(count);
+        }
+    }
+
+    fn line_break(&mut self) {
+        self.pending_space = false;
+        self.visible('\n');
+    }
+
+    fn tab(&mut self) {
+        self.visible('\t');
+    }
+}
+
+fn is_collapsible_whitespace(c: char) -> bool {
+    matches!(c, ' ' | '\t' | '\n' | '\r' | '\x0C')
+}
+
+fn next_element_sibling_matches(node: &VirtNode, tags: &[&str]) -> bool {
+    let parent = match node.parent() {
+        Some(p) => p,
+        None => return false,
+    };
+    let children = parent.children.borrow();
+    let mut after = children.iter().skip_while(|c| !std::ptr::eq(&***c, node)).skip(1);
+    after.find(|c| c.is_element()).and_then(|c| c.tag().map(|t| tags.contains(&t))).unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{rsdom::VirtNode, Dom};
+    use std::rc::Rc;
+
+    fn elem(tag: &str, children: Vec<Rc<VirtNode>>) -> Rc<VirtNode> {
+        let elem = VirtNode::create_element(tag);
+        for child in children {
+            elem.append_child(&child);
+        }
+        elem
+    }
+
+    fn text(contents: &str) -> Rc<VirtNode> {
+        VirtNode::create_text_node(contents)
+    }
+
+    #[test]
+    fn collapses_whitespace() {
+        let div = elem(
+            "div",
+            vec![text("  hello \n\t "), elem("b", vec![text(" big ")]), text("  world  ")],
+        );
+        assert_eq!(div.get_inner_text(), "hello big world");
+    }
+
+    #[test]
+    fn blocks_and_paragraphs() {
+        let div = elem(
+            "div",
+            vec![
+                text("intro"),
+                elem("p", vec![text("first")]),
+                elem("p", vec![text("second")]),
+                elem("div", vec![elem("div", vec![text("nested")])]),
+                elem("span", vec![text("inline")]),
+                text(" tail "),
+            ],
+        );
+        assert_eq!(div.get_inner_text(), "intro\n\nfirst\n\nsecond\n\nnested\ninline tail");
+    }
+
+    #[test]
+    fn line_breaks() {
+        let div = elem(
+            "div",
+            vec![
+                text("one "),
+                elem("br", vec![]),
+                text(" two"),
+                elem("br", vec![]),
+                elem("br", vec![]),
+                text("three"),
+            ],
+        );
+        assert_eq!(div.get_inner_text(), "one\ntwo\n\nthree");
+    }
+
+    #[test]
+    fn hidden_elements() {
+        let hidden = elem("span", vec![text("secret")]);
+        hidden.set_attribute("hidden", "");
+        let div = elem(
+            "div",
+            vec![
+                text("shown "),
+                hidden,
+                elem("script", vec![text("let x = 1;")]),
+                elem("style", vec![text("p {}")]),
+                text("text"),
+            ],
+        );
+        assert_eq!(div.get_inner_text(), "shown text");
+
+        // unrendered elements return their text content
+        let hidden = elem("div", vec![text("  as  is ")]);
+        hidden.set_attribute("hidden", "");
+        assert_eq!(hidden.get_inner_text(), "  as  is ");
+    }
+
+    #[test]
+    fn preformatted() {
+        let div =
+            elem("div", vec![text("a  b"), elem("pre", vec![text("  c\n  d")]), text("e  f")]);
+        assert_eq!(div.get_inner_text(), "a b\n  c\n  d\ne f");
+    }
+
+    #[test]
+    fn tables() {
+        let row =
+            |cells: &[&str]| elem("tr", cells.iter().map(|c| elem("td", vec![text(c)])).collect());
+        let table = elem("table", vec![elem("tbody", vec![row(&["a", "b"]), row(&["c", "d"])])]);
+        assert_eq!(table.get_inner_text(), "a\tb\nc\td");
+    }
+
+    #[test]
+    fn text_nodes_are_empty() {
+        assert_eq!(text("hello").get_inner_text(), "");
+    }
+}
@@ -0,0 +1,395 @@
+//! Mutation observation for the virtual DOM, modeled on the web's
+//! [`MutationObserver`][mdn].
+//!
+//! [mdn]: https://developer.mozilla.org/en-US/docs/Web/API/MutationObserver
+
+use super::VirtNode;
+use futures::channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender};
+use std::rc::Rc;
+
+/// A change to the virtual DOM tree, corresponding to the web's
+/// [`MutationRecord`][mdn].
+///
+/// [mdn]: https://developer.mozilla.org/en-US/docs/Web/API/MutationRecord
+#[derive(Clone, Debug)]
+pub enum Mutation {
+    /// Children were added to or removed from `target`.
+    ChildList {
+        /// The node whose children changed.
+        target: Rc<VirtNode>,
+        /// The nodes which were added.
+        added_nodes: Vec<Rc<VirtNode>>,
+        /// The nodes which were removed.
+        removed_nodes: Vec<Rc<VirtNode>>,
+        /// The sibling before the added or removed nodes.
+        previous_sibling: Option<Rc<VirtNode>>,
+        /// The sibling after the added or removed nodes.
+        next_sibling: Option<Rc<VirtNode>>,
+    },
+    /// An attribute of `target` was set or removed.
+    Attributes {
+        /// The element whose attribute changed.
+        target: Rc<VirtNode>,
+        /// The name of the attribute.
+        attribute_name: String,
+        /// The attribute's previous value, if it was present and the observer
+        /// asked for [`MutationObserverInit::attribute_old_value`].
+        old_value: Option<String>,
+    },
+    /// The contents of the text node `target` changed.
+    CharacterData {
+        /// The text node which changed.
+        target: Rc<VirtNode>,
+        /// The node's previous contents, if the observer asked for
+        /// [`MutationObserverInit::character_data_old_value`].
+        old_value: Option<String>,
+    },
+}
+
+impl Mutation {
+    /// The kind of mutation, named as on the web: `childList`, `attributes`, or
+    /// `characterData`.
+    pub fn type_(&self) -> &'static str {
+        match self {
+            Mutation::ChildList { .. } => "childList",
+            Mutation::Attributes { .. } => "attributes",
+            Mutation::CharacterData { .. } => "characterData",
+        }
+    }
+
+    /// The node affected by the mutation.
+    pub fn target(&self) -> &Rc<VirtNode> {
+        match self {
+            Mutation::ChildList { target, .. }
+            | Mutation::Attributes { target, .. }
+            | Mutation::CharacterData { target, .. } => target,
+        }
+    }
+
+    fn without_old_value(mut self) -> Self {
+        match &mut self {
+            Mutation::Attributes { old_value, .. } | Mutation::CharacterData { old_value, .. } => {
+                *old_value = None;
+            }
+            Mutation::ChildList { .. } => (),
+        }
+        self
+    }
+}
+
+/// Options for which mutations an observer receives, corresponding to the
+/// web's [`MutationObserverInit`][mdn].
+///
+/// [mdn]: https://developer.mozilla.org/en-US/docs/Web/API/MutationObserverInit
+#[derive(Clone, Debug, Default)]
+pub struct MutationObserverInit {
+    child_list: bool,
+    attributes: Option<bool>,
+    character_data: Option<bool>,
+    subtree: bool,
+    attribute_old_value: bool,
+    character_data_old_value: bool,
+    attribute_filter: Option<Vec<String>>,
+}
+
+impl MutationObserverInit {
+    /// Returns the default options, which observe nothing.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Observe the addition and removal of children.
+    pub fn child_list(&mut self, val: bool) -> &mut Self {
+        self.child_list = val;
+        self
+    }
+
+    /// Observe changes to attributes. Implied by `attribute_old_value` and
+    /// `attribute_filter` unless explicitly disabled.
+    pub fn attributes(&mut self, val: bool) -> &mut Self {
+        self.attributes = Some(val);
+        self
+    }
+
+    /// Observe changes to the contents of text nodes. Implied by
+    /// `character_data_old_value` unless explicitly disabled.
+    pub fn character_data(&mut self, val: bool) -> &mut Self {
+        self.character_data = Some(val);
+        self
+    }
+
+    /// Observe the target's descendants in addition to the target.
+    pub fn subtree(&mut self, val: bool) -> &mut Self {
+        self.subtree = val;
+        self
+    }
+
+    /// Record the previous values of changed attributes.
+    pub fn attribute_old_value(&mut self, val: bool) -> &mut Self {
+        self.attribute_old_value = val;
+        self
+    }
+
+    /// Record the previous contents of changed text nodes.
+    pub fn character_data_old_value(&mut self, val: bool) -> &mut Self {
+        self.character_data_old_value = val;
+        self
+    }
+
+    /// Only observe changes to the named attributes.
+    pub fn attribute_filter(&mut self, names: &[&str]) -> &mut Self {
+        self.attribute_filter = Some(names.iter().map(|&n| n.to_owned()).collect());
+        self
+    }
+
+    fn observes_attributes(&self) -> bool {
+        self.attributes.unwrap_or(self.attribute_old_value || self.attribute_filter.is_some())
+    }
+
+    fn observes_character_data(&self) -> bool {
+        self.character_data.unwrap_or(self.character_data_old_value)
+    }
+
+    /// Returns the mutation as this observer should receive it, if at all.
+    fn filter(&self, mutation: &Mutation, at_target: bool) -> Option<Mutation> {
+        if !at_target && !self.subtree {
+            return None;
+        }
+
+        let (observed, old_value) = match mutation {
+            Mutation::ChildList { .. } => (self.child_list, false),
+            Mutation::Attributes { attribute_name, .. } => {
+                let filtered = match &self.attribute_filter {
+                    Some(names) => names.contains(attribute_name),
+                    None => true,
+                };
+                (self.observes_attributes() && filtered, self.attribute_old_value)
+            }
+            Mutation::CharacterData { .. } => {
+                (self.observes_character_data(), self.character_data_old_value)
+            }
+        };
+
+        if !observed {
+            None
+        } else if old_value {
+            Some(mutation.clone())
+        } else {
+            Some(mutation.clone().without_old_value())
+        }
+    }
+}
+
+/// An observer registered on a virtual node.
+pub(crate) struct Registration {
+    options: MutationObserverInit,
+    sender: UnboundedSender<Vec<Mutation>>,
+}
+
+impl VirtNode {
+    /// Returns a stream of the mutations to this node, and its descendants if
+    /// `options` includes [`MutationObserverInit::subtree`]. Each mutation is
+    /// delivered as soon as it is made. Observation stops when the stream is
+    /// dropped.
+    ///
+    /// # Panics
+    ///
+    /// If `options` doesn't observe any kind of mutation.
+    pub fn observe_mutations_with_options(
+        &self,
+        options: &MutationObserverInit,
+    ) -> UnboundedReceiver<Vec<Mutation>> {
+        assert!(
+            options.child_list
+                || options.observes_attributes()
+                || options.observes_character_data(),
+            "one of child_list, attributes, or character_data must be observed",
+        );
+        if options.attributes == Some(false)
+            && (options.attribute_old_value || options.attribute_filter.is_some())
+        {
+            panic!("attribute_old_value and attribute_filter require observing attributes");
+        }
+        if options.character_data == Some(false) && options.character_data_old_value {
+            panic!("character_data_old_value requires observing character_data");
+        }
+
+        let (sender, records) = unbounded();
+        self.observers.borrow_mut().push(Registration { options: options.clone(), sender });
+        records
+    }
+
+    /// Deliver `mutation` to the observers of its target and of the target's
+    /// ancestors.
+    pub(crate) fn queue_mutation(self: &Rc<Self>, mutation: Mutation) {
+        let mut current = Some(self.clone());
+        while let Some(node) = current {
+            let at_target = Rc::ptr_eq(&node, self);
+            node.observers.borrow_mut().retain(|registration| {
+                match registration.options.filter(&mutation, at_target) {
+                    Some(record) => registration.sender.unbounded_send(vec![record]).is_ok(),
+                    None => !registration.sender.is_closed(),
+                }
+            });
+            current = node.parent();
+        }
+    }
+}
+
+pub(crate) fn all_mutations() -> MutationObserverInit {
+    let mut options = MutationObserverInit::new();
+    options.attributes(true);
+    options.character_data(true);
+    options.child_list(true);
+    options.subtree(true);
+    options
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Dom;
+    use futures::{FutureExt, StreamExt};
+
+    fn drain(records: &mut UnboundedReceiver<Vec<Mutation>>) -> Vec<Mutation> {
+        let mut drained = vec![];
+        while let Some(Some(batch)) = records.next().now_or_never() {
+            drained.extend(batch);
+        }
+        drained
+    }
+
+    #[test]
+    fn child_list() {
+        let root = VirtNode::create_element("ul");
+        let mut records = root.observe_mutations();
+
+        let first = VirtNode::create_element("li");
+        let second = VirtNode::create_element("li");
+        root.append_child(&first);
+        root.append_child(&second);
+        root.remove_child(&first);
+
+        let mutations = drain(&mut records);
+        assert_eq!(mutations.len(), 3);
+        match &mutations[1] {
+            Mutation::ChildList { target, added_nodes, previous_sibling, next_sibling, .. } => {
+                assert!(Rc::ptr_eq(target, &root));
+                assert!(Rc::ptr_eq(&added_nodes[0], &second));
+                assert!(Rc::ptr_eq(previous_sibling.as_ref().unwrap(), &first));
+                assert!(next_sibling.is_none());
+            }
+            other => panic!("unexpected mutation {:?}", other),
+        }
+        match &mutations[2] {
+            Mutation::ChildList { removed_nodes, previous_sibling, next_sibling, .. } => {
+                assert!(Rc::ptr_eq(&removed_nodes[0], &first));
+                assert!(previous_sibling.is_none());
+                assert!(Rc::ptr_eq(next_sibling.as_ref().unwrap(), &second));
+            }
+            other => panic!("unexpected mutation {:?}", other),
+        }
+    }
+
+    #[test]
+    fn attributes_and_old_values() {
+        let root = VirtNode::create_element("div");
+        let mut with_old = root
+            .observe_mutations_with_options(MutationObserverInit::new().attribute_old_value(true));
+        let mut without_old =
+            root.observe_mutations_with_options(MutationObserverInit::new().attributes(true));
+
+        root.set_attribute("class", "a");
+        root.set_attribute("class", "b");
+        root.remove_attribute("class");
+
+        let old_values = |mutations: Vec<Mutation>| {
+            mutations
+                .into_iter()
+                .map(|m| match m {
+                    Mutation::Attributes { attribute_name, old_value, .. } => {
+                        assert_eq!(attribute_name, "class");
+                        old_value
+                    }
+                    other => panic!("unexpected mutation {:?}", other),
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            old_values(drain(&mut with_old)),
+            [None, Some("a".to_string()), Some("b".to_string())]
+        );
+        assert_eq!(old_values(drain(&mut without_old)), [None, None, None]);
+    }
+
+    #[test]
+    fn attribute_filter() {
+        let root = VirtNode::create_element("div");
+        let mut records = root.observe_mutations_with_options(
+            MutationObserverInit::new().attribute_filter(&["hidden"]),
+        );
+
+        root.set_attribute("class", "a");
+        root.set_attribute("hidden", "");
+
+        let mutations = drain(&mut records);
+        assert_eq!(mutations.len(), 1);
+        assert!(matches!(
+            &mutations[0],
+            Mutation::Attributes { attribute_name, .. } if attribute_name == "hidden"
+        ));
+    }
+
+    #[test]
+    fn character_data() {
+        let root = VirtNode::create_element("p");
+        let text = VirtNode::create_text_node("hello");
+        root.append_child(&text);
+        let mut records = root.observe_mutations_with_options(
+            MutationObserverInit::new().character_data_old_value(true).subtree(true),
+        );
+
+        text.set_data("goodbye");
+        let mutations = drain(&mut records);
+        assert_eq!(mutations.len(), 1);
+        assert_eq!(mutations[0].type_(), "characterData");
+        assert!(matches!(
+            &mutations[0],
+            Mutation::CharacterData { target, old_value: Some(old) }
+                if Rc::ptr_eq(target, &text) && old == "hello"
+        ));
+    }
+
+    #[test]
+    fn subtree() {
+        let root = VirtNode::create_element("div");
+        let child = VirtNode::create_element("p");
+        root.append_child(&child);
+
+        let mut shallow =
+            root.observe_mutations_with_options(MutationObserverInit::new().attributes(true));
+        let mut deep = root.observe_mutations();
+
+        child.set_attribute("class", "a");
+        assert!(drain(&mut shallow).is_empty());
+        assert_eq!(drain(&mut deep).len(), 1);
+    }
+
+    #[test]
+    fn dropped_observers_are_unregistered() {
+        let root = VirtNode::create_element("div");
+        let records = root.observe_mutations();
+        assert_eq!(root.observers.borrow().len(), 1);
+
+        drop(records);
+        root.set_attribute("class", "a");
+        assert!(root.observers.borrow().is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "one of child_list, attributes, or character_data")]
+    fn must_observe_something() {
+        VirtNode::create_element("div")
+            .observe_mutations_with_options(MutationObserverInit::new().subtree(true));
+    }
+}
@@ -0,0 +1,696 @@
+//! Parsing HTML into virtual DOM trees.
+//!
+//! This follows the broad strokes of the [HTML parsing algorithm][spec]: tag
+//! and attribute names are case-insensitive, void elements never have children,
+//! the contents of raw text elements like `<script>` aren't parsed as markup,
+//! and common implied end tags (e.g. for `<p>`, `<li>`, and table cells) are
+//! inserted. It doesn't attempt the spec's error recovery beyond closing
+//! unmatched elements and ignoring stray end tags.
+//!
+//! [spec]: https://html.spec.whatwg.org/multipage/parsing.html
+
+use super::{
+    html::{RAW_TEXT, VOID},
+    VirtData, VirtNode,
+};
+use crate::Dom;
+use std::rc::Rc;
+
+/// Elements whose contents are text, with character references decoded.
+const ESCAPABLE_RAW_TEXT: &[&str] = &["textarea", "title"];
+
+/// Elements which close an open `<p>` when they start.
+const CLOSES_P: &[&str] = &[
+    "address",
+    "article",
+    "aside",
+    "blockquote",
+    "center",
+    "dd",
+    "details",
+    "dialog",
+    "dir",
+    "div",
+    "dl",
+    "dt",
+    "fieldset",
+    "figcaption",
+    "figure",
+    "footer",
+    "form",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "header",
+    "hgroup",
+    "hr",
+    "li",
+    "listing",
+    "main",
+    "menu",
+    "nav",
+    "ol",
+    "p",
+    "plaintext",
+    "pre",
+    "section",
+    "summary",
+    "table",
+    "ul",
+    "xmp",
+];
+
+/// Elements which limit the search for an open element to implicitly close.
+const SCOPE: &[&str] =
+    &["applet", "button", "caption", "html", "marquee", "object", "table", "td", "template", "th"];
+
+/// Elements which limit the search for an open table part to implicitly close.
+const TABLE_SCOPE: &[&str] = &["html", "table", "template"];
+
+const HEADINGS: &[&str] = &["h1", "h2", "h3", "h4", "h5", "h6"];
+
+/// Elements which belong in a document's `<head>` when they appear before any
+/// body content.
+const HEAD_CONTENT: &[&str] = &[
+    "base", "basefont", "bgsound", "link", "meta", "noscript", "script", "style", "template",
+    "title",
+];
+
+/// Parse `html` as the contents of an element, returning the top-level nodes.
+///
+/// ```
+/// # use augdom::rsdom::{parse_fragment, HtmlOptions};
+/// let nodes = parse_fragment("<ul class=todos><li>one<li>two &amp; three</ul>");
+/// assert_eq!(
+///     nodes[0].to_html(&HtmlOptions::new()),
+///     r#"<ul class="todos"><li>one</li><li>two &amp; three</li></ul>"#,
+/// );
+/// ```
+pub fn parse_fragment(html: &str) -> Vec<Rc<VirtNode>> {
+    let mut builder = TreeBuilder::default();
+    for token in Tokenizer::new(html) {
+        builder.token(token);
+    }
+    builder.roots
+}
+
+/// Parse `html` as a complete document, returning its `<head>` and `<body>`.
+pub(crate) fn parse_document(html: &str) -> (Rc<VirtNode>, Rc<VirtNode>) {
+    let head = VirtNode::create_element("head");
+    let body = VirtNode::create_element("body");
+
+    let mut in_body = false;
+    let mut top_level = parse_fragment(html);
+    while let Some(node) = top_level.first().cloned() {
+        top_level.remove(0);
+        let children = || node.children.borrow_mut().drain(..).collect::<Vec<_>>();
+        match node.tag() {
+            Some("html") => {
+                top_level.splice(0..0, children());
+            }
+            Some("head") if !in_body => children().iter().for_each(|c| head.append_child(c)),
+            Some("body") => {
+                in_body = true;
+                children().iter().for_each(|c| body.append_child(c));
+            }
+            Some(tag) if !in_body && HEAD_CONTENT.contains(&tag) => head.append_child(&node),
+            None if !in_body && is_whitespace(&node) => (),
+            None if matches!(node.data, VirtData::Comment(_)) => {
+                if in_body { &body } else { &head }.append_child(&node);
+            }
+            _ => {
+                in_body = true;
+                body.append_child(&node);
+            }
+        }
+    }
+
+    (head, body)
+}
+
+fn is_whitespace(node: &VirtNode) -> bool {
+    match &node.data {
+        VirtData::Text(text) => text.borrow().chars().all(is_html_whitespace),
+        _ => false,
+    }
+}
+
+fn is_html_whitespace(c: char) -> bool {
+    matches!(c, ' ' | '\t' | '\n' | '\r' | '\x0C')
+}
+
+/// Assembles tokens into trees, tracking the currently open elements.
+#[derive(Default)]
+struct TreeBuilder {
+    roots: Vec<Rc<VirtNode>>,
+    open: Vec<Rc<VirtNode>>,
+}
+
+impl TreeBuilder {
+    fn token(&mut self, token: Token) {
+        match token {
+            Token::StartTag { name, attrs } => self.start_tag(name, attrs),
+            Token::EndTag { name } => self.end_tag(&name),
+            Token::Text(text) => self.text(&text),
+            Token::Comment(comment) => self.insert(&VirtNode::create_comment(&comment)),
+            // a document's doctype isn't part of its head or body
+            Token::Doctype(_) => (),
+        }
+    }
+
+    fn start_tag(&mut self, name: String, attrs: Vec<(String, String)>) {
+        let name = name.as_str();
+        if CLOSES_P.contains(&name) {
+            self.close_in_scope(&["p"], SCOPE, &[]);
+        }
+        match name {
+            "li" => self.close_in_scope(&["li"], SCOPE, &["ol", "ul"]),
+            "dd" | "dt" => self.close_in_scope(&["dd", "dt"], SCOPE, &["dl"]),
+            "option" => self.close_current(&["option"]),
+            "optgroup" => {
+                self.close_current(&["option"]);
+                self.close_current(&["optgroup"]);
+            }
+            "tbody" | "thead" | "tfoot" => {
+                self.close_in_scope(&["tbody", "thead", "tfoot"], TABLE_SCOPE, &[]);
+            }
+            "tr" => {
+                self.close_in_scope(&["tr"], TABLE_SCOPE, &["tbody", "thead", "tfoot"]);
+                if self.current_is(&["table"]) {
+                    self.start_tag("tbody".to_string(), vec![]);
+                }
+            }
+            "td" | "th" => self.close_in_scope(&["td", "th"], TABLE_SCOPE, &["tr"]),
+            _ if HEADINGS.contains(&name) => self.close_current(HEADINGS),
+            _ => (),
+        }
+
+        let elem = VirtNode::create_element(name);
+        if let VirtData::Elem { attrs: elem_attrs, .. } = &elem.data {
+            *elem_attrs.borrow_mut() = attrs;
+        }
+        self.insert(&elem);
+
+        if !VOID.contains(&name) {
+            self.open.push(elem);
+        }
+    }
+
+    fn end_tag(&mut self, name: &str) {
+        if let Some(idx) = self.open.iter().rposition(|open| open.tag() == Some(name)) {
+            self.open.truncate(idx);
+        }
+    }
+
+    fn text(&mut self, text: &str) {
+        let last = match self.insertion_parent() {
+            Some(parent) => parent.children.borrow().last().cloned(),
+            None => self.roots.last().cloned(),
+        };
+
+        // adjacent text is always merged into a single node
+        if let Some(VirtData::Text(existing)) = last.as_ref().map(|l| &l.data) {
+            existing.borrow_mut().push_str(text);
+        } else {
+            self.insert(&VirtNode::create_text_node(text));
+        }
+    }
+
+    fn insert(&mut self, node: &Rc<VirtNode>) {
+        match self.insertion_parent() {
+            Some(parent) => parent.append_child(node),
+            None => self.roots.push(node.clone()),
+        }
+    }
+
+    /// Returns the node new children are added to, the current element or, if
+    /// it's a `<template>`, the element's contents.
+    fn insertion_parent(&self) -> Option<&Rc<VirtNode>> {
+        self.open.last().map(|current| current.content().unwrap_or(current))
+    }
+
+    fn current_is(&self, tags: &[&str]) -> bool {
+        self.open.last().and_then(|c| c.tag().map(|t| tags.contains(&t))).unwrap_or(false)
+    }
+
+    fn close_current(&mut self, tags: &[&str]) {
+        if self.current_is(tags) {
+            self.open.pop();
+        }
+    }
+
+    /// Close the innermost open element in `tags` and any elements opened
+    /// after it, unless an element in `scope` or `boundaries` is found first.
+    fn close_in_scope(&mut self, tags: &[&str], scope: &[&str], boundaries: &[&str]) {
+        for (idx, open) in self.open.iter().enumerate().rev() {
+            let tag = open.tag().unwrap_or_default();
+            if tags.contains(&tag) {
+                self.open.truncate(idx);
+                return;
+            }
+            if scope.contains(&tag) || boundaries.contains(&tag) {
+                return;
+            }
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    StartTag { name: String, attrs: Vec<(String, String)> },
+    EndTag { name: String },
+    Text(String),
+    Comment(String),
+    Doctype(String),
+}
+
+/// Splits markup into a stream of tokens.
+struct Tokenizer<'a> {
+    input: &'a str,
+    /// Set after a raw text element's start tag, until its end tag.
+    raw_text: Option<(String, bool)>,
+}
+
+impl<'a> Tokenizer<'a> {
+    fn new(input: &'a str) -> Self {
+        Self { input, raw_text: None }
+    }
+
+    fn advance(&mut self, len: usize) -> &'a str {
+        let (consumed, rest) = self.input.split_at(len);
+        self.input = rest;
+        consumed
+    }
+
+    /// Consume the text before the end tag of the raw text element `tag`,
+    /// returning `None` if the input starts with that end tag.
+    fn raw_text(&mut self, tag: &str, decode: bool) -> Option<Token> {
+        let mut end = 0;
+        let len = loop {
+            match self.input[end..].find("</") {
+                Some(offset) => {
+                    let candidate = end + offset;
+                    let after = &self.input[candidate + 2..];
+                    let name_matches = after
+                        .as_bytes()
+                        .get(..tag.len())
+                        .is_some_and(|name| name.eq_ignore_ascii_case(tag.as_bytes()))
+                        && after[tag.len()..]
+                            .chars()
+                            .next()
+                            .map(|c| is_html_whitespace(c) || c == '/' || c == '>')
+                            .unwrap_or(true);
+                    if name_matches && tag != "plaintext" {
+                        break candidate;
+                    }
+                    end = candidate + 2;
+                }
+                None => break self.input.len(),
+            }
+        };
+
+        if len == 0 {
+            return None;
+        }
+        let text = self.advance(len);
+        Some(Token::Text(if decode { decode_references(text) } else { text.to_owned() }))
+    }
+
+    fn tag(&mut self) -> Option<Token> {
+        let is_end = self.input.starts_with("</");
+        let rest = &self.input[if is_end { 2 } else { 1 }..];
+        let name_len = rest
+            .find(|c: char| is_html_whitespace(c) || c == '/' || c == '>')
+            .unwrap_or(rest.len());
+        let name = rest[..name_len].to_ascii_lowercase();
+        let mut cursor = &rest[name_len..];
+
+        let mut attrs: Vec<(String, String)> = vec![];
+        loop {
+            cursor = cursor.trim_start_matches(|c: char| is_html_whitespace(c) || c == '/');
+            if cursor.is_empty() {
+                // unterminated tags are dropped
+                self.input = cursor;
+                return None;
+            }
+            if let Some(after) = cursor.strip_prefix('>') {
+                cursor = after;
+                break;
+            }
+
+            // the name's first character is taken even if it's `=`
+            let attr_len = cursor
+                .char_indices()
+                .skip(1)
+                .find(|&(_, c)| is_html_whitespace(c) || c == '/' || c == '>' || c == '=')
+                .map_or(cursor.len(), |(len, _)| len);
+            let attr_name = cursor[..attr_len].to_ascii_lowercase();
+            cursor = cursor[attr_len..].trim_start_matches(is_html_whitespace);
+
+            let mut value = String::new();
+            if let Some(after) = cursor.strip_prefix('=') {
+                cursor = after.trim_start_matches(is_html_whitespace);
+                let (raw, rest) = match cursor.chars().next() {
+                    Some(quote @ '"') | Some(quote @ '\'') => match cursor[1..].find(quote) {
+                        Some(len) => (&cursor[1..len + 1], &cursor[len + 2..]),
+                        None => (&cursor[1..], ""),
+                    },
+                    _ => {
+                        let len = cursor
+                            .find(|c: char| is_html_whitespace(c) || c == '>')
+                            .unwrap_or(cursor.len());
+                        (&cursor[..len], &cursor[len..])
+                    }
+                };
+                value = decode_references(raw);
+                cursor = rest;
+            }
+
+            // the first of any duplicated attributes wins
+            if !attrs.iter().any(|(n, _)| *n == attr_name) {
+                attrs.push((attr_name, value));
+            }
+        }
+        self.input = cursor;
+
+        Some(if is_end {
+            Token::EndTag { name }
+        } else {
+            if RAW_TEXT.contains(&name.as_str()) {
+                self.raw_text = Some((name.clone(), false));
+            } else if ESCAPABLE_RAW_TEXT.contains(&name.as_str()) {
+                self.raw_text = Some((name.clone(), true));
+            }
+            if matches!(name.as_str(), "pre" | "listing" | "textarea") {
+                // a newline immediately after these start tags is ignored
+                if let Some(rest) = self.input.strip_prefix('\n') {
+                    self.input = rest;
+                } else if let Some(rest) = self.input.strip_prefix("\r\n") {
+                    self.input = rest;
+                }
+            }
+            Token::StartTag { name, attrs }
+        })
+    }
+
+    /// Consume a comment, doctype, or other markup declaration starting with
+    /// `<!` or `<?`.
+    fn declaration(&mut self) -> Token {
+        if let Some(rest) = self.input.strip_prefix("<!--") {
+            let (contents, len) = match rest.find("-->") {
+                Some(end) => (&rest[..end], 4 + end + 3),
+                None => (rest, self.input.len()),
+            };
+            let token = Token::Comment(contents.to_owned());
+            self.advance(len);
+            return token;
+        }
+
+        let len = self.input.find('>').map(|end| end + 1).unwrap_or_else(|| self.input.len());
+        let decl = self.advance(len);
+        let contents = decl[2..].trim_end_matches('>');
+        let is_doctype = contents.get(..7).is_some_and(|d| d.eq_ignore_ascii_case("doctype"));
+        if is_doctype {
+            Token::Doctype(contents[7..].trim().to_owned())
+        } else {
+            Token::Comment(contents.to_owned())
+        }
+    }
+}
+
+impl Iterator for Tokenizer<'_> {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Token> {
+        loop {
+            if self.input.is_empty() {
+                return None;
+            }
+
+            // raw text continues until its element's end tag
+            if let Some((tag, decode)) = self.raw_text.take() {
+                if let Some(text) = self.raw_text(&tag, decode) {
+                    self.raw_text = Some((tag, decode));
+                    return Some(text);
+                }
+            }
+
+            let mut chars = self.input.chars();
+            let starts_markup = match (chars.next(), chars.next(), chars.next()) {
+                (Some('<'), Some('/'), Some(c)) => c.is_ascii_alphabetic(),
+                (Some('<'), Some('!'), _) | (Some('<'), Some('?'), _) => {
+                    return Some(self.declaration());
+                }
+                (Some('<'), Some(c), _) => c.is_ascii_alphabetic(),
+                _ => false,
+            };
+
+            if starts_markup {
+                match self.tag() {
+                    Some(token) => return Some(token),
+                    None => continue,
+                }
+            }
+
+            // text continues until the next `<` which starts markup
+            let mut len = if self.input.starts_with('<') { 1 } else { 0 };
+            len += self.input[len..].find('<').unwrap_or_else(|| self.input.len() - len);
+            let text = self.advance(len);
+            return Some(Token::Text(decode_references(text)));
+        }
+    }
+}
+
+/// Named character references supported by the parser, a subset of the
+/// [full list][spec].
+///
+/// [spec]: https://html.spec.whatwg.org/multipage/named-characters.html
+const NAMED_REFERENCES: &[(&str, char)] = &[
+    ("amp", '&'),
+    ("apos", '\''),
+    ("bull", '\u{2022}'),
+    ("cent", '\u{a2}'),
+    ("copy", '\u{a9}'),
+    ("darr", '\u{2193}'),
+    ("deg", '\u{b0}'),
+    ("divide", '\u{f7}'),
+    ("euro", '\u{20ac}'),
+    ("gt", '>'),
+    ("hellip", '\u{2026}'),
+    ("laquo", '\u{ab}'),
+    ("larr", '\u{2190}'),
+    ("ldquo", '\u{201c}'),
+    ("lsquo", '\u{2018}'),
+    ("lt", '<'),
+    ("mdash", '\u{2014}'),
+    ("middot", '\u{b7}'),
+    ("nbsp", '\u{a0}'),
+    ("ndash", '\u{2013}'),
+    ("para", '\u{b6}'),
+    ("plusmn", '\u{b1}'),
+    ("pound", '\u{a3}'),
+    ("quot", '"'),
+    ("raquo", '\u{bb}'),
+    ("rarr", '\u{2192}'),
+    ("rdquo", '\u{201d}'),
+    ("reg", '\u{ae}'),
+    ("rsquo", '\u{2019}'),
+    ("sect", '\u{a7}'),
+    ("shy", '\u{ad}'),
+    ("times", '\u{d7}'),
+    ("trade", '\u{2122}'),
+    ("uarr", '\u{2191}'),
+    ("yen", '\u{a5}'),
+];
+
+/// Replace the character references in `text`. References which aren't
+/// terminated by a `;` or aren't recognized are left as-is.
+fn decode_references(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let reference = rest[1..].find(';').map(|end| &rest[1..end + 1]);
+        let replacement = reference.and_then(|reference| {
+            if let Some(number) = reference.strip_prefix('#') {
+                let parsed = match number.strip_prefix('x').or_else(|| number.strip_prefix('X')) {
+                    Some(hex) => u32::from_str_radix(hex, 16),
+                    None => number.parse(),
+                };
+                parsed
+                    .ok()
+                    .map(|n| std::char::from_u32(n).filter(|&c| c != '\0').unwrap_or('\u{fffd}'))
+            } else {
+                NAMED_REFERENCES.iter().find(|(name, _)| *name == reference).map(|(_, c)| *c)
+            }
+        });
+
+        match (reference, replacement) {
+            (Some(reference), Some(c)) => {
+                decoded.push(c);
+                rest = &rest[reference.len() + 2..];
+            }
+            _ => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rsdom::HtmlOptions;
+
+    fn round_trip(html: &str) -> String {
+        parse_fragment(html).iter().map(|n| n.to_html(&HtmlOptions::new())).collect()
+    }
+
+    #[test]
+    fn elements_attributes_and_text() {
+        assert_eq!(
+            round_trip(r#"<DIV Class="a b" id='c' data-x=1 hidden>hi <b>there</b></DIV>!"#),
+            r#"<div class="a b" id="c" data-x="1" hidden="">hi <b>there</b></div>!"#
+        );
+    }
+
+    #[test]
+    fn void_elements() {
+        assert_eq!(
+            round_trip("<p>one<br>two<br/>three<img src=a.png></p>"),
+            r#"<p>one<br>two<br>three<img src="a.png"></p>"#
+        );
+    }
+
+    #[test]
+    fn implied_end_tags() {
+        assert_eq!(
+            round_trip("<ul><li>one<li>two</ul><p>first<p>second<div>third</div>"),
+            "<ul><li>one</li><li>two</li></ul><p>first</p><p>second</p><div>third</div>"
+        );
+        assert_eq!(
+            round_trip("<dl><dt>term<dd>definition<dt>other</dl>"),
+            "<dl><dt>term</dt><dd>definition</dd><dt>other</dt></dl>"
+        );
+        assert_eq!(
+            round_trip("<table><tr><td>a<td>b<tr><th>c</table>"),
+            "<table><tbody><tr><td>a</td><td>b</td></tr><tr><th>c</th></tr></tbody></table>"
+        );
+    }
+
+    #[test]
+    fn unmatched_tags() {
+        assert_eq!(
+            round_trip("<div><span>unclosed</div>after"),
+            "<div><span>unclosed</span></div>after"
+        );
+        assert_eq!(round_trip("stray</span> end"), "stray end");
+    }
+
+    #[test]
+    fn raw_text() {
+        assert_eq!(
+            round_trip("<script>if (a < b && c) { x = '<p>'; }</script><style>a>b{}</STYLE>"),
+            "<script>if (a < b && c) { x = '<p>'; }</script><style>a>b{}</style>"
+        );
+        let title = parse_fragment("<title>a &amp; <b></title>");
+        assert_eq!(title[0].get_inner_text(), "a & <b>");
+    }
+
+    #[test]
+    fn raw_text_starting_with_other_end_tags() {
+        assert_eq!(
+            round_trip("<textarea></b> hi</textarea>"),
+            "<textarea>&lt;/b&gt; hi</textarea>"
+        );
+        assert_eq!(round_trip("<script></p></script>"), "<script></p></script>");
+    }
+
+    #[test]
+    fn non_ascii_markup() {
+        assert_eq!(
+            round_trip("<script>x = \"</éé€\";</script>"),
+            "<script>x = \"</éé€\";</script>"
+        );
+        assert_eq!(round_trip("<p é=1 ü>x</p>"), r#"<p é="1" ü="">x</p>"#);
+        assert_eq!(round_trip("<p>a<!é>b<!-- €€ -->c</p>"), "<p>a<!--é-->b<!-- €€ -->c</p>");
+    }
+
+    #[test]
+    fn character_references() {
+        let p =
+            parse_fragment("<p title=\"&quot;hi&quot;\">&lt;&#65;&#x42;&nbsp;&unknown; &amp</p>");
+        assert_eq!(p[0].get_attribute("title").unwrap(), "\"hi\"");
+        assert_eq!(p[0].get_inner_text(), "<AB\u{a0}&unknown; &amp");
+    }
+
+    #[test]
+    fn comments() {
+        assert_eq!(
+            round_trip("<!DOCTYPE html><p>a<!-- <b>not markup</b> -->b</p><!---->"),
+            "<p>a<!-- <b>not markup</b> -->b</p><!---->"
+        );
+        let p = parse_fragment("<p>a<!--b-->c</p>");
+        assert_eq!(p[0].children.borrow().len(), 3);
+        assert_eq!(p[0].get_inner_text(), "ac");
+    }
+
+    #[test]
+    fn template_contents() {
+        let nodes = parse_fragment("<template><li>one<li>two</template><p>after</p>");
+        let template = &nodes[0];
+        assert!(template.children.borrow().is_empty());
+        let content = template.template_content().unwrap();
+        assert_eq!(content.node_type(), crate::NodeType::DocumentFragment);
+        assert_eq!(content.children.borrow().len(), 2);
+        assert_eq!(
+            nodes.iter().map(|n| n.to_html(&HtmlOptions::new())).collect::<String>(),
+            "<template><li>one</li><li>two</li></template><p>after</p>"
+        );
+    }
+
+    #[test]
+    fn leading_newline_in_pre() {
+        assert_eq!(round_trip("<pre>\n  code</pre>"), "<pre>  code</pre>");
+    }
+
+    #[test]
+    fn literal_angle_brackets() {
+        assert_eq!(round_trip("a < b <3 c"), "a &lt; b &lt;3 c");
+    }
+
+    #[test]
+    fn documents() {
+        let (head, body) = parse_document(
+            "<!DOCTYPE html>\n<html><head><title>t</title></head>\n<body><p>hi</p></body></html>",
+        );
+        assert_eq!(head.to_html(&HtmlOptions::new()), "<head><title>t</title></head>");
+        assert_eq!(body.to_html(&HtmlOptions::new()), "<body><p>hi</p></body>");
+
+        let (head, body) = parse_document("<meta charset=utf-8>\n<title>t</title><main>hi</main>");
+        assert_eq!(
+            head.to_html(&HtmlOptions::new()),
+            r#"<head><meta charset="utf-8"><title>t</title></head>"#
+        );
+        assert_eq!(body.to_html(&HtmlOptions::new()), "<body><main>hi</main></body>");
+
+        let (head, body) = parse_document("<!--a--><title>t</title><p>hi</p><!--b-->");
+        assert_eq!(head.to_html(&HtmlOptions::new()), "<head><!--a--><title>t</title></head>");
+        assert_eq!(body.to_html(&HtmlOptions::new()), "<body><p>hi</p><!--b--></body>");
+    }
+
+    #[test]
+    fn serialized_html_round_trips() {
+        let html = r#"<div id="app"><ul><li class="done">a &amp; b</li><li>c</li></ul><input type="checkbox" checked=""><pre>  x
+y</pre></div>"#;
+        assert_eq!(round_trip(html), html);
+    }
+}
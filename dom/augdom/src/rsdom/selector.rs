@@ -0,0 +1,514 @@
+//! CSS selector matching for virtual nodes, implemented with the [`selectors`]
+//! crate from Servo.
+//!
+//! [`selectors`]: https://docs.rs/selectors
+
+use super::{VirtData, VirtNode};
+use crate::InvalidSelector;
+use cssparser::{CowRcStr, ParseError, ParserInput, SourceLocation, ToCss};
+use precomputed_hash::PrecomputedHash;
+use selectors::{
+    attr::{AttrSelectorOperation, CaseSensitivity, NamespaceConstraint},
+    matching::{self, ElementSelectorFlags, MatchingContext, MatchingMode, QuirksMode},
+    parser::{SelectorImpl, SelectorParseErrorKind},
+    OpaqueElement, SelectorList,
+};
+use std::{
+    borrow::Borrow,
+    collections::hash_map::DefaultHasher,
+    fmt::{Debug, Display, Formatter, Result as FmtResult, Write as FmtWrite},
+    hash::{Hash, Hasher},
+    rc::Rc,
+};
+
+/// A parsed list of selectors which can be matched against virtual nodes.
+pub(crate) struct Selectors(SelectorList<Simple>);
+
+impl Selectors {
+    /// Parse a comma-separated list of selectors, returning an error if
+    /// `selectors` is not valid CSS selector syntax or uses a pseudo-class
+    /// which isn't supported.
+    pub fn parse(selectors: &str) -> Result<Self, InvalidSelector> {
+        let mut input = ParserInput::new(selectors);
+        let mut parser = cssparser::Parser::new(&mut input);
+        match SelectorList::parse(&Parser, &mut parser) {
+            Ok(list) => Ok(Selectors(list)),
+            Err(e) => Err(InvalidSelector::new(selectors, format!("{:?}", e.kind))),
+        }
+    }
+
+    /// Returns true if `node` is an element matching any of the selectors.
+    pub fn matches(&self, node: &Rc<VirtNode>) -> bool {
+        if !node.is_element() {
+            return false;
+        }
+        let mut context =
+            MatchingContext::new(MatchingMode::Normal, None, None, QuirksMode::NoQuirks);
+        matching::matches_selector_list(&self.0, &Element(node.clone()), &mut context)
+    }
+}
+
+/// Strings stored in parsed selectors.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub(crate) struct CssString(String);
+
+impl<'a> From<&'a str> for CssString {
+    fn from(s: &'a str) -> Self {
+        CssString(s.to_owned())
+    }
+}
+
+impl AsRef<str> for CssString {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Borrow<str> for CssString {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Display for CssString {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.write_str(&self.0)
+    }
+}
+
+impl ToCss for CssString {
+    fn to_css<W: FmtWrite>(&self, dest: &mut W) -> FmtResult {
+        cssparser::serialize_identifier(&self.0, dest)
+    }
+}
+
+impl PrecomputedHash for CssString {
+    fn precomputed_hash(&self) -> u32 {
+        let mut hasher = DefaultHasher::new();
+        self.0.hash(&mut hasher);
+        hasher.finish() as u32
+    }
+}
+
+/// The selector "implementation" supported by rsdom: no namespaces and a small
+/// set of stateful pseudo-classes.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct Simple;
+
+impl SelectorImpl for Simple {
+    type AttrValue = CssString;
+    type BorrowedLocalName = str;
+    type BorrowedNamespaceUrl = str;
+    type ClassName = CssString;
+    type ExtraMatchingData = ();
+    type Identifier = CssString;
+    type LocalName = CssString;
+    type NamespacePrefix = CssString;
+    type NamespaceUrl = CssString;
+    type NonTSPseudoClass = PseudoClass;
+    type PartName = CssString;
+    type PseudoElement = PseudoElement;
+}
+
+/// Pseudo-classes which depend on an element's state rather than its position
+/// in the tree. Tree-structural pseudo-classes like `:nth-child` are handled by
+/// the `selectors` crate itself.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum PseudoClass {
+    AnyLink,
+    Checked,
+    Disabled,
+    Enabled,
+    Link,
+    /// A state which virtual elements are never in, named without its colon.
+    Never(&'static str),
+}
+
+/// Pseudo-classes for user interaction and rendering states which virtual
+/// elements can't have, because nothing displays or focuses them.
+const NEVER_MATCHED: &[&str] = &[
+    "active",
+    "autofill",
+    "focus",
+    "focus-visible",
+    "focus-within",
+    "fullscreen",
+    "hover",
+    "modal",
+    "paused",
+    "picture-in-picture",
+    "playing",
+    "popover-open",
+    "target",
+    "target-within",
+    "user-invalid",
+    "user-valid",
+    "visited",
+];
+
+impl selectors::parser::NonTSPseudoClass for PseudoClass {
+    type Impl = Simple;
+
+    fn is_active_or_hover(&self) -> bool {
+        false
+    }
+
+    fn is_user_action_state(&self) -> bool {
+        false
+    }
+
+    fn has_zero_specificity(&self) -> bool {
+        false
+    }
+}
+
+impl ToCss for PseudoClass {
+    fn to_css<W: FmtWrite>(&self, dest: &mut W) -> FmtResult {
+        dest.write_str(match self {
+            PseudoClass::AnyLink => ":any-link",
+            PseudoClass::Checked => ":checked",
+            PseudoClass::Disabled => ":disabled",
+            PseudoClass::Enabled => ":enabled",
+            PseudoClass::Link => ":link",
+            PseudoClass::Never(name) => return write!(dest, ":{}", name),
+        })
+    }
+}
+
+/// rsdom doesn't render anything, so there are no pseudo-elements to match.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) enum PseudoElement {}
+
+impl selectors::parser::PseudoElement for PseudoElement {
+    type Impl = Simple;
+}
+
+impl ToCss for PseudoElement {
+    fn to_css<W: FmtWrite>(&self, _dest: &mut W) -> FmtResult {
+        match *self {}
+    }
+}
+
+struct Parser;
+
+impl<'i> selectors::Parser<'i> for Parser {
+    type Error = SelectorParseErrorKind<'i>;
+    type Impl = Simple;
+
+    fn parse_non_ts_pseudo_class(
+        &self,
+        location: SourceLocation,
+        name: CowRcStr<'i>,
+    ) -> Result<PseudoClass, ParseError<'i, Self::Error>> {
+        Ok(match &*name.to_ascii_lowercase() {
+            "any-link" => PseudoClass::AnyLink,
+            "checked" => PseudoClass::Checked,
+            "disabled" => PseudoClass::Disabled,
+            "enabled" => PseudoClass::Enabled,
+            "link" => PseudoClass::Link,
+            other => match NEVER_MATCHED.iter().find(|never| **never == other) {
+                Some(never) => PseudoClass::Never(never),
+                None => {
+                    return Err(location.new_custom_error(
+                        SelectorParseErrorKind::UnsupportedPseudoClassOrElement(name),
+                    ))
+                }
+            },
+        })
+    }
+}
+
+/// A virtual element viewed through the `selectors` crate's matching API.
+#[derive(Clone)]
+struct Element(Rc<VirtNode>);
+
+impl Element {
+    fn tag(&self) -> &str {
+        match &self.0.data {
+            VirtData::Elem { tag, .. } => tag,
+            _ => unreachable!("only elements are wrapped for matching"),
+        }
+    }
+
+    fn attr(&self, name: &str) -> Option<String> {
+        crate::Dom::get_attribute(&self.0, name)
+    }
+
+    fn sibling_element(
+        &self,
+        step: impl Fn(&Rc<VirtNode>) -> Option<Rc<VirtNode>>,
+    ) -> Option<Self> {
+        let mut next = step(&self.0);
+        while let Some(node) = next {
+            if node.is_element() {
+                return Some(Element(node));
+            }
+            next = step(&node);
+        }
+        None
+    }
+}
+
+impl Debug for Element {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "<{}>", self.tag())
+    }
+}
+
+impl selectors::Element for Element {
+    type Impl = Simple;
+
+    fn opaque(&self) -> OpaqueElement {
+        OpaqueElement::new::<VirtNode>(&self.0)
+    }
+
+    fn parent_element(&self) -> Option<Self> {
+        self.0.parent().filter(|p| p.is_element()).map(Element)
+    }
+
+    fn parent_node_is_shadow_root(&self) -> bool {
+        false
+    }
+
+    fn containing_shadow_host(&self) -> Option<Self> {
+        None
+    }
+
+    fn is_pseudo_element(&self) -> bool {
+        false
+    }
+
+    fn prev_sibling_element(&self) -> Option<Self> {
+        self.sibling_element(|n| n.prev_sibling())
+    }
+
+    fn next_sibling_element(&self) -> Option<Self> {
+        self.sibling_element(crate::Dom::next_sibling)
+    }
+
+    fn is_html_element_in_html_document(&self) -> bool {
+        true
+    }
+
+    fn has_local_name(&self, local_name: &str) -> bool {
+        self.tag().eq_ignore_ascii_case(local_name)
+    }
+
+    fn has_namespace(&self, ns: &str) -> bool {
+        ns.is_empty()
+    }
+
+    fn is_same_type(&self, other: &Self) -> bool {
+        self.tag().eq_ignore_ascii_case(other.tag())
+    }
+
+    fn attr_matches(
+        &self,
+        ns: &NamespaceConstraint<&CssString>,
+        local_name: &CssString,
+        operation: &AttrSelectorOperation<&CssString>,
+    ) -> bool {
+        if let NamespaceConstraint::Specific(ns) = ns {
+            if !ns.0.is_empty() {
+                return false;
+            }
+        }
+        self.attr(&local_name.0).map(|value| operation.eval_str(&value)).unwrap_or(false)
+    }
+
+    fn match_non_ts_pseudo_class<F>(
+        &self,
+        pc: &PseudoClass,
+        _context: &mut MatchingContext<Simple>,
+        _flags_setter: &mut F,
+    ) -> bool
+    where
+        F: FnMut(&Self, ElementSelectorFlags),
+    {
+        match pc {
+            PseudoClass::AnyLink | PseudoClass::Link => self.is_link(),
+            PseudoClass::Checked => self.attr("checked").is_some(),
+            PseudoClass::Disabled => self.attr("disabled").is_some(),
+            PseudoClass::Enabled => self.attr("disabled").is_none(),
+            PseudoClass::Never(_) => false,
+        }
+    }
+
+    fn match_pseudo_element(
+        &self,
+        pe: &PseudoElement,
+        _context: &mut MatchingContext<Simple>,
+    ) -> bool {
+        match *pe {}
+    }
+
+    fn is_link(&self) -> bool {
+        matches!(self.tag(), "a" | "area" | "link") && self.attr("href").is_some()
+    }
+
+    fn is_html_slot_element(&self) -> bool {
+        false
+    }
+
+    fn has_id(&self, id: &CssString, case_sensitivity: CaseSensitivity) -> bool {
+        self.attr("id")
+            .map(|own| case_sensitivity.eq(own.as_bytes(), id.0.as_bytes()))
+            .unwrap_or(false)
+    }
+
+    fn has_class(&self, name: &CssString, case_sensitivity: CaseSensitivity) -> bool {
+        self.attr("class")
+            .map(|classes| {
+                classes
+                    .split_ascii_whitespace()
+                    .any(|class| case_sensitivity.eq(class.as_bytes(), name.0.as_bytes()))
+            })
+            .unwrap_or(false)
+    }
+
+    fn exported_part(&self, _name: &CssString) -> Option<CssString> {
+        None
+    }
+
+    fn imported_part(&self, _name: &CssString) -> Option<CssString> {
+        None
+    }
+
+    fn is_part(&self, _name: &CssString) -> bool {
+        false
+    }
+
+    fn is_empty(&self) -> bool {
+        self.0.children.borrow().iter().all(|child| match &child.data {
+            VirtData::Text(t) => t.borrow().is_empty(),
+            VirtData::Comment(_) => true,
+            _ => false,
+        })
+    }
+
+    fn is_root(&self) -> bool {
+        self.0.parent().is_none()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Dom;
+
+    fn list() -> Rc<VirtNode> {
+        let root = VirtNode::create_element("div");
+        root.set_attribute("id", "root");
+
+        let ul = VirtNode::create_element("ul");
+        ul.set_attribute("class", "todo-list main");
+        root.append_child(&ul);
+
+        for (i, text) in ["first", "second", "third"].iter().enumerate() {
+            let li = VirtNode::create_element("li");
+            li.set_attribute("data-idx", &i.to_string());
+            if i == 1 {
+                li.set_attribute("class", "completed");
+            }
+            let input = VirtNode::create_element("input");
+            input.set_attribute("type", "checkbox");
+            if i == 1 {
+                input.set_attribute("checked", "");
+            }
+            li.append_child(&input);
+            li.append_child(&VirtNode::create_text_node(text));
+            ul.append_child(&li);
+        }
+
+        root
+    }
+
+    fn idxs(nodes: Vec<Rc<VirtNode>>) -> Vec<String> {
+        nodes.iter().map(|n| n.get_attribute("data-idx").unwrap_or_default()).collect()
+    }
+
+    #[test]
+    fn type_class_and_id() {
+        let root = list();
+        assert_eq!(root.query_selector_all("li").unwrap().len(), 3);
+        assert_eq!(
+            root.query_selector_all("LI").unwrap().len(),
+            3,
+            "tag names are case-insensitive"
+        );
+        assert_eq!(idxs(root.query_selector_all(".completed").unwrap()), ["1"]);
+        assert_eq!(idxs(root.query_selector_all("li.completed").unwrap()), ["1"]);
+        assert!(root.query_selector("#root").unwrap().is_none(), "only descendants are matched");
+        assert!(root.query_selector("ul.main.todo-list").unwrap().is_some());
+        assert!(root.query_selector("ul.missing").unwrap().is_none());
+    }
+
+    #[test]
+    fn attributes() {
+        let root = list();
+        assert_eq!(root.query_selector_all("[data-idx]").unwrap().len(), 3);
+        assert_eq!(idxs(root.query_selector_all("[data-idx=\"2\"]").unwrap()), ["2"]);
+        assert_eq!(root.query_selector_all("[class~=main]").unwrap().len(), 1);
+        assert_eq!(root.query_selector_all("[class^=todo]").unwrap().len(), 1);
+        assert_eq!(root.query_selector_all("input[type=checkbox]").unwrap().len(), 3);
+    }
+
+    #[test]
+    fn combinators() {
+        let root = list();
+        assert_eq!(
+            root.query_selector_all("#root li").unwrap().len(),
+            3,
+            "ancestors outside scope match"
+        );
+        assert_eq!(root.query_selector_all("ul > li").unwrap().len(), 3);
+        assert_eq!(root.query_selector_all("div > li").unwrap().len(), 0);
+        assert_eq!(idxs(root.query_selector_all(".completed + li").unwrap()), ["2"]);
+        assert_eq!(idxs(root.query_selector_all("li:first-child ~ li").unwrap()), ["1", "2"]);
+    }
+
+    #[test]
+    fn pseudo_classes() {
+        let root = list();
+        assert_eq!(idxs(root.query_selector_all("li:nth-child(2n+1)").unwrap()), ["0", "2"]);
+        assert_eq!(idxs(root.query_selector_all("li:last-child").unwrap()), ["2"]);
+        assert_eq!(idxs(root.query_selector_all("li:not(.completed)").unwrap()), ["0", "2"]);
+        assert_eq!(root.query_selector_all("input:checked").unwrap().len(), 1);
+        assert_eq!(root.query_selector_all("input:empty").unwrap().len(), 3);
+        assert_eq!(root.query_selector_all("li:empty").unwrap().len(), 0);
+    }
+
+    #[test]
+    fn document_order() {
+        let root = list();
+        let found = root.query_selector_all("ul, li, input").unwrap();
+        let tags = found
+            .iter()
+            .map(|n| match &n.data {
+                VirtData::Elem { tag, .. } => tag.as_str(),
+                _ => unreachable!(),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(tags, ["ul", "li", "input", "li", "input", "li", "input"]);
+        assert_eq!(
+            root.query_selector("li, ul").unwrap().unwrap().get_attribute("class").unwrap(),
+            "todo-list main"
+        );
+    }
+
+    #[test]
+    fn states_of_rendered_elements_never_match() {
+        let root = list();
+        assert!(root.query_selector("input:focus").unwrap().is_none());
+        assert!(root.query_selector("li:hover, a:visited").unwrap().is_none());
+        assert_eq!(root.query_selector_all("input:not(:focus-within)").unwrap().len(), 3);
+    }
+
+    #[test]
+    fn invalid_selectors_are_errors() {
+        let root = list();
+        let err = root.query_selector("li[").unwrap_err();
+        assert!(err.to_string().starts_with("invalid selector `li[`"), "{}", err);
+        assert!(root.query_selector_all("li:not-a-pseudo-class").is_err());
+    }
+}
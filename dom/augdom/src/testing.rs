@@ -101,7 +101,13 @@ impl TargetExt for Node {
                         }
                     }
                     #[cfg(feature = "rsdom")]
-                    Node::Virtual(_) => (), // TODO support events for virtual nodes
+                    Node::Virtual(n) => {
+                        // TODO append the key to our value if we're a textarea
+                        if n.tag() == Some("input") {
+                            let new_input = n.get_attribute("value").unwrap_or_default() + key;
+                            n.set_attribute("value", &new_input);
+                        }
+                    }
                 }
                 key
             }
@@ -306,7 +312,9 @@ where
                 .get_attribute("id")
                 .map(|id| {
                     let selector = format!("label[for={}]", id);
-                    self.finder.target.query_selector(&selector).map(|l| l.get_inner_text())
+                    // ids which can't be written in a selector aren't matched
+                    let label = self.finder.target.query_selector(&selector).ok().flatten();
+                    label.map(|l| l.get_inner_text())
                 })
                 .flatten(),
             AltText => node.get_attribute("alt"),
@@ -367,7 +375,6 @@ where
     /// # Panics
     ///
     /// If more than one matching node is found.
-    #[cfg(feature = "webdom")]
     pub async fn one(&self) -> Result<N, QueryError<'_, N>> {
         let mut matches = self.many().await?.into_iter();
         let matched = matches.next().expect("empty results are a query error");
@@ -382,7 +389,6 @@ where
 
     /// Wait until the query can succeed then return a `Vec` of matching nodes
     /// in the queried subtree.
-    #[cfg(feature = "webdom")]
     pub async fn many(&self) -> Result<Vec<N>, QueryError<'_, N>> {
         macro_rules! try_query {
             () => {{
@@ -393,10 +399,10 @@ where
         }
 
         let mut mutations = self.query.finder.target.observe_mutations();
-        let timeout = gloo_timers::future::TimeoutFuture::new(1_000);
-        futures::pin_mut!(timeout);
-
         try_query!(); // see if we can eagerly eval
+
+        let timeout = timeout(1_000);
+        futures::pin_mut!(timeout);
         loop {
             futures::select_biased! {
                 _ = timeout.as_mut().fuse() => {
@@ -408,3 +414,112 @@ where
         }
     }
 }
+
+/// Returns a future which resolves after `millis` milliseconds.
+#[cfg(feature = "webdom")]
+fn timeout(millis: u32) -> impl Future<Output = ()> {
+    gloo_timers::future::TimeoutFuture::new(millis)
+}
+
+/// Returns a future which resolves after `millis` milliseconds.
+#[cfg(not(feature = "webdom"))]
+fn timeout(millis: u32) -> impl Future<Output = ()> {
+    futures_timer::Delay::new(std::time::Duration::from_millis(millis.into()))
+}
+
+#[cfg(all(test, not(feature = "webdom")))]
+mod tests {
+    use super::*;
+    use crate::{document, event::EventHandle, in_virtual_document};
+    use futures::task::LocalSpawnExt;
+    use std::{cell::RefCell, rc::Rc};
+
+    #[test]
+    fn drives_virtual_handlers() {
+        in_virtual_document(|| {
+            let document = document();
+            let div = document.create_element("div");
+
+            let input = document.create_element("input");
+            input.set_attribute("placeholder", "What needs to be done?");
+            div.append_child(&input);
+
+            let button = document.create_element("button");
+            button.set_attribute("data-testid", "clear");
+            button.append_child(&document.create_text_node("Clear"));
+            div.append_child(&button);
+
+            let todos = Rc::new(RefCell::new(vec![]));
+            let (entered, target) = (todos.clone(), input.clone());
+            let _keydown = EventHandle::new(&input, move |keypress: KeyDown| {
+                if keypress.key() == "Enter" {
+                    entered.borrow_mut().push(target.get_attribute("value").unwrap());
+                    target.set_attribute("value", "");
+                }
+            });
+            let cleared = todos.clone();
+            let _click = EventHandle::new(&button, move |_: Click| cleared.borrow_mut().clear());
+
+            let input = div.find().by_placeholder_text("What needs to be done?").one().unwrap();
+            input.keyboardln("weed the garden");
+            input.keyboardln("buy seeds");
+            assert_eq!(*todos.borrow(), ["weed the garden", "buy seeds"]);
+            assert_eq!(input.get_attribute("value").unwrap(), "");
+
+            div.find().by_test_id("clear").one().unwrap().click();
+            assert!(todos.borrow().is_empty());
+        })();
+    }
+
+    #[test]
+    fn text_strategies() {
+        in_virtual_document(|| {
+            let document = document();
+            let form = document.create_element("form");
+
+            let label = document.create_element("label");
+            label.set_attribute("for", "username");
+            label.append_child(&document.create_text_node("  Username\n"));
+            form.append_child(&label);
+
+            let input = document.create_element("input");
+            input.set_attribute("id", "username");
+            form.append_child(&input);
+
+            let button = document.create_element("button");
+            button.append_child(&document.create_text_node("Log "));
+            let emphasis = document.create_element("em");
+            emphasis.append_child(&document.create_text_node(" in"));
+            button.append_child(&emphasis);
+            form.append_child(&button);
+
+            assert_eq!(form.find().by_label_text("Username").one().unwrap(), input);
+            assert_eq!(form.find().by_text("Log in").one().unwrap(), button);
+        })();
+    }
+
+    #[test]
+    fn until_waits_for_mutations() {
+        in_virtual_document(|| {
+            let document = document();
+            let div = document.create_element("div");
+            let mut pool = futures::executor::LocalPool::new();
+
+            let (container, placeholder) = (div.clone(), "What needs to be done?");
+            pool.spawner()
+                .spawn_local(async move {
+                    let input = document.create_element("input");
+                    input.set_attribute("placeholder", placeholder);
+                    container.append_child(&input);
+                })
+                .unwrap();
+
+            let find = div.find();
+            let query = find.by_placeholder_text(placeholder);
+            assert!(query.one().is_err());
+
+            let input = pool.run_until(query.until().one()).unwrap();
+            assert_eq!(input.get_attribute("placeholder").unwrap(), placeholder);
+        })();
+    }
+}
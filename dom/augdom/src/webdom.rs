@@ -1,11 +1,12 @@
 //! An implementation of `augdom`'s APIs on top of the actual web using the
 //! `web-sys` crate and `wasm-bindgen`.
 
-use super::Node;
+use super::{InvalidSelector, Node, NodeType};
 use futures::{channel::mpsc::UnboundedReceiver, Stream};
 use prettiest::Pretty;
 use std::{
     any::type_name,
+    cell::Cell,
     io::Write,
     pin::Pin,
     task::{Context, Poll},
@@ -67,7 +68,12 @@ impl crate::Dom for sys::Node {
                 ))
                 .expect("writing start of element");
 
-            let children = sys::Node::child_nodes(elem.as_ref());
+            // a template's contents are written in place of its children
+            let parent = match elem.dyn_ref::<sys::HtmlTemplateElement>() {
+                Some(template) => template.content().into(),
+                None => elem.clone().into(),
+            };
+            let children = sys::Node::child_nodes(&parent);
             for i in 0..children.length() {
                 children.item(i).unwrap().write_xml(writer);
             }
@@ -81,8 +87,24 @@ impl crate::Dom for sys::Node {
                     &text.data(),
                 )))
                 .expect("writing text node");
+        } else if let Some(comment) = self.dyn_ref::<sys::Comment>() {
+            writer
+                .write_event(Event::Comment(BytesText::from_plain_str(&comment.data())))
+                .expect("writing comment node");
+        } else if let Some(doctype) = self.dyn_ref::<sys::DocumentType>() {
+            writer
+                .write_event(Event::DocType(BytesText::from_plain_str(&format!(
+                    " {}",
+                    doctype.name()
+                ))))
+                .expect("writing doctype node");
+        } else if self.dyn_ref::<sys::DocumentFragment>().is_some() {
+            let children = self.child_nodes();
+            for i in 0..children.length() {
+                children.item(i).unwrap().write_xml(writer);
+            }
         } else {
-            unreachable!("augdom only creates elements and text nodes. this is a bug.");
+            unreachable!("augdom doesn't create this kind of node. this is a bug.");
         }
     }
 
@@ -90,14 +112,38 @@ impl crate::Dom for sys::Node {
         self.first_child()
     }
 
+    fn last_child(&self) -> Option<Self> {
+        self.last_child()
+    }
+
+    fn child_nodes(&self) -> Self::Nodes {
+        NodeList { idx: 0, inner: self.child_nodes() }
+    }
+
+    fn contains(&self, other: &Self) -> bool {
+        self.contains(Some(other))
+    }
+
     fn append_child(&self, child: &Self) {
         self.append_child(child).unwrap();
     }
 
+    fn insert_before(&self, new_child: &Self, reference: Option<&Self>) {
+        self.insert_before(new_child, reference).unwrap();
+    }
+
+    fn parent_node(&self) -> Option<Self> {
+        self.parent_node()
+    }
+
     fn next_sibling(&self) -> Option<Self> {
         self.next_sibling()
     }
 
+    fn previous_sibling(&self) -> Option<Self> {
+        self.previous_sibling()
+    }
+
     fn remove_child(&self, to_remove: &Self) -> Option<Self> {
         self.remove_child(to_remove).ok()
     }
@@ -126,18 +172,47 @@ impl crate::Dom for sys::Node {
         e.map(sys::HtmlElement::inner_text).unwrap_or_default()
     }
 
+    fn node_type(&self) -> NodeType {
+        match self.node_type() {
+            sys::Node::ELEMENT_NODE => NodeType::Element,
+            sys::Node::TEXT_NODE => NodeType::Text,
+            sys::Node::COMMENT_NODE => NodeType::Comment,
+            sys::Node::DOCUMENT_NODE => NodeType::Document,
+            sys::Node::DOCUMENT_TYPE_NODE => NodeType::DocumentType,
+            sys::Node::DOCUMENT_FRAGMENT_NODE => NodeType::DocumentFragment,
+            other => NodeType::Other(other),
+        }
+    }
+
+    fn get_node_value(&self) -> Option<String> {
+        self.node_value()
+    }
+
+    fn set_node_value(&self, value: &str) {
+        self.set_node_value(Some(value));
+    }
+
+    fn template_content(&self) -> Option<Self> {
+        let template: Option<&sys::HtmlTemplateElement> = self.dyn_ref();
+        template.map(|t| t.content().into())
+    }
+
     fn dispatch<E: crate::event::Event>(&self, event: E) {
         event.dispatch(self);
     }
 
-    fn query_selector(&self, selectors: &str) -> Option<Self> {
+    fn query_selector(&self, selectors: &str) -> Result<Option<Self>, InvalidSelector> {
         let e: &sys::Element = self.dyn_ref().unwrap();
-        sys::Element::query_selector(e, selectors).unwrap().map(Into::into)
+        let found = sys::Element::query_selector(e, selectors)
+            .map_err(|e| InvalidSelector::new(selectors, format!("{:?}", e)))?;
+        Ok(found.map(Into::into))
     }
 
-    fn query_selector_all(&self, selectors: &str) -> Self::Nodes {
+    fn query_selector_all(&self, selectors: &str) -> Result<Self::Nodes, InvalidSelector> {
         let e: &sys::Element = self.dyn_ref().unwrap();
-        NodeList { idx: 0, inner: sys::Element::query_selector_all(e, selectors).unwrap() }
+        let inner = sys::Element::query_selector_all(e, selectors)
+            .map_err(|e| InvalidSelector::new(selectors, format!("{:?}", e)))?;
+        Ok(NodeList { idx: 0, inner })
     }
 
     fn observe_mutations(&self) -> Self::Observer {
@@ -182,6 +257,26 @@ impl Node {
     }
 }
 
+thread_local! {
+    /// Numbers assigned to nodes which have been hashed, and the next number.
+    static IDENTITIES: (js_sys::WeakMap, Cell<u64>) = (js_sys::WeakMap::new(), Cell::new(0));
+}
+
+/// Returns a number which identifies `node` for as long as it's alive, allowing
+/// nodes to be hashed without comparing them with each other through JS.
+pub(crate) fn identity(node: &sys::Node) -> u64 {
+    IDENTITIES.with(|(identities, next)| {
+        let object: &js_sys::Object = node.as_ref();
+        if let Some(identity) = identities.get(object).as_f64() {
+            return identity as u64;
+        }
+        let identity = next.get();
+        next.set(identity + 1);
+        identities.set(object, &JsValue::from(identity as f64));
+        identity
+    })
+}
+
 /// Wraps [`sys::NodeList`] to implement `Iterator`.
 pub struct NodeList {
     inner: sys::NodeList,
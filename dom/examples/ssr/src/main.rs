@@ -3,11 +3,12 @@ extern crate gotham_derive;
 #[macro_use]
 extern crate serde_derive;
 
-use augdom::Dom;
+use augdom::{rsdom::HtmlOptions, Document};
 use gotham::{
     router::{builder::*, Router},
     state::{FromState, State},
 };
+use mime::Mime;
 use mox::mox;
 use moxie_dom::{
     elements::text_content::{li, ul, Ul},
@@ -36,15 +37,15 @@ fn simple_list(items: &[String]) -> Ul {
     list.build()
 }
 
-fn parts_handler(state: State) -> (State, String) {
+fn parts_handler(state: State) -> (State, (Mime, String)) {
     let parts = {
         let path = PathExtractor::borrow_from(&state);
         path.parts.to_owned()
     };
-    let web_div = augdom::create_virtual_element("div");
-    let mut renderer = DomLoop::new_virtual(web_div.clone(), move || simple_list(&parts));
+    let document = Document::new_virtual();
+    let mut renderer = DomLoop::new_virtual(document.body(), move || simple_list(&parts));
     renderer.run_once();
-    (state, web_div.pretty_outer_html(2))
+    (state, (mime::TEXT_HTML_UTF_8, document.to_html(&HtmlOptions::new())))
 }
 
 fn router() -> Router {
@@ -56,6 +57,7 @@ fn router() -> Router {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use augdom::Dom;
     use gotham::test::TestServer;
     use hyper::StatusCode;
 
@@ -65,15 +67,12 @@ mod tests {
         let response = test_server.client().get("http://localhost/parts/head").perform().unwrap();
 
         assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["content-type"], "text/html; charset=utf-8");
 
         let body = String::from_utf8(response.read_body().unwrap()).unwrap();
         assert_eq!(
             &body,
-            r#"<div>
-  <ul>
-    <li>head</li>
-  </ul>
-</div>"#,
+            "<!DOCTYPE html><html><head></head><body><ul><li>head</li></ul></body></html>",
         );
     }
 
@@ -91,14 +90,9 @@ mod tests {
         let body = String::from_utf8(response.read_body().unwrap()).unwrap();
         assert_eq!(
             &body,
-            &r#"<div>
-  <ul>
-    <li>head</li>
-    <li>shoulders</li>
-    <li>knees</li>
-    <li>toes</li>
-  </ul>
-</div>"#,
+            "<!DOCTYPE html><html><head></head><body><ul>\
+             <li>head</li><li>shoulders</li><li>knees</li><li>toes</li>\
+             </ul></body></html>",
         );
     }
 
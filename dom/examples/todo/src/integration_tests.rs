@@ -62,6 +62,7 @@ impl Test {
 
     fn todos(&self) -> Vec<String> {
         self.query_selector_all(".todo-list li")
+            .unwrap()
             .iter()
             .map(|t| t.get_inner_text())
             .collect::<Vec<_>>()
@@ -3,7 +3,8 @@
 use augdom::{Dom, Node};
 use moxie::cache_with;
 use std::{
-    cell::Cell,
+    cell::RefCell,
+    collections::HashMap,
     fmt::{Debug, Formatter, Result as FmtResult},
 };
 
@@ -14,14 +15,19 @@ use std::{
 /// nodes, adhering fairly closely to the upstream web specs.
 pub struct CachedNode {
     id: topo::CallId,
-    last_child: Cell<Option<Node>>,
+    /// The children declared for this node in the current revision.
+    children: RefCell<Vec<Node>>,
     node: Node,
+    /// The node to which children are attached, either `node` itself or a
+    /// `<template>`'s contents.
+    content: Node,
 }
 
 impl CachedNode {
     #[topo::nested]
     pub(crate) fn new(node: Node) -> Self {
-        Self { node, last_child: Cell::new(None), id: topo::CallId::current() }
+        let content = node.template_content().unwrap_or_else(|| node.clone());
+        Self { node, content, children: RefCell::new(vec![]), id: topo::CallId::current() }
     }
 
     pub(crate) fn raw_node(&self) -> &Node {
@@ -55,43 +61,88 @@ impl CachedNode {
         }
     }
 
-    pub(crate) fn ensure_child_attached(&self, new_child: &Node) {
-        let prev_sibling = self.last_child.replace(Some(new_child.clone()));
+    /// Declare `new_child` as the next child of this node. Children are
+    /// attached when [`CachedNode::reconcile_children`] is called.
+    pub(crate) fn push_child(&self, new_child: &Node) {
+        self.children.borrow_mut().push(new_child.clone());
+    }
 
-        let existing = if prev_sibling.is_none() {
-            self.node.first_child()
-        } else {
-            prev_sibling.and_then(|p| p.next_sibling())
-        };
+    /// Update this node's children to match those declared with
+    /// [`CachedNode::push_child`], removing any others.
+    ///
+    /// Children are matched by identity, so a child which is cached in a keyed
+    /// slot (e.g. with `topo::call_in_slot` or `#[topo::nested(slot = ...)]`)
+    /// is moved rather than re-created when its position changes. Only the
+    /// children outside of the longest run which kept their relative order are
+    /// moved, so their DOM state like focus and input values is preserved.
+    pub(crate) fn reconcile_children(&self) {
+        let desired = self.children.borrow();
+        let current = self.content.child_nodes();
 
-        if let Some(ref existing) = existing {
-            if existing != new_child {
-                self.node.replace_child(new_child, existing);
+        // the common prefix and suffix are already in place
+        let prefix = desired.iter().zip(&current).take_while(|(d, c)| d == c).count();
+        let suffix = desired[prefix..]
+            .iter()
+            .rev()
+            .zip(current[prefix..].iter().rev())
+            .take_while(|(d, c)| d == c)
+            .count();
+        let desired_middle = &desired[prefix..desired.len() - suffix];
+        let current_middle = &current[prefix..current.len() - suffix];
+
+        // nodes hash by identity, so finding each child doesn't compare it to every other
+        #[allow(clippy::mutable_key_type)] // hashing ignores the mutable contents
+        let indices =
+            current_middle.iter().enumerate().map(|(i, c)| (c, i)).collect::<HashMap<_, _>>();
+        let positions = desired_middle.iter().map(|d| indices.get(d).copied()).collect::<Vec<_>>();
+
+        let mut kept = vec![false; current_middle.len()];
+        for &position in positions.iter().flatten() {
+            kept[position] = true;
+        }
+        for (to_remove, _) in current_middle.iter().zip(&kept).filter(|(_, &kept)| !kept) {
+            self.content.remove_child(to_remove).unwrap();
+        }
+
+        // insert in reverse so that each child's next sibling is already in place
+        let stable = longest_increasing(&positions);
+        let mut next_sibling = current.get(current.len() - suffix).cloned();
+        for (child, stable) in desired_middle.iter().zip(stable).rev() {
+            if !stable {
+                self.content.insert_before(child, next_sibling.as_ref());
             }
-        } else {
-            self.node.append_child(new_child);
+            next_sibling = Some(child.clone());
         }
     }
+}
 
-    pub(crate) fn remove_trailing_children(&self) {
-        let last_desired_child = self.last_child.replace(None);
-
-        // if there weren't any children declared this revision, we need to
-        // make sure we clean up any from the last revision
-        let mut next_to_remove = if let Some(c) = last_desired_child {
-            // put back the last node we found this revision so this can be called multiple
-            // times
-            self.last_child.set(Some(c.clone()));
-            c.next_sibling()
-        } else {
-            self.node.first_child()
+/// Returns whether each position is part of the longest strictly increasing
+/// subsequence of `positions`, ignoring `None`s.
+fn longest_increasing(positions: &[Option<usize>]) -> Vec<bool> {
+    // tails[len] is the index of the smallest position ending a run of len + 1
+    let mut tails: Vec<usize> = vec![];
+    let mut predecessors = vec![None; positions.len()];
+    for (idx, position) in positions.iter().enumerate() {
+        let position = match position {
+            Some(p) => *p,
+            None => continue,
         };
-
-        while let Some(to_remove) = next_to_remove {
-            next_to_remove = to_remove.next_sibling();
-            self.node.remove_child(&to_remove).unwrap();
+        let len = tails.partition_point(|&t| positions[t].unwrap() < position);
+        predecessors[idx] = len.checked_sub(1).map(|prev| tails[prev]);
+        if len == tails.len() {
+            tails.push(idx);
+        } else {
+            tails[len] = idx;
         }
     }
+
+    let mut in_sequence = vec![false; positions.len()];
+    let mut current = tails.last().copied();
+    while let Some(idx) = current {
+        in_sequence[idx] = true;
+        current = predecessors[idx];
+    }
+    in_sequence
 }
 
 impl Debug for CachedNode {
@@ -102,10 +153,147 @@ impl Debug for CachedNode {
 
 #[cfg(test)]
 mod tests {
+    use super::longest_increasing;
     use crate::{elements::just_all_of_it_ok::div, prelude::*};
     use mox::mox;
     use moxie::{runtime::RunLoop, state};
 
+    #[test]
+    fn longest_increasing_positions() {
+        let stable = |positions: &[Option<usize>]| {
+            let flags = longest_increasing(positions);
+            flags.iter().enumerate().filter(|(_, s)| **s).map(|(i, _)| i).collect::<Vec<_>>()
+        };
+        assert_eq!(stable(&[]), [] as [usize; 0]);
+        assert_eq!(stable(&[Some(0), Some(1), Some(2)]), [0, 1, 2]);
+        assert_eq!(stable(&[Some(3), Some(0), Some(1), Some(2)]), [1, 2, 3]);
+        assert_eq!(stable(&[Some(1), Some(2), Some(3), Some(0)]), [0, 1, 2]);
+        assert_eq!(stable(&[None, Some(2), None, Some(0), Some(1)]), [3, 4]);
+        assert_eq!(stable(&[Some(2), Some(1), Some(0)]).len(), 1);
+    }
+
+    #[cfg(feature = "rsdom")]
+    mod keyed {
+        use crate::{elements::just_all_of_it_ok::*, prelude::*};
+        use augdom::{rsdom::Mutation, MutationRecord};
+        use futures::{FutureExt, StreamExt};
+        use mox::mox;
+        use moxie::runtime::RunLoop;
+        use std::{cell::RefCell, rc::Rc};
+
+        struct KeyedList {
+            ids: Rc<RefCell<Vec<u32>>>,
+            rt: RunLoop<Box<dyn FnMut() -> RawNode>>,
+        }
+
+        impl KeyedList {
+            fn new(ids: &[u32]) -> Self {
+                let ids = Rc::new(RefCell::new(ids.to_vec()));
+                let list_ids = ids.clone();
+                let root = augdom::in_virtual_document(move || {
+                    let mut list = ul();
+                    for id in list_ids.borrow().iter() {
+                        list = list.child(topo::call_in_slot(id, || mox!(<li>{% "{}", id }</li>)));
+                    }
+                    list.build().raw_node_that_has_sharp_edges_please_be_careful().clone()
+                });
+                Self { ids, rt: RunLoop::new(Box::new(root)) }
+            }
+
+            /// Render with `ids`, returning the rendered children and the number
+            /// of nodes added and removed.
+            fn render(&mut self, ids: &[u32]) -> (Vec<RawNode>, usize, usize) {
+                let list = self.rt.run_once();
+                let mut mutations = list.observe_mutations();
+                *self.ids.borrow_mut() = ids.to_vec();
+                let list = self.rt.run_once();
+
+                let (mut added, mut removed) = (0, 0);
+                while let Some(Some(batch)) = mutations.next().now_or_never() {
+                    for record in batch {
+                        if let MutationRecord::Virtual(Mutation::ChildList {
+                            target,
+                            added_nodes,
+                            removed_nodes,
+                            ..
+                        }) = record
+                        {
+                            if RawNode::Virtual(target) == list {
+                                added += added_nodes.len();
+                                removed += removed_nodes.len();
+                            }
+                        }
+                    }
+                }
+
+                let children = list.child_nodes();
+                let texts = children.iter().map(|c| c.get_inner_text()).collect::<Vec<_>>();
+                let expected = ids.iter().map(|id| id.to_string()).collect::<Vec<_>>();
+                assert_eq!(texts, expected);
+                (children, added, removed)
+            }
+        }
+
+        #[test]
+        fn insert_at_head_adds_one_node() {
+            let mut list = KeyedList::new(&[]);
+            let ids = (1..=1000).collect::<Vec<_>>();
+            let (before, ..) = list.render(&ids);
+
+            let with_head = std::iter::once(0).chain(ids).collect::<Vec<_>>();
+            let (after, added, removed) = list.render(&with_head);
+            assert_eq!((added, removed), (1, 0));
+            assert_eq!(&after[1..], &before[..]);
+        }
+
+        #[test]
+        fn reorders_move_few_nodes() {
+            let mut list = KeyedList::new(&[]);
+            let (before, ..) = list.render(&[1, 2, 3, 4, 5]);
+
+            // moving the first child to the end only moves that child
+            let (after, added, removed) = list.render(&[2, 3, 4, 5, 1]);
+            assert_eq!((added, removed), (1, 1));
+            assert_eq!(after[4], before[0]);
+            assert_eq!(after[..4], before[1..]);
+
+            // swapping two children moves both
+            let (after, added, removed) = list.render(&[1, 3, 4, 5, 2]);
+            assert_eq!((added, removed), (2, 2));
+            assert_eq!(after[0], before[0]);
+            assert_eq!(after[4], before[1]);
+        }
+
+        #[test]
+        fn reversing_keeps_one_node_in_place() {
+            let mut list = KeyedList::new(&[]);
+            let ids = (1..=1000).collect::<Vec<_>>();
+            let (before, ..) = list.render(&ids);
+
+            let reversed = ids.into_iter().rev().collect::<Vec<_>>();
+            let (after, added, removed) = list.render(&reversed);
+            assert_eq!((added, removed), (999, 999));
+            assert!(after.iter().eq(before.iter().rev()));
+        }
+
+        #[test]
+        fn removed_and_new_children() {
+            let mut list = KeyedList::new(&[]);
+            let (before, ..) = list.render(&[1, 2, 3, 4]);
+
+            // 1 and 3 are removed, 5 is added, and 4 is moved
+            let (after, added, removed) = list.render(&[4, 5, 2]);
+            assert_eq!((added, removed), (2, 3));
+            assert_eq!(after[0], before[3]);
+            assert_eq!(after[2], before[1]);
+            assert_eq!(before[0].parent_node(), None);
+            assert_eq!(before[2].parent_node(), None);
+
+            let (after, added, removed) = list.render(&[]);
+            assert_eq!((after.len(), added, removed), (0, 0, 3));
+        }
+    }
+
     #[wasm_bindgen_test::wasm_bindgen_test]
     pub fn attributes_change() {
         let mut rt = RunLoop::new(|| {
@@ -21,7 +21,9 @@ pub mod html {
             address, article, aside, footer, h1, h2, h3, h4, h5, h6, header, hgroup, main, nav,
             section,
         },
+        slot,
         table::{caption, col, colgroup, table, tbody, td, tfoot, th, thead, tr},
+        template,
         text_content::{blockquote, dd, div, dl, dt, figcaption, figure, hr, li, ol, p, pre, ul},
         text_semantics::{
             a, abbr, b, bdi, bdo, br, cite, code, data, del, dfn, em, i, ins, kbd, mark, q, rb, rp,
@@ -149,4 +151,10 @@ html_element! {
     categories {
         Metadata, Flow, Phrasing
     }
+
+    children {
+        categories {
+            Flow
+        }
+    }
 }
@@ -33,8 +33,8 @@ impl DomLoop {
             #[allow(clippy::redundant_closure)] // removing the closure syntax makes this FnOnce
             let new_root = topo::call(|| root());
 
-            parent.ensure_child_attached(new_root.to_bind());
-            parent.remove_trailing_children();
+            parent.push_child(new_root.to_bind());
+            parent.reconcile_children();
         }) as Box<dyn FnMut()>);
 
         #[cfg(feature = "webdom")]
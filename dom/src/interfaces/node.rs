@@ -83,7 +83,7 @@ where
 pub trait Parent<C: Child>: NodeWrapper {
     /// Add a child to this node.
     fn child<T: NodeBuilder<Output = C>>(self, child: T) -> Self {
-        self.node().ensure_child_attached(child.build().to_bind());
+        self.node().push_child(child.build().to_bind());
         self
     }
 }
@@ -126,7 +126,7 @@ macro_rules! element {
             /// Initialize the element with all of the attributes so far.
             fn build(self) -> [<$name:camel>] {
                 use crate::interfaces::node::sealed::Memoized;
-                self.node().remove_trailing_children();
+                self.node().reconcile_children();
 
                 [<$name:camel>] { inner: self.inner }
             }
@@ -1,6 +1,6 @@
 use mox::mox;
 use moxie_dom::{
-    elements::html::div,
+    elements::html::{div, template},
     interfaces::node::{Child, NodeBuilder},
     prelude::*,
 };
@@ -37,3 +37,12 @@ pub async fn simple_builder() {
 pub async fn built_builder() {
     render_test(|| div().child(text("test text").build()).build(), "<div>test text</div>").await;
 }
+
+#[wasm_bindgen_test]
+pub async fn template_content() {
+    render_test(
+        || mox!(<template><div>"inert"</div></template>),
+        "<template><div>inert</div></template>",
+    )
+    .await;
+}
@@ -202,6 +202,12 @@ Call [`" stringify!($cache) "::get`] to get a [`CacheMiss`] and [`CacheMiss::ini
         self.inner.values_mut().for_each(|namespace| namespace.sweep());
         self.revision += 1;
     }
+
+    /// Visit each value stored in the cache, including those which haven't been marked alive
+    /// since the last GC.
+    pub fn inspect(&self, mut visit: impl FnMut(Stored<'_>)) {
+        self.inner.values().for_each(|namespace| namespace.inspect(&mut visit));
+    }
 }
 
 impl std::panic::UnwindSafe for $cache {}
@@ -297,10 +303,15 @@ See [`" stringify!($shared) "::cache`] for an ergonomic wrapper that requires `O
         Output: 'static $(+ $bound)?,
         Ret: 'static $(+ $bound)?,
     {
+        let output = std::any::type_name::<Output>();
         let miss = match { self.inner.$acquire().get(key, arg) } {
-            Ok(stored) => return with(stored),
+            Ok(stored) => {
+                tracing::trace!(output, "cache hit");
+                return with(stored);
+            }
             Err(m) => m,
         };
+        tracing::trace!(output, "cache miss");
 
         let (to_store, to_return) = miss.init(|arg| {
             let store = init(arg);
@@ -312,6 +323,46 @@ See [`" stringify!($shared) "::cache`] for an ergonomic wrapper that requires `O
         to_return
     }}
 
+doc_comment!{r"
+Caches the result of `init(arg)` once per `key` like [`" stringify!($shared) "::cache_with`], but
+only stores the `Output` if `init` succeeds.
+
+If `init` returns an error, any value previously stored for `key` is kept and the error is
+returned. The previous value's dependencies are retained by the next GC as if it had been read
+without being re-initialized, even when they weren't read while running `init`.
+"=>
+    pub fn try_cache_with<Key, Scope, Arg, Input, Output, Ret, Error>(
+        &self,
+        key: &Key,
+        arg: &Arg,
+        init: impl FnOnce(&Input) -> Result<Output, Error>,
+        with: impl FnOnce(&Output) -> Ret,
+    ) -> Result<Ret, Error>
+    where
+        Key: Eq + Hash + ToOwned<Owned = Scope> + ?Sized,
+        Scope: 'static + Borrow<Key> + Eq + Hash $(+ $bound)?,
+        Arg: PartialEq<Input> + ToOwned<Owned=Input> + ?Sized,
+        Input: 'static + Borrow<Arg> $(+ $bound)?,
+        Output: 'static $(+ $bound)?,
+        Ret: 'static $(+ $bound)?,
+    {
+        let output = std::any::type_name::<Output>();
+        let miss = match { self.inner.$acquire().get(key, arg) } {
+            Ok(stored) => {
+                tracing::trace!(output, "cache hit");
+                return Ok(with(stored));
+            }
+            Err(m) => m,
+        };
+        tracing::trace!(output, "cache miss");
+
+        let to_store = miss.try_init(init)?;
+        let to_return = with(&to_store.output);
+
+        self.inner.$acquire().store(to_store);
+        Ok(to_return)
+    }}
+
 doc_comment!{r"
 Caches the result of `init(arg)` once per `key`, re-running it when `arg` changes. Clones
 the cached output before returning the result.
@@ -365,6 +416,14 @@ Forwards to [`" stringify!($cache) "::gc`].
         self.inner.$acquire().gc();
     }}
 
+doc_comment!{"
+Forwards to [`" stringify!($cache) "::inspect`]. The cache is locked while `visit` runs, so it
+must not query the cache itself.
+"=>
+    pub fn inspect(&self, visit: impl FnMut(Stored<'_>)) {
+        self.inner.$acquire().inspect(visit);
+    }}
+
     fn addr(&self) -> usize {
         $refct::as_ptr(&self.inner) as *const _ as _
     }
@@ -600,6 +659,63 @@ mod $test_mod {
         assert_eq!(once_calls.load(Ordering::SeqCst), 1);
         assert_eq!(once_drops.load(Ordering::SeqCst), 1);
     }
+
+    #[test]
+    fn failed_try_cache_retains_previous_children() {
+        let once_drops = Arc::new(AtomicU32::new(0));
+        let storage = $shared::default();
+
+        let mut i = 0; // a unique value for the cache
+        let drop_adder = once_drops.clone();
+        let mut tick = move |should_fail| {
+            let result = storage.try_cache_with(&(), &i, |&i| {
+                if i == 0 {
+                    storage.hold(&(), &(), |_| {
+                        Arc::new(CountDrops { num_drops: drop_adder.clone() })
+                    });
+                }
+                if should_fail { Err(()) } else { Ok(()) }
+            }, |_| {});
+            storage.gc();
+            i += 1;
+            result
+        };
+
+        assert_eq!(tick(false), Ok(()));
+        assert_eq!(tick(true), Err(()));
+        assert_eq!(tick(true), Err(()));
+        assert_eq!(once_drops.load(Ordering::SeqCst), 0, "failures retain the child");
+
+        assert_eq!(tick(false), Ok(()));
+        assert_eq!(once_drops.load(Ordering::SeqCst), 1, "success replaces the child");
+    }
+
+    #[test]
+    fn inspect_visits_stored_values() {
+        let storage = $shared::default();
+        storage.cache(&'a', &1u8, |&n| u32::from(n));
+        storage.cache(&'b', &1u8, |&n| u32::from(n));
+        storage.cache(&1u64, &(), |()| String::new());
+
+        let mut visited = vec![];
+        storage.inspect(|stored| {
+            let scope = stored.scope::<char>().map(|c| c.to_string());
+            visited.push((scope, stored.input_type(), stored.output_type()));
+        });
+        visited.sort();
+        assert_eq!(visited, vec![
+            (None, "()", "alloc::string::String"),
+            (Some(String::from("a")), "u8", "u32"),
+            (Some(String::from("b")), "u8", "u32"),
+        ]);
+
+        storage.gc();
+        storage.cache(&'a', &1u8, |&n| u32::from(n));
+        storage.gc();
+        let mut count = 0;
+        storage.inspect(|_| count += 1);
+        assert_eq!(count, 1, "only values which survived gc are visited");
+    }
 }
     };
 }
@@ -223,6 +223,11 @@
 //! value is used if it or a value which depends on it has been used/rooted
 //! since the last call to `gc()`.
 //!
+//! Each `gc()` emits a debug-level [`tracing`] event for every query type
+//! which had values dropped, and each query through a shared cache's
+//! `cache_with` or `try_cache_with` emits a trace-level `cache hit` or
+//! `cache miss` event.
+//!
 //! ```
 //! let storage = dyn_cache::local::SharedLocalCache::default();
 //! let a_scope = 'a';
@@ -328,7 +333,7 @@ use downcast_rs::{impl_downcast, Downcast};
 use hash_hasher::HashBuildHasher;
 use hashbrown::hash_map::DefaultHashBuilder;
 use std::{
-    any::TypeId,
+    any::{Any, TypeId},
     fmt::{Debug, Formatter, Result as FmtResult},
     hash::{BuildHasher, Hash, Hasher},
     marker::PhantomData,
@@ -389,6 +394,26 @@ impl<'k, Key: ?Sized, Scope, Input, Output, H> CacheMiss<'k, Key, Scope, Input,
         let (output, to_return) = self.key_miss.init(query);
         (CacheEntry { output, miss: self }, to_return)
     }
+
+    /// Prepare the cache miss to be populated by running `query(arg)` like
+    /// [`CacheMiss::init`], returning the error instead if `query` fails.
+    ///
+    /// ```
+    /// # use dyn_cache::local::LocalCache;
+    /// # let mut cache = LocalCache::default();
+    /// # let (scope, arg) = (&'a', &"1");
+    /// let miss = cache.get::<_, _, _, _, u8>(scope, arg).unwrap_err();
+    /// let entry = miss.try_init(|n| n.parse()).unwrap();
+    /// cache.store(entry);
+    /// assert_eq!(cache.get(scope, arg).ok(), Some(&1u8));
+    /// ```
+    pub fn try_init<E>(
+        self,
+        query: impl FnOnce(&Input) -> Result<Output, E>,
+    ) -> Result<CacheEntry<'k, Key, Scope, Input, Output, H>, E> {
+        let output = self.key_miss.init(query)?;
+        Ok(CacheEntry { output, miss: self })
+    }
 }
 
 impl<'k, Key, Scope, Input, Output, H> Debug for CacheMiss<'k, Key, Scope, Input, Output, H>
@@ -414,6 +439,48 @@ pub struct CacheEntry<'k, Key: ?Sized, Scope, Input, Output, H = DefaultHashBuil
     output: Output,
 }
 
+/// A value stored in a cache, as visited by [`local::LocalCache::inspect`] or
+/// [`sync::SendCache::inspect`].
+#[derive(Clone, Copy)]
+pub struct Stored<'a> {
+    scope: &'a dyn Any,
+    scope_type: &'static str,
+    input_type: &'static str,
+    output_type: &'static str,
+}
+
+impl<'a> Stored<'a> {
+    /// Returns the scope the value is stored under, if it has type `Scope`.
+    pub fn scope<Scope: 'static>(&self) -> Option<&'a Scope> {
+        self.scope.downcast_ref()
+    }
+
+    /// Returns the name of the scope's type.
+    pub fn scope_type(&self) -> &'static str {
+        self.scope_type
+    }
+
+    /// Returns the name of the stored input's type.
+    pub fn input_type(&self) -> &'static str {
+        self.input_type
+    }
+
+    /// Returns the name of the stored output's type.
+    pub fn output_type(&self) -> &'static str {
+        self.output_type
+    }
+}
+
+impl Debug for Stored<'_> {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.debug_struct("Stored")
+            .field("scope_type", &self.scope_type)
+            .field("input_type", &self.input_type)
+            .field("output_type", &self.output_type)
+            .finish()
+    }
+}
+
 /// A cache for types which are not thread-safe (`?Send`).
 pub mod local {
     use std::{cell::RefCell, rc::Rc};
@@ -436,6 +503,9 @@ trait Storage: Downcast + Debug {
 
     /// Remove dead entries.
     fn sweep(&mut self);
+
+    /// Visit each stored value.
+    fn inspect(&self, visit: &mut dyn FnMut(Stored<'_>));
 }
 
 impl_downcast!(Storage);
@@ -1,7 +1,7 @@
 use super::{
     cache_cell::CacheCell,
     dep_node::{DepNode, Dependent},
-    Storage,
+    Storage, Stored,
 };
 use hashbrown::{
     hash_map::{DefaultHashBuilder, RawEntryMut},
@@ -193,11 +193,34 @@ where
     }
 
     fn sweep(&mut self) {
+        let before = self.inner.len();
         self.inner.retain(|_, c| {
             let keep = c.is_live();
             c.mark_dead();
             keep
         });
+
+        let dropped = before - self.inner.len();
+        if dropped > 0 {
+            tracing::debug!(
+                scope = type_name::<Scope>(),
+                input = type_name::<Input>(),
+                output = type_name::<Output>(),
+                dropped,
+                "cache gc dropped values"
+            );
+        }
+    }
+
+    fn inspect(&self, visit: &mut dyn FnMut(Stored<'_>)) {
+        for scope in self.inner.keys() {
+            visit(Stored {
+                scope,
+                scope_type: type_name::<Scope>(),
+                input_type: type_name::<Input>(),
+                output_type: type_name::<Output>(),
+            });
+        }
     }
 }
 
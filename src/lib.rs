@@ -31,7 +31,11 @@
 //! supported by the [`cache`], [`cache_with`], [`once`], and [`once_with`]
 //! functions. Values returned from cached closures are available in subsequent
 //! [`runtime::Revision`]s at the same callsite and are dropped from the cache
-//! at the end of the first revision where they were not used.
+//! at the end of the first revision where they were not used. The [`memo`]
+//! function tracks the state variables read by its closure and re-runs it when
+//! any of them change, and [`restartable`] uses the same tracking to skip
+//! entire subtrees of the root function which haven't changed. Subtrees run
+//! with [`error_boundary`] keep their cached values while they're failing.
 //!
 //! ## State
 //!
@@ -39,7 +43,17 @@
 //! revisions. They are declared with the [`cache_state`] and [`state`]
 //! functions which return a [`Commit`] for reading the current value and a
 //! [`Key`] for updating it. Updates to state variables wake the runtime,
-//! initiating a new revision.
+//! initiating a new revision. State variables declared with
+//! [`persisted_state`] can be saved and restored with [`runtime::Snapshot`]s,
+//! and those declared with [`history_state`] can be undone, redone, and
+//! reverted to the values they had in earlier revisions.
+//!
+//! ## Effects
+//!
+//! Side effects which should only happen once a revision has finished, like
+//! subscribing to an external event source, are declared with [`effect`]. Each
+//! effect runs after the revision in which its argument changes and cleans up
+//! after itself before running again or when it is no longer declared.
 //!
 //! ## Loading Futures
 //!
@@ -48,7 +62,31 @@
 //! future is spawned to an async executor and return its status on every
 //! revision. When the future has completed, `Poll::Ready` is returned on
 //! each revision. If a revision occurs without referencing the pending future,
-//! the task is cancelled.
+//! the task is cancelled. Streams, like those of messages from a socket, can
+//! be subscribed to in the same way with [`subscribe`] and [`subscribe_with`],
+//! which return the latest item produced. A subtree can be wrapped in a [`suspense`] boundary
+//! which shows a fallback until all of its loads have resolved, rather than
+//! handling each pending load separately.
+//!
+//! ## Threads
+//!
+//! The functions in this module store values in a thread-local cache. The
+//! [`sync`] module has thread-safe counterparts for use in a
+//! [`runtime::SendRuntime`], which can be moved between threads.
+//!
+//! ## Profiling
+//!
+//! The runtime emits [`tracing`] spans for each revision and nested call along
+//! with events for cache reads and commits to state variables. With the
+//! `profiling` feature enabled, the `profiling` module can record them in a
+//! format which can be viewed as flamegraphs.
+//!
+//! ## Inspecting
+//!
+//! [`runtime::Runtime::inspect`] returns the call tree of the most recent
+//! revision with each call's cached values and state variables, which can be
+//! dumped as text. Debugging tools can change state variables from outside the
+//! tree with [`runtime::Runtime::set_state`].
 //!
 //! [moxie-dom]: https://docs.rs/moxie-dom
 //! [topo]: https://docs.rs/topo/
@@ -56,10 +94,14 @@
 #![forbid(unsafe_code)]
 #![deny(clippy::all, missing_docs)]
 
+#[cfg(feature = "profiling")]
+pub mod profiling;
 pub mod runtime;
+pub mod sync;
 pub mod testing;
 
-use crate::runtime::{Context, Var};
+use crate::runtime::{Context, Suspense, Var};
+use futures::Stream;
 use parking_lot::Mutex;
 use std::{
     borrow::Borrow,
@@ -67,7 +109,7 @@ use std::{
     future::Future,
     hash::{Hash, Hasher},
     ops::Deref,
-    sync::Arc,
+    sync::{atomic::AtomicU64, Arc},
     task::Poll,
 };
 use topo::CallId;
@@ -264,6 +306,215 @@ where
     rt.cache.cache(&CallId::current(), &(), |()| init())
 }
 
+/// Memoizes `init` at this callsite, re-running it only when one of the state
+/// variables it read has received a commit since the last run.
+///
+/// Unlike [`cache`], there's no need to pass the values which `init` depends on
+/// as an argument. Reads of state variables through a [`Commit`] or [`Key`],
+/// or by declaring them inside `init`, are recorded each time `init` runs.
+/// Memoized values read inside `init` contribute their own dependencies, so
+/// memos can be composed.
+///
+/// # Example
+///
+/// ```
+/// use moxie::{memo, runtime::RunLoop, state};
+/// use std::cell::Cell;
+///
+/// let num_computed = Cell::new(0);
+/// let mut rt = RunLoop::new(|| {
+///     let (_, first) = state(|| String::from("Ada"));
+///     let (_, last) = state(|| String::from("Lovelace"));
+///     let full_name = memo(|| {
+///         num_computed.set(num_computed.get() + 1);
+///         format!("{} {}", *first, *last)
+///     });
+///     (full_name, last)
+/// });
+///
+/// let (full_name, last) = rt.run_once();
+/// assert_eq!(full_name, "Ada Lovelace");
+/// rt.run_once();
+/// assert_eq!(num_computed.get(), 1, "no state has changed");
+///
+/// last.set(String::from("King"));
+/// assert_eq!(rt.run_once().0, "Ada King");
+/// assert_eq!(num_computed.get(), 2);
+/// ```
+#[topo::nested]
+#[illicit::from_env(rt: &Context)]
+pub fn memo<Output>(init: impl FnOnce() -> Output) -> Output
+where
+    Output: Clone + 'static,
+{
+    rt.memo(&CallId::current(), init)
+}
+
+/// Runs `body` as a restartable scope, reusing its previous output without
+/// calling it unless `arg` has changed or a state variable read inside `body`
+/// has received a commit.
+///
+/// Wrapping the body of a [`topo::nested`] function in a restartable scope
+/// lets the runtime skip that whole subtree of the root function, including
+/// its topological bookkeeping, in revisions where nothing it depends on has
+/// changed. Values cached inside the scope are retained while it is skipped.
+///
+/// Like [`memo`], reads of state variables are tracked through [`Key`]s and
+/// state variables declared inside the scope. Any other inputs, like values
+/// from the environment or [`Commit`]s captured from outside the scope, must
+/// be passed as `arg` to be observed.
+///
+/// Restartable scopes are also where revisions run with
+/// [`runtime::Runtime::run_until`] or [`runtime::Runtime::run_with_budget`]
+/// can yield once their budget is exhausted.
+///
+/// # Example
+///
+/// ```
+/// use moxie::{restartable, runtime::RunLoop, state, Key};
+/// use std::cell::Cell;
+///
+/// let rows_run = Cell::new(0);
+///
+/// #[topo::nested]
+/// fn row(label: &str, rows_run: &Cell<u32>) -> (String, Key<u32>) {
+///     restartable(label, |label| {
+///         rows_run.set(rows_run.get() + 1);
+///         let (clicks, clicked) = state(|| 0);
+///         (format!("{}: {}", label, clicks), clicked)
+///     })
+/// }
+///
+/// let mut rt = RunLoop::new(|| (row("first", &rows_run), row("second", &rows_run)));
+///
+/// let ((first, first_clicks), (second, _)) = rt.run_once();
+/// assert_eq!((first.as_str(), second.as_str()), ("first: 0", "second: 0"));
+/// assert_eq!(rows_run.get(), 2);
+///
+/// rt.run_once();
+/// assert_eq!(rows_run.get(), 2, "neither row has changed");
+///
+/// first_clicks.set(1);
+/// let ((first, _), (second, _)) = rt.run_once();
+/// assert_eq!((first.as_str(), second.as_str()), ("first: 1", "second: 0"));
+/// assert_eq!(rows_run.get(), 3, "only the first row was run again");
+/// ```
+#[topo::nested]
+#[illicit::from_env(rt: &Context)]
+pub fn restartable<Arg, Input, Output>(arg: &Arg, body: impl FnOnce(&Arg) -> Output) -> Output
+where
+    Arg: PartialEq<Input> + ToOwned<Owned = Input> + ?Sized,
+    Input: Borrow<Arg> + 'static,
+    Output: Clone + 'static,
+{
+    rt.restartable(&CallId::current(), arg, body)
+}
+
+/// Runs `child`, returning its output if it succeeds or the output of
+/// `fallback` if it returns an error or panics.
+///
+/// Values cached by `child` are kept from its last successful run until it
+/// succeeds again, even those it didn't reach before failing, so state
+/// variables and other cached values in the subtree aren't reset by a failure.
+/// Values `child` cached for the first time during a failed run are dropped at
+/// the end of the revision unless they're used again in the next one.
+///
+/// Panics are caught with [`std::panic::catch_unwind`], so the process's panic
+/// hook still reports them. Targets which abort on panic, like
+/// `wasm32-unknown-unknown`, can't recover from them and only fall back on
+/// errors.
+///
+/// # Example
+///
+/// ```
+/// use moxie::{error_boundary, runtime::RunLoop, state};
+///
+/// let mut rt = RunLoop::new(|| {
+///     let (input, key) = state(|| String::from("1"));
+///     let shown = error_boundary(
+///         || input.parse::<u32>().map(|n| format!("parsed {}", n)),
+///         |caught| format!("couldn't parse {:?}: {}", input, caught),
+///     );
+///     (shown, key)
+/// });
+///
+/// let (shown, input) = rt.run_once();
+/// assert_eq!(shown, "parsed 1");
+///
+/// input.set(String::from("one"));
+/// let (shown, _) = rt.run_once();
+/// assert_eq!(shown, "couldn't parse \"one\": invalid digit found in string");
+/// ```
+#[topo::nested]
+#[illicit::from_env(rt: &Context)]
+pub fn error_boundary<Output, E>(
+    child: impl FnOnce() -> Result<Output, E>,
+    fallback: impl FnOnce(runtime::Caught<E>) -> Output,
+) -> Output {
+    rt.error_boundary(&CallId::current(), child).unwrap_or_else(fallback)
+}
+
+/// Runs `op` after the current revision whenever `arg` changes, calling the
+/// cleanup returned by its previous run first. The last cleanup is called once
+/// this callsite goes unused for a revision.
+///
+/// Effects are for side effects which shouldn't happen while the root function
+/// is still running, such as subscribing to external events. After the
+/// runtime's root function has returned and unused values have been dropped
+/// from its cache:
+///
+/// 1. cleanups for effects whose callsites went unused run, in the order those
+///    effects were last declared
+/// 2. effects whose `arg` changed run in the order they were declared, each
+///    preceded by its own previous cleanup
+///
+/// # Example
+///
+/// ```
+/// use moxie::{effect, runtime::RunLoop};
+/// use std::{cell::RefCell, rc::Rc};
+///
+/// let log = Rc::new(RefCell::new(vec![]));
+/// let subscribed_to = Rc::new(RefCell::new(Some("news")));
+///
+/// let (root_log, topic) = (log.clone(), subscribed_to.clone());
+/// let mut rt = RunLoop::new(move || {
+///     if let Some(topic) = *topic.borrow() {
+///         let log = root_log.clone();
+///         effect(topic, move |topic: &String| {
+///             log.borrow_mut().push(format!("subscribe {}", topic));
+///             let topic = topic.clone();
+///             move || log.borrow_mut().push(format!("unsubscribe {}", topic))
+///         });
+///     }
+///     root_log.borrow_mut().push(String::from("root"));
+/// });
+///
+/// rt.run_once();
+/// assert_eq!(log.take(), ["root", "subscribe news"]);
+///
+/// rt.run_once();
+/// assert_eq!(log.take(), ["root"], "arg hasn't changed");
+///
+/// *subscribed_to.borrow_mut() = Some("weather");
+/// rt.run_once();
+/// assert_eq!(log.take(), ["root", "unsubscribe news", "subscribe weather"]);
+///
+/// *subscribed_to.borrow_mut() = None;
+/// rt.run_once();
+/// assert_eq!(log.take(), ["root", "unsubscribe weather"]);
+/// ```
+#[topo::nested]
+#[illicit::from_env(rt: &Context)]
+pub fn effect<Arg, Input, Cleanup>(arg: &Arg, op: impl FnOnce(&Input) -> Cleanup + 'static)
+where
+    Arg: PartialEq<Input> + ToOwned<Owned = Input> + ?Sized,
+    Input: Borrow<Arg> + 'static,
+    Cleanup: FnOnce() + 'static,
+{
+    rt.effect(&CallId::current(), arg, op)
+}
+
 /// Root a state variable at this callsite, returning a [`Key`] to the state
 /// variable.
 ///
@@ -307,6 +558,76 @@ where
     rt.cache_state(&CallId::current(), &(), |_| init())
 }
 
+/// Root a state variable at this callsite like [`state`], including it in the
+/// runtime's [`runtime::Snapshot`]s. If a snapshot was restored into the
+/// runtime before its first revision, the state variable starts with the
+/// restored value instead of calling `init`.
+///
+/// # Example
+///
+/// ```
+/// use moxie::{persisted_state, runtime::RunLoop};
+///
+/// let root = || persisted_state(|| 0u64);
+///
+/// let mut rt = RunLoop::new(root);
+/// let (count, key) = rt.run_once();
+/// assert_eq!(*count, 0);
+/// key.set(1);
+///
+/// // a new runtime can pick up where the last one left off
+/// let mut restarted = RunLoop::new(root);
+/// restarted.restore(rt.snapshot());
+/// let (count, _) = restarted.run_once();
+/// assert_eq!(*count, 1);
+/// ```
+#[topo::nested]
+#[illicit::from_env(rt: &Context)]
+pub fn persisted_state<Output>(init: impl FnOnce() -> Output) -> (Commit<Output>, Key<Output>)
+where
+    Output: runtime::Persist + 'static,
+{
+    rt.persisted_state(&CallId::current(), init)
+}
+
+/// Root a state variable at this callsite like [`state`], recording each of
+/// its commits so that updates can be reverted with [`Key::undo`] and
+/// reapplied with [`Key::redo`]. The state variable is also reverted by
+/// [`runtime::Runtime::revert_to`].
+///
+/// The most recent commits are retained for as long as the state variable is
+/// live, up to the capacity set with
+/// [`runtime::Runtime::set_history_capacity`]. Because commits are immutable
+/// and shared, undoing an update returns the same
+/// [`Commit`] which was replaced rather than a copy of it.
+///
+/// # Example
+///
+/// ```
+/// use moxie::{history_state, runtime::RunLoop};
+///
+/// let mut rt = RunLoop::new(|| history_state(String::new));
+/// let (_, text) = rt.run_once();
+///
+/// text.set(String::from("hello"));
+/// text.set(String::from("hello world"));
+/// assert_eq!(*rt.run_once().0, "hello world");
+///
+/// assert!(text.undo());
+/// assert_eq!(*rt.run_once().0, "hello");
+///
+/// assert!(text.redo());
+/// assert_eq!(*rt.run_once().0, "hello world");
+/// ```
+#[topo::nested]
+#[illicit::from_env(rt: &Context)]
+pub fn history_state<Output>(init: impl FnOnce() -> Output) -> (Commit<Output>, Key<Output>)
+where
+    Output: 'static,
+{
+    rt.history_state(&CallId::current(), init)
+}
+
 /// Root a state variable at this callsite, returning a [`Key`] to the state
 /// variable. Re-initializes the state variable if the capture `arg` changes.
 ///
@@ -617,6 +938,168 @@ where
     rt.load_with(&CallId::current(), capture, init, Clone::clone)
 }
 
+/// Subscribe to the stream returned by `init` whenever `arg` changes, returning
+/// the result of calling `with` with the latest item it has produced.
+///
+/// The stream is spawned onto the runtime's task executor like the futures
+/// passed to [`load_with`], and each item it produces is committed to a state
+/// variable at this callsite, waking the runtime. `Poll::Pending` is returned
+/// until the first item is produced, and the last item is kept after the
+/// stream ends. If a revision occurs without calling this at the same
+/// callsite, the stream is cancelled.
+#[topo::nested]
+#[illicit::from_env(rt: &Context)]
+pub fn subscribe_with<Arg, Input, S, Item, Ret>(
+    arg: &Arg,
+    init: impl FnOnce(&Input) -> S,
+    with: impl FnOnce(&Item) -> Ret,
+) -> Poll<Ret>
+where
+    Arg: PartialEq<Input> + ToOwned<Owned = Input> + ?Sized,
+    Input: Borrow<Arg> + 'static,
+    S: Stream<Item = Item> + 'static,
+    Item: 'static,
+    Ret: 'static,
+{
+    rt.subscribe_with(&CallId::current(), arg, init, with)
+}
+
+/// Subscribe to a stream, cloning its latest item on each revision after the
+/// first is produced. Re-initializes the stream if the capture argument
+/// changes from previous revisions. See [`subscribe_with`].
+///
+/// # Example
+///
+/// ```
+/// use futures::{channel::mpsc, executor::LocalPool};
+/// use moxie::{runtime::RunLoop, subscribe};
+/// use std::task::Poll;
+///
+/// let (sender, receiver) = mpsc::unbounded();
+/// let mut receiver = Some(receiver);
+/// let mut rt = RunLoop::new(move || subscribe(&(), |()| receiver.take().unwrap()));
+///
+/// let mut exec = LocalPool::new();
+/// rt.set_task_executor(exec.spawner());
+/// assert_eq!(rt.run_once(), Poll::Pending);
+///
+/// sender.unbounded_send("connected").unwrap();
+/// exec.run_until_stalled();
+/// assert_eq!(rt.run_once(), Poll::Ready("connected"));
+///
+/// sender.unbounded_send("first message").unwrap();
+/// sender.unbounded_send("second message").unwrap();
+/// exec.run_until_stalled();
+/// assert_eq!(rt.run_once(), Poll::Ready("second message"));
+///
+/// drop(sender);
+/// exec.run_until_stalled();
+/// assert_eq!(rt.run_once(), Poll::Ready("second message"), "the last item is kept");
+/// ```
+#[topo::nested]
+#[illicit::from_env(rt: &Context)]
+pub fn subscribe<Arg, Input, S, Item>(arg: &Arg, init: impl FnOnce(&Input) -> S) -> Poll<Item>
+where
+    Arg: PartialEq<Input> + ToOwned<Owned = Input> + ?Sized,
+    Input: Borrow<Arg> + 'static,
+    S: Stream<Item = Item> + 'static,
+    Item: Clone + 'static,
+{
+    rt.subscribe_with(&CallId::current(), arg, init, Clone::clone)
+}
+
+/// Runs `children`, returning their output once none of the loads in their
+/// subtree are pending and the output of `fallback` until then.
+///
+/// `children` are run on every revision so that their loads are started
+/// together and kept alive, but their output is discarded while any of those
+/// loads are pending. When the last one resolves, the whole subtree is shown at
+/// once rather than as each of its loads resolve. Loads inside a nested
+/// boundary only suspend the nested boundary.
+///
+/// See [`suspense_timeout`] for a boundary which gives up waiting.
+///
+/// # Example
+///
+/// ```
+/// use futures::{
+///     channel::oneshot::{channel, Canceled, Sender},
+///     executor::LocalPool,
+/// };
+/// use moxie::{load_once, runtime::RunLoop, suspense};
+/// use std::{cell::RefCell, task::Poll};
+///
+/// #[topo::nested]
+/// fn loaded(senders: &RefCell<Vec<Sender<&'static str>>>) -> Poll<Result<&'static str, Canceled>> {
+///     load_once(|| {
+///         let (sender, receiver) = channel();
+///         senders.borrow_mut().push(sender);
+///         receiver
+///     })
+/// }
+///
+/// let senders = RefCell::new(vec![]);
+/// let mut rt = RunLoop::new(|| {
+///     suspense(
+///         || String::from("loading..."),
+///         || match (loaded(&senders), loaded(&senders)) {
+///             (Poll::Ready(Ok(first)), Poll::Ready(Ok(second))) => first.to_owned() + second,
+///             _ => String::from("discarded until both have loaded"),
+///         },
+///     )
+/// });
+///
+/// let mut exec = LocalPool::new();
+/// rt.set_task_executor(exec.spawner());
+/// assert_eq!(rt.run_once(), "loading...");
+///
+/// senders.borrow_mut().remove(0).send("hello, ").unwrap();
+/// exec.run_until_stalled();
+/// assert_eq!(rt.run_once(), "loading...", "the second load is still pending");
+///
+/// senders.borrow_mut().remove(0).send("world").unwrap();
+/// exec.run_until_stalled();
+/// assert_eq!(rt.run_once(), "hello, world");
+/// ```
+#[topo::nested]
+pub fn suspense<Output>(
+    fallback: impl FnOnce() -> Output,
+    children: impl FnOnce() -> Output,
+) -> Output {
+    match Suspense::run(children) {
+        (_, true) => fallback(),
+        (output, false) => output,
+    }
+}
+
+/// Runs `children` like [`suspense`], but shows their output even while loads
+/// are pending once the future returned by `timeout` has completed.
+///
+/// `timeout` is called when the boundary becomes suspended and its future is
+/// loaded like [`load_once`], except that it doesn't suspend an enclosing
+/// boundary while it's pending. It's dropped once the boundary has no pending
+/// loads, so the boundary waits for a fresh timeout the next time it's
+/// suspended. moxie doesn't provide timers, so `timeout` usually returns one
+/// from the embedding's platform, like `gloo_timers::future::TimeoutFuture` on
+/// the web.
+#[topo::nested]
+#[illicit::from_env(rt: &Context)]
+pub fn suspense_timeout<Timeout, Output>(
+    timeout: impl FnOnce() -> Timeout,
+    fallback: impl FnOnce() -> Output,
+    children: impl FnOnce() -> Output,
+) -> Output
+where
+    Timeout: Future<Output = ()> + 'static,
+{
+    let (output, suspended) = Suspense::run(children);
+    if suspended && rt.load_unsuspended(&CallId::current(), timeout).is_pending() {
+        fallback()
+    } else {
+        output
+    }
+}
+
 /// A read-only pointer to the value of a state variable *at a particular
 /// revision*.
 ///
@@ -626,15 +1109,45 @@ where
 /// each time.
 ///
 /// See [`state`] and [`cache_state`] for examples.
-#[derive(Eq, Hash, PartialEq)]
 pub struct Commit<State> {
     id: CallId,
     inner: Arc<State>,
+    /// The state variable's counter of commits, allowing memoized readers to
+    /// check for changes.
+    version: Arc<AtomicU64>,
+    /// The value of `version` when this commit was rooted.
+    seen: u64,
 }
 
 impl<State> Clone for Commit<State> {
     fn clone(&self) -> Self {
-        Self { id: self.id, inner: Arc::clone(&self.inner) }
+        Self {
+            id: self.id,
+            inner: Arc::clone(&self.inner),
+            version: Arc::clone(&self.version),
+            seen: self.seen,
+        }
+    }
+}
+
+impl<State> PartialEq for Commit<State>
+where
+    State: PartialEq,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id && self.inner == other.inner
+    }
+}
+
+impl<State> Eq for Commit<State> where State: Eq {}
+
+impl<State> Hash for Commit<State>
+where
+    State: Hash,
+{
+    fn hash<H: Hasher>(&self, hasher: &mut H) {
+        self.id.hash(hasher);
+        self.inner.hash(hasher);
     }
 }
 
@@ -651,6 +1164,7 @@ impl<State> Deref for Commit<State> {
     type Target = State;
 
     fn deref(&self) -> &Self::Target {
+        runtime::record_read(|| (self.version.clone(), self.seen));
         self.inner.deref()
     }
 }
@@ -696,6 +1210,11 @@ impl<State> Key<State> {
     /// outside of a `Revision`'s execution, otherwise unpredictable waker
     /// behavior may be obtained.
     ///
+    /// To update several state variables together, or to avoid overwriting
+    /// updates made since a value was read, use a [`runtime::Transaction`].
+    /// Commits made with a [`runtime::Priority::Background`] priority may be
+    /// deferred while urgent commits are applied first.
+    ///
     /// [Runtime]: crate::runtime::Runtime
     /// [run_once]: crate::runtime::Runtime::run_once
     ///
@@ -737,6 +1256,21 @@ impl<State> Key<State> {
         }
     }
 
+    /// Enqueues a commit reverting the state variable's most recent update if
+    /// it was declared with [`history_state`]. Returns whether there was an
+    /// update to undo. Has the same properties as [update](Key::update)
+    /// regarding waking the runtime.
+    pub fn undo(&self) -> bool {
+        self.var.lock().undo()
+    }
+
+    /// Enqueues a commit reapplying the update most recently reverted with
+    /// [`Key::undo`], returning whether there was one. Any other update made
+    /// after the undo discards the updates which can be redone.
+    pub fn redo(&self) -> bool {
+        self.var.lock().redo()
+    }
+
     /// Set a new value for the state variable, immediately taking effect.
     fn force(&self, new: State) {
         self.var.lock().enqueue_commit(new);
@@ -1044,4 +1578,117 @@ mod tests {
             "must be no task holding the channel and able to receive a message"
         );
     }
+
+    #[test]
+    fn changed_args_resubscribe() {
+        let mut pool = futures::executor::LocalPool::new();
+        let senders = Rc::new(std::cell::RefCell::new(vec![]));
+        let root_senders = senders.clone();
+
+        let mut rt = RunLoop::new(move || {
+            let (channel, key) = state(|| 0u8);
+            let latest = subscribe(&*channel, |&channel| {
+                let (send, recv) = futures::channel::mpsc::unbounded();
+                root_senders.borrow_mut().push(send);
+                futures::StreamExt::map(recv, move |item: u8| (channel, item))
+            });
+            (latest, key)
+        });
+        rt.set_task_executor(pool.spawner());
+
+        let (latest, channel) = rt.run_once();
+        assert_eq!(latest, Poll::Pending);
+        senders.borrow_mut()[0].unbounded_send(1).unwrap();
+        pool.run_until_stalled();
+        assert_eq!(rt.run_once().0, Poll::Ready((0, 1)));
+
+        channel.set(1);
+        assert_eq!(rt.run_once().0, Poll::Pending, "new subscriptions start pending");
+        pool.run_until_stalled();
+        assert!(senders.borrow_mut()[0].is_closed(), "old subscription was cancelled");
+
+        senders.borrow_mut()[1].unbounded_send(2).unwrap();
+        pool.run_until_stalled();
+        assert_eq!(rt.run_once().0, Poll::Ready((1, 2)));
+    }
+
+    #[test]
+    fn interest_loss_cancels_subscription() {
+        let mut pool = futures::executor::LocalPool::new();
+        let (send, recv) = futures::channel::mpsc::unbounded::<u8>();
+        let mut recv = Some(recv);
+
+        let mut rt = RunLoop::new(move || -> Option<Poll<u8>> {
+            if Revision::current().0 < 3 {
+                Some(subscribe(&(), |()| recv.take().unwrap()))
+            } else {
+                None
+            }
+        });
+        rt.set_task_executor(pool.spawner());
+
+        send.unbounded_send(1).unwrap();
+        assert_eq!(rt.run_once(), Some(Poll::Pending));
+        pool.run_until_stalled();
+        assert_eq!(rt.run_once(), Some(Poll::Ready(1)));
+
+        assert_eq!(rt.run_once(), None);
+        assert!(!send.is_closed(), "interest dropped, task not polled since being aborted");
+
+        pool.run_until_stalled();
+        assert!(send.is_closed(), "interest dropped, task dropped");
+    }
+
+    #[test]
+    fn restartable_scopes_rerun_on_arg_changes() {
+        let label = Cell::new("a");
+        let runs = Cell::new(0);
+        let mut rt = RunLoop::new(|| {
+            restartable(label.get(), |label| {
+                runs.set(runs.get() + 1);
+                label.to_owned()
+            })
+        });
+
+        assert_eq!((rt.run_once(), runs.get()), (String::from("a"), 1));
+        assert_eq!((rt.run_once(), runs.get()), (String::from("a"), 1));
+
+        label.set("b");
+        assert_eq!((rt.run_once(), runs.get()), (String::from("b"), 2));
+    }
+
+    #[test]
+    fn skipped_scopes_retain_cached_values() {
+        let created = Cell::new(0);
+        let outer_runs = Cell::new(0);
+        let mut rt = RunLoop::new(|| {
+            let (_, outer) = state(|| 0);
+            restartable(&(), |()| {
+                outer_runs.set(outer_runs.get() + 1);
+                let (inner, inner_key) = state(|| 0);
+                let created = cache(&(), |()| {
+                    created.set(created.get() + 1);
+                    created.get()
+                });
+                let nested = restartable(&*inner, |inner| inner * 10);
+                (created, nested, *outer, inner_key, outer.clone())
+            })
+        });
+
+        let (created_in, nested, outer_value, inner_key, outer_key) = rt.run_once();
+        assert_eq!((created_in, nested, outer_value), (1, 0, 0));
+        for _ in 0..3 {
+            rt.run_once();
+        }
+        assert_eq!(outer_runs.get(), 1, "skipped while nothing changed");
+
+        inner_key.set(2);
+        let (created_in, nested, ..) = rt.run_once();
+        assert_eq!((created_in, nested, outer_runs.get()), (1, 20, 2), "cache retained");
+
+        outer_key.set(3);
+        let (created_in, _, outer_value, ..) = rt.run_once();
+        assert_eq!((created_in, outer_value, outer_runs.get()), (1, 3, 3));
+        assert_eq!(created.get(), 1);
+    }
 }
@@ -0,0 +1,289 @@
+//! Recording moxie's [`tracing`] output for profilers.
+//!
+//! The runtime and the crates it's built on emit structured diagnostics which
+//! explain what each revision did and why it ran:
+//!
+//! * an info-level `revision` span for each run of a
+//!   [`crate::runtime::Runtime`] or [`crate::runtime::SendRuntime`], with
+//!   debug-level `gc` and `effects` spans for the work done after the root
+//!   function returns
+//! * a trace-level `call` span for each [`topo::nested`] call, tagged with its
+//!   [`topo::CallId`] and callsite location
+//! * trace-level `cache hit` and `cache miss` events for each cache read, and
+//!   debug-level events for the values dropped from the cache by each GC
+//! * a debug-level event for each commit to a state variable, tagged with the
+//!   [`crate::Key`]'s id and location, before the commit wakes the runtime
+//!
+//! Any [`tracing::Subscriber`] can consume these. [`ChromeTrace`] records them
+//! in the [Chrome trace event format], which can be loaded into
+//! `chrome://tracing`, [Perfetto], or [speedscope] to view revisions as
+//! flamegraphs. This module requires the `profiling` feature and isn't
+//! available on targets without [`std::time::Instant`], like
+//! `wasm32-unknown-unknown`.
+//!
+//! # Example
+//!
+//! ```
+//! use moxie::{profiling::ChromeTrace, runtime::RunLoop, state};
+//! use tracing_subscriber::layer::SubscriberExt;
+//!
+//! let trace = ChromeTrace::default();
+//! let subscriber = tracing_subscriber::registry().with(trace.clone());
+//!
+//! tracing::subscriber::with_default(subscriber, || {
+//!     let mut rt = RunLoop::new(|| state(|| 0u32));
+//!     let (_, key) = rt.run_once();
+//!     key.set(1);
+//!     rt.run_once();
+//! });
+//!
+//! let mut json = vec![];
+//! trace.write_to(&mut json).unwrap();
+//! // std::fs::write("revisions.json", json).unwrap();
+//! ```
+//!
+//! [Chrome trace event format]: https://docs.google.com/document/d/1CvAClvFfyA5R-PhYUmn5OOQtYMH4h6I0nSsKchNAySU
+//! [Perfetto]: https://ui.perfetto.dev
+//! [speedscope]: https://www.speedscope.app
+
+use parking_lot::Mutex;
+use serde_json::{json, Map, Value};
+use std::{
+    fmt::{Debug, Formatter, Result as FmtResult},
+    io,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Instant,
+};
+use tracing::{
+    field::{Field, Visit},
+    span::{Attributes, Id, Record},
+    Event, Metadata, Subscriber,
+};
+use tracing_subscriber::{layer::Context, registry::LookupSpan, Layer};
+
+/// A [`Layer`] which records spans and events as Chrome trace events.
+///
+/// Clones share the same recording, so one can be added to a subscriber while
+/// another is kept to write out the trace with [`ChromeTrace::write_to`].
+/// Spans with a `location` field, like the `call` spans for each
+/// [`topo::nested`] function, are named for their location so that they can
+/// be told apart in flamegraphs.
+#[derive(Clone)]
+pub struct ChromeTrace {
+    inner: Arc<Mutex<Recording>>,
+}
+
+struct Recording {
+    start: Instant,
+    events: Vec<Value>,
+}
+
+impl Default for ChromeTrace {
+    fn default() -> Self {
+        Self { inner: Arc::new(Mutex::new(Recording { start: Instant::now(), events: vec![] })) }
+    }
+}
+
+impl ChromeTrace {
+    /// Write the events recorded so far as a JSON trace file.
+    pub fn write_to(&self, writer: impl io::Write) -> io::Result<()> {
+        let recording = self.inner.lock();
+        let trace = json!({ "traceEvents": &recording.events, "displayTimeUnit": "ms" });
+        serde_json::to_writer(writer, &trace).map_err(Into::into)
+    }
+
+    /// Returns the number of events recorded so far.
+    pub fn len(&self) -> usize {
+        self.inner.lock().events.len()
+    }
+
+    /// Returns true if no events have been recorded.
+    pub fn is_empty(&self) -> bool {
+        self.inner.lock().events.is_empty()
+    }
+
+    fn record(
+        &self,
+        phase: &str,
+        name: String,
+        metadata: &Metadata,
+        args: Option<&Map<String, Value>>,
+    ) {
+        let mut recording = self.inner.lock();
+        let mut event = json!({
+            "name": name,
+            "cat": metadata.target(),
+            "ph": phase,
+            "ts": recording.start.elapsed().as_secs_f64() * 1_000_000.0,
+            "pid": 1,
+            "tid": thread_id(),
+        });
+        if let Some(args) = args {
+            event["args"] = Value::Object(args.clone());
+        }
+        if phase == "i" {
+            // instant events are scoped to their thread
+            event["s"] = json!("t");
+        }
+        recording.events.push(event);
+    }
+}
+
+impl Debug for ChromeTrace {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.debug_struct("ChromeTrace").field("events", &self.len()).finish()
+    }
+}
+
+impl<S> Layer<S> for ChromeTrace
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes, id: &Id, ctx: Context<S>) {
+        let mut fields = Fields::default();
+        attrs.record(&mut fields);
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(fields);
+        }
+    }
+
+    fn on_record(&self, id: &Id, values: &Record, ctx: Context<S>) {
+        if let Some(span) = ctx.span(id) {
+            if let Some(fields) = span.extensions_mut().get_mut::<Fields>() {
+                values.record(fields);
+            }
+        }
+    }
+
+    fn on_event(&self, event: &Event, _ctx: Context<S>) {
+        let mut fields = Fields::default();
+        event.record(&mut fields);
+        let name = match fields.0.remove("message") {
+            Some(Value::String(message)) => message,
+            _ => event.metadata().name().to_owned(),
+        };
+        self.record("i", name, event.metadata(), Some(&fields.0));
+    }
+
+    fn on_enter(&self, id: &Id, ctx: Context<S>) {
+        if let Some(span) = ctx.span(id) {
+            let extensions = span.extensions();
+            let args = extensions.get::<Fields>().map(|fields| &fields.0);
+            self.record("B", span_name(span.metadata(), args), span.metadata(), args);
+        }
+    }
+
+    fn on_exit(&self, id: &Id, ctx: Context<S>) {
+        if let Some(span) = ctx.span(id) {
+            let extensions = span.extensions();
+            let args = extensions.get::<Fields>().map(|fields| &fields.0);
+            self.record("E", span_name(span.metadata(), args), span.metadata(), None);
+        }
+    }
+}
+
+fn span_name(metadata: &Metadata, args: Option<&Map<String, Value>>) -> String {
+    match args.and_then(|args| args.get("location")) {
+        Some(Value::String(location)) => format!("{} {}", metadata.name(), location),
+        _ => metadata.name().to_owned(),
+    }
+}
+
+/// Returns a small number unique to the current thread, for grouping events.
+fn thread_id() -> u64 {
+    static NEXT: AtomicU64 = AtomicU64::new(1);
+    thread_local! {
+        static ID: u64 = NEXT.fetch_add(1, Ordering::Relaxed);
+    }
+    ID.with(|id| *id)
+}
+
+/// The fields recorded for a span or event, converted to JSON.
+#[derive(Default)]
+struct Fields(Map<String, Value>);
+
+impl Visit for Fields {
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.0.insert(field.name().to_owned(), value.into());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0.insert(field.name().to_owned(), value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0.insert(field.name().to_owned(), value.into());
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_owned(), value.into());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        self.0.insert(field.name().to_owned(), format!("{:?}", value).into());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{cache, once, runtime::RunLoop, state};
+    use tracing_subscriber::layer::SubscriberExt;
+
+    fn events_named<'a>(trace: &'a Value, phase: &'a str, name: &'a str) -> Vec<&'a Value> {
+        let events = trace["traceEvents"].as_array().unwrap();
+        events.iter().filter(|e| e["ph"] == phase && e["name"] == name).collect()
+    }
+
+    #[test]
+    fn revisions_are_exported_with_their_causes() {
+        let trace = ChromeTrace::default();
+        let subscriber = tracing_subscriber::registry().with(trace.clone());
+
+        tracing::subscriber::with_default(subscriber, || {
+            let mut rt = RunLoop::new(|| {
+                let (count, key) = state(|| 0u32);
+                cache(&*count, |n| n * 2);
+                if *count == 0 {
+                    once(|| String::from("dropped after the first revision"));
+                }
+                key
+            });
+            let key = rt.run_once();
+            key.set(1);
+            rt.run_once();
+        });
+
+        let mut json = vec![];
+        trace.write_to(&mut json).unwrap();
+        let trace: Value = serde_json::from_slice(&json).unwrap();
+
+        let revisions = events_named(&trace, "B", "revision");
+        assert_eq!(revisions.len(), 2);
+        assert_eq!(revisions[1]["args"]["revision"], 2);
+        assert_eq!(events_named(&trace, "E", "revision").len(), 2, "spans are closed");
+
+        let commits = events_named(&trace, "i", "state variable received a commit");
+        assert_eq!(commits.len(), 1);
+        let location = commits[0]["args"]["location"].as_str().unwrap();
+        assert!(location.starts_with("src/profiling.rs:"), "commit is tagged with its key");
+
+        let state_call = format!("call {}", location);
+        let calls = events_named(&trace, "B", &state_call);
+        assert_eq!(calls.len(), 2, "nested calls are named for their location");
+
+        let reads_of = |name, output: &str| {
+            let reads = events_named(&trace, "i", name);
+            reads.iter().filter(|e| e["args"]["output"].as_str().unwrap().contains(output)).count()
+        };
+        assert_eq!(reads_of("cache miss", "Var<u32>"), 1);
+        assert_eq!(reads_of("cache hit", "Var<u32>"), 1, "state is reused");
+        assert_eq!(reads_of("cache miss", "u32"), 3, "doubled count is recomputed");
+        let dropped = events_named(&trace, "i", "cache gc dropped values");
+        assert_eq!(dropped.len(), 1);
+        assert_eq!(dropped[0]["args"]["output"], "alloc::string::String");
+    }
+}
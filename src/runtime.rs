@@ -1,24 +1,55 @@
 //! [`Runtime`]s are the primary integration point between moxie and
 //! embedding environments.
 
+mod boundary;
+mod budget;
 mod context;
+mod effect;
+mod history;
+mod inspect;
+mod load;
+mod memo;
+mod priority;
+mod registry;
 mod runloop;
+mod send;
+mod snapshot;
+mod suspense;
+mod transaction;
 mod var;
 
 use dyn_cache::local::SharedLocalCache;
 use futures::{
-    future::LocalFutureObj,
-    task::{noop_waker, LocalSpawn, SpawnError},
+    future::{FutureObj, LocalFutureObj},
+    task::{noop_waker, LocalSpawn, Spawn, SpawnError},
 };
 use illicit::AsContext;
 use std::{
     fmt::{Debug, Formatter, Result as FmtResult},
     rc::Rc,
-    task::Waker,
+    sync::Arc,
+    task::{Poll, Waker},
+    time::{Duration, Instant},
 };
 
+pub use boundary::Caught;
+pub(crate) use budget::Budget;
 pub(crate) use context::Context;
+pub(crate) use effect::{EffectCell, Effects};
+pub(crate) use history::{Histories, History};
+pub(crate) use inspect::Inspector;
+pub use inspect::{CachedValue, CallNode, Inspection, SetStateError, StateValue};
+pub(crate) use memo::{record_read, Memo};
+pub(crate) use priority::Lanes;
+pub use priority::Priority;
+pub(crate) use registry::Registry;
 pub use runloop::RunLoop;
+pub(crate) use send::SendContext;
+pub use send::{SendRunLoop, SendRuntime};
+pub(crate) use snapshot::Persistence;
+pub use snapshot::{Persist, Snapshot};
+pub(crate) use suspense::Suspense;
+pub use transaction::{Conflict, Transaction};
 pub(crate) use var::Var;
 
 /// Revisions measure moxie's notion of time passing. Each `Runtime` increments
@@ -33,6 +64,8 @@ impl Revision {
     pub fn current() -> Self {
         if let Ok(r) = illicit::get::<Context>() {
             r.revision()
+        } else if let Ok(r) = illicit::get::<SendContext>() {
+            r.revision()
         } else {
             Revision::default()
         }
@@ -124,7 +157,14 @@ impl std::fmt::Debug for Revision {
 /// [dyn-cache]: https://docs.rs/dyn-cache
 pub struct Runtime {
     revision: Revision,
+    /// The priority applied by a revision which yielded before completing.
+    in_progress: Option<Priority>,
     cache: SharedLocalCache,
+    effects: Rc<Effects>,
+    histories: Rc<Histories>,
+    inspector: Rc<Inspector>,
+    lanes: Arc<Lanes>,
+    persistence: Rc<Persistence>,
     spawner: Spawner,
     wk: Waker,
 }
@@ -142,7 +182,13 @@ impl Runtime {
         Self {
             spawner: Spawner(Rc::new(JunkSpawner)),
             revision: Revision(0),
+            in_progress: None,
             cache: SharedLocalCache::default(),
+            effects: Rc::new(Effects::default()),
+            histories: Rc::new(Histories::default()),
+            inspector: Rc::new(Inspector::default()),
+            lanes: Arc::new(Lanes::default()),
+            persistence: Rc::new(Persistence::default()),
             wk: noop_waker(),
         }
     }
@@ -155,14 +201,233 @@ impl Runtime {
 
     /// Runs the root closure once with access to the runtime context,
     /// increments the runtime's `Revision`, and drops any cached values
-    /// which were not marked alive.
+    /// which were not marked alive. Finally, runs any effects declared with
+    /// [`crate::effect`] which need to run.
+    ///
+    /// If there are both urgent and background commits pending, only the
+    /// urgent commits are applied and the state change waker is called again
+    /// after the revision so the deferred commits can be applied in the next
+    /// one. See [`Priority`].
+    ///
+    /// If a revision started with [`Runtime::run_until`] has yielded, it is
+    /// completed instead of starting a new one.
     pub fn run_once<Out>(&mut self, op: impl FnOnce() -> Out) -> Out {
-        self.revision.0 += 1;
+        match self.run_with(Budget::default(), op) {
+            Poll::Ready(ret) => ret,
+            Poll::Pending => unreachable!("revisions without a budget can't yield"),
+        }
+    }
+
+    /// Runs the root closure like [`Runtime::run_once`], yielding if the
+    /// revision takes longer than `budget`. See [`Runtime::run_until`].
+    ///
+    /// This uses [`std::time::Instant`], which isn't available on
+    /// `wasm32-unknown-unknown`. Use `run_until` with a clock from the
+    /// embedding environment there instead.
+    pub fn run_with_budget<Out>(
+        &mut self,
+        budget: Duration,
+        op: impl FnOnce() -> Out,
+    ) -> Poll<Out> {
+        let deadline = Instant::now() + budget;
+        self.run_until(move || Instant::now() >= deadline, op)
+    }
+
+    /// Runs the root closure like [`Runtime::run_once`], yielding from
+    /// restartable scopes once `should_yield` returns true.
+    ///
+    /// Each [`crate::restartable`] scope which needs to run again first calls
+    /// `should_yield`. If it returns true, the scope's previous output is
+    /// reused and the revision is left incomplete: the root closure's return
+    /// value is discarded, `Poll::Pending` is returned, and the state change
+    /// waker is called. The next call to `run_until` or `run_once` resumes the
+    /// same revision, reusing the output of every scope which has already run
+    /// for it, until the root closure completes without yielding. No cached
+    /// values are dropped and no effects run until the revision completes.
+    ///
+    /// Revisions which apply background commits also yield once an urgent
+    /// commit is made, so that the embedding can respond to it. See
+    /// [`Priority`].
+    ///
+    /// Scopes which have never produced an output can't yield, so the first
+    /// revision always runs to completion.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use moxie::{restartable, runtime::Runtime, state};
+    /// use std::{cell::Cell, task::Poll};
+    ///
+    /// let root = || {
+    ///     let (_, count) = state(|| 0u64);
+    ///     let slow = restartable(&*count, |n| n + 1);
+    ///     let slower = restartable(&*count, |n| n + 2);
+    ///     (slow, slower, count)
+    /// };
+    ///
+    /// let mut rt = Runtime::new();
+    /// let (.., count) = rt.run_once(root);
+    /// count.set(10);
+    ///
+    /// // yield once the first scope has run
+    /// let calls = Cell::new(0);
+    /// let out_of_time = move || calls.replace(calls.get() + 1) > 0;
+    /// assert!(rt.run_until(out_of_time, root).is_pending());
+    /// assert_eq!(rt.in_progress(), Some(rt.revision()));
+    ///
+    /// let (slow, slower, _) = rt.run_once(root);
+    /// assert_eq!((slow, slower), (11, 12));
+    /// assert_eq!(rt.in_progress(), None);
+    /// ```
+    pub fn run_until<Out>(
+        &mut self,
+        should_yield: impl Fn() -> bool + 'static,
+        op: impl FnOnce() -> Out,
+    ) -> Poll<Out> {
+        self.run_with(Budget::new(should_yield), op)
+    }
+
+    /// Returns the revision which yielded before completing, if any. It will be
+    /// resumed by the next call to [`Runtime::run_once`] or
+    /// [`Runtime::run_until`].
+    pub fn in_progress(&self) -> Option<Revision> {
+        self.in_progress.map(|_| self.revision)
+    }
+
+    fn run_with<Out>(&mut self, budget: Budget, op: impl FnOnce() -> Out) -> Poll<Out> {
+        let resumed = self.in_progress.is_some();
+        let applied_priority = match self.in_progress.take() {
+            Some(resumed) => resumed,
+            None => {
+                self.revision.0 += 1;
+                self.lanes.begin_revision()
+            }
+        };
 
-        let ret = self.context_handle().offer(|| topo::call(op));
+        let span = tracing::info_span!(
+            "revision",
+            revision = self.revision.0,
+            priority = ?applied_priority,
+            resumed,
+        );
+        let _entered = span.enter();
 
-        self.cache.gc();
-        ret
+        // urgent commits made before the revision runs are applied by it, only
+        // those made while it runs interrupt it
+        self.lanes.take_interrupt();
+        let budget = budget.interrupted_by(self.lanes.clone());
+
+        let ret = self.context_handle(applied_priority, budget.clone()).offer(|| topo::call(op));
+
+        if budget.yielded() {
+            tracing::debug!("revision yielded");
+            self.in_progress = Some(applied_priority);
+            self.wk.wake_by_ref();
+            return Poll::Pending;
+        }
+
+        tracing::debug_span!("gc").in_scope(|| self.cache.gc());
+        self.persistence.end_revision();
+        tracing::debug_span!("effects").in_scope(|| self.effects.flush());
+        if self.lanes.has_deferred() {
+            self.wk.wake_by_ref();
+        }
+        Poll::Ready(ret)
+    }
+
+    /// Returns true if background commits were deferred by the most recent
+    /// revision or have been made since.
+    pub fn has_deferred(&self) -> bool {
+        self.lanes.has_deferred()
+    }
+
+    /// Returns the latest values of the state variables declared with
+    /// [`crate::persisted_state`] which are live after the most recent
+    /// revision, including any pending commits. State variables in
+    /// [`crate::restartable`] scopes which were skipped are still live.
+    pub fn snapshot(&self) -> Snapshot {
+        self.persistence.snapshot(&self.cache)
+    }
+
+    /// Restores the state variables in `snapshot`. Each is used in place of the
+    /// initializer passed to [`crate::persisted_state`] when the state variable
+    /// is declared during the first revision. Any states which aren't claimed
+    /// during the first revision are dropped.
+    ///
+    /// # Panics
+    ///
+    /// If [`Runtime::run_once`] has already been called.
+    pub fn restore(&mut self, snapshot: Snapshot) {
+        assert_eq!(self.revision, Revision(0), "state must be restored before the first revision");
+        self.persistence.restore(snapshot);
+    }
+
+    /// Enqueues commits which return each state variable declared with
+    /// [`crate::history_state`] to the value it had during `revision`. The
+    /// commits are recorded as updates, so they can be undone with
+    /// [`crate::Key::undo`].
+    ///
+    /// Only state variables which are live after the most recent revision are
+    /// reverted, including those in skipped [`crate::restartable`] scopes.
+    /// Those which didn't exist yet during `revision` are left unchanged.
+    pub fn revert_to(&self, revision: Revision) {
+        self.histories.revert_to(&self.cache, revision);
+    }
+
+    /// Returns a read-only view of the call tree as of the most recent
+    /// revision, listing the values cached for each call and the state
+    /// variables it declared, including their pending commits. See
+    /// [`Inspection`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use moxie::{runtime::RunLoop, state};
+    ///
+    /// let mut rt = RunLoop::new(|| state(|| 0u32));
+    /// let (_, key) = rt.run_once();
+    /// key.set(1);
+    ///
+    /// let inspection = rt.inspect();
+    /// let count = inspection.states()[0];
+    /// assert_eq!((count.value(), count.pending_value()), (Some("0"), Some("1")));
+    /// println!("{}", inspection);
+    ///
+    /// // debugging tools can set state variables as if they had their keys
+    /// rt.set_state(count.path(), 5u32).unwrap();
+    /// assert_eq!(*rt.run_once().0, 5);
+    /// ```
+    pub fn inspect(&self) -> Inspection {
+        self.inspector.inspect(self.revision, &self.cache)
+    }
+
+    /// Shows the `Debug` representation of state variables with type `State`
+    /// in [`Runtime::inspect`]. Primitives and strings are shown without being
+    /// registered.
+    pub fn inspect_with_debug<State: Debug + 'static>(&self) {
+        self.inspector.register_debug::<State>();
+    }
+
+    /// Enqueues a commit of `state` to the state variable declared at `path`,
+    /// as if it were set with its [`crate::Key`]. Intended for debugging tools,
+    /// which can find the paths of state variables with [`Runtime::inspect`].
+    ///
+    /// Only state variables which were live in the most recent revision can be
+    /// set.
+    pub fn set_state<State: 'static>(
+        &self,
+        path: &topo::CallPath,
+        state: State,
+    ) -> Result<(), SetStateError> {
+        self.inspector.set_state(&self.cache, path, state)
+    }
+
+    /// Sets the number of commits retained by the histories of state variables
+    /// declared with [`crate::history_state`] after this is called. Once a
+    /// history is full, its oldest updates can no longer be undone and its
+    /// oldest revisions can no longer be reverted to. Defaults to 100.
+    pub fn set_history_capacity(&mut self, capacity: usize) {
+        self.histories.set_capacity(capacity);
     }
 
     /// Sets the [`std::task::Waker`] which will be called when state variables
@@ -188,7 +453,9 @@ impl Debug for Spawner {
     }
 }
 
+/// Fails to spawn tasks, until an executor is set.
 struct JunkSpawner;
+
 impl LocalSpawn for JunkSpawner {
     fn spawn_local_obj(&self, _: LocalFutureObj<'static, ()>) -> Result<(), SpawnError> {
         Err(SpawnError::shutdown())
@@ -199,6 +466,16 @@ impl LocalSpawn for JunkSpawner {
     }
 }
 
+impl Spawn for JunkSpawner {
+    fn spawn_obj(&self, _: FutureObj<'static, ()>) -> Result<(), SpawnError> {
+        Err(SpawnError::shutdown())
+    }
+
+    fn status(&self) -> Result<(), SpawnError> {
+        Err(SpawnError::shutdown())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
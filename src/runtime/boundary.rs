@@ -0,0 +1,175 @@
+use std::{
+    any::Any,
+    error::Error,
+    fmt::{Debug, Display, Formatter, Result as FmtResult},
+};
+
+/// The failure of a subtree run by [`crate::error_boundary`], passed to its
+/// fallback.
+#[derive(Debug)]
+pub enum Caught<E> {
+    /// The subtree returned an error.
+    Error(E),
+    /// The subtree panicked with this payload.
+    Panic(Box<dyn Any + Send>),
+}
+
+impl<E> Caught<E> {
+    /// Returns the panic's message if the subtree panicked with a string, as
+    /// `panic!` does when it's given a message.
+    pub fn panic_message(&self) -> Option<&str> {
+        match self {
+            Caught::Error(_) => None,
+            Caught::Panic(payload) => payload
+                .downcast_ref::<&'static str>()
+                .copied()
+                .or_else(|| payload.downcast_ref::<String>().map(String::as_str)),
+        }
+    }
+}
+
+impl<E: Display> Display for Caught<E> {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match (self, self.panic_message()) {
+            (Caught::Error(error), _) => Display::fmt(error, f),
+            (Caught::Panic(_), Some(message)) => write!(f, "panicked: {}", message),
+            (Caught::Panic(_), None) => f.write_str("panicked"),
+        }
+    }
+}
+
+impl<E: Error + 'static> Error for Caught<E> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Caught::Error(error) => Some(error),
+            Caught::Panic(_) => None,
+        }
+    }
+}
+
+/// A cache argument which is never equal to the stored one, so that the
+/// initializer always runs while the cell from the last success stays in place
+/// until it's replaced.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Fresh;
+
+impl PartialEq for Fresh {
+    fn eq(&self, _: &Self) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Caught;
+    use crate::{effect, error_boundary, once, runtime::RunLoop, state};
+    use std::{
+        cell::{Cell, RefCell},
+        rc::Rc,
+    };
+
+    #[test]
+    fn panics_render_the_fallback_and_retain_the_subtree() {
+        let inits = Rc::new(Cell::new(0));
+        let child_inits = inits.clone();
+        let mut rt = RunLoop::new(move || {
+            let (fail, key) = state(|| false);
+            let inits = child_inits.clone();
+            let shown = error_boundary(
+                move || {
+                    let cached = once(|| {
+                        inits.set(inits.get() + 1);
+                        String::from("child")
+                    });
+                    if *fail {
+                        panic!("child failed");
+                    }
+                    Ok::<_, ()>(cached)
+                },
+                |caught| format!("fallback: {}", caught.panic_message().unwrap()),
+            );
+            (shown, key)
+        });
+
+        let (shown, fail) = rt.run_once();
+        assert_eq!((shown.as_str(), inits.get()), ("child", 1));
+
+        fail.set(true);
+        assert_eq!(rt.run_once().0, "fallback: child failed");
+        assert_eq!(rt.run_once().0, "fallback: child failed", "still failing");
+
+        fail.set(false);
+        assert_eq!(rt.run_once().0, "child");
+        assert_eq!(inits.get(), 1, "cached value survived the failed revisions");
+    }
+
+    #[test]
+    fn errors_render_the_fallback_and_retain_the_subtree() {
+        let log = Rc::new(RefCell::new(vec![]));
+        let child_log = log.clone();
+        let mut rt = RunLoop::new(move || {
+            let (value, key) = state(|| 1u32);
+            let log = child_log.clone();
+            let shown = error_boundary(
+                move || {
+                    let log = log.clone();
+                    effect(&(), move |()| {
+                        log.borrow_mut().push("subscribed");
+                        move || log.borrow_mut().push("unsubscribed")
+                    });
+                    if *value == 0 {
+                        Err("zero")
+                    } else {
+                        Ok(*value)
+                    }
+                },
+                |caught| match caught {
+                    Caught::Error(error) => {
+                        assert_eq!(error, "zero");
+                        0
+                    }
+                    Caught::Panic(_) => unreachable!("child doesn't panic"),
+                },
+            );
+            (shown, key)
+        });
+
+        let (shown, value) = rt.run_once();
+        assert_eq!((shown, log.take()), (1, vec!["subscribed"]));
+
+        value.set(0);
+        assert_eq!((rt.run_once().0, log.take()), (0, vec![]), "effect wasn't cleaned up");
+
+        value.set(2);
+        assert_eq!((rt.run_once().0, log.take()), (2, vec![]));
+    }
+
+    #[test]
+    fn boundaries_drop_the_subtree_once_unused() {
+        let drops = Rc::new(Cell::new(0));
+        let child_drops = drops.clone();
+        let mut rt = RunLoop::new(move || {
+            let (shown, key) = state(|| true);
+            if *shown {
+                let drops = child_drops.clone();
+                error_boundary(
+                    || {
+                        Ok::<_, ()>(once(|| {
+                            Rc::new(scopeguard::guard((), move |()| drops.set(drops.get() + 1)))
+                        }))
+                    },
+                    |_| unreachable!(),
+                );
+            }
+            key
+        });
+
+        let shown = rt.run_once();
+        rt.run_once();
+        assert_eq!(drops.get(), 0);
+
+        shown.set(false);
+        rt.run_once();
+        assert_eq!(drops.get(), 1);
+    }
+}
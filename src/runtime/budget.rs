@@ -0,0 +1,139 @@
+use super::Lanes;
+use std::{
+    cell::Cell,
+    fmt::{Debug, Formatter, Result as FmtResult},
+    rc::Rc,
+    sync::Arc,
+};
+
+/// Decides when a revision run with [`super::Runtime::run_until`] should yield,
+/// and records whether it did.
+#[derive(Clone, Default)]
+pub(crate) struct Budget {
+    should_yield: Option<Rc<dyn Fn() -> bool>>,
+    /// The lanes of a runtime whose urgent commits interrupt the revision.
+    interrupted_by: Option<Arc<Lanes>>,
+    yielded: Rc<Cell<bool>>,
+}
+
+impl Budget {
+    pub fn new(should_yield: impl Fn() -> bool + 'static) -> Self {
+        Self {
+            should_yield: Some(Rc::new(should_yield)),
+            interrupted_by: None,
+            yielded: Default::default(),
+        }
+    }
+
+    /// Also exhaust a limited budget once an urgent commit is made while the
+    /// revision applies background commits.
+    pub fn interrupted_by(self, lanes: Arc<Lanes>) -> Self {
+        Self { interrupted_by: Some(lanes), ..self }
+    }
+
+    /// Returns true if restartable scopes should reuse their previous output
+    /// rather than running.
+    pub fn is_exhausted(&self) -> bool {
+        let interrupted = || self.interrupted_by.as_ref().is_some_and(|l| l.take_interrupt());
+        self.should_yield
+            .as_ref()
+            .map(|should_yield| should_yield() || interrupted())
+            .unwrap_or(false)
+    }
+
+    /// Record that a scope yielded, leaving the revision incomplete.
+    pub fn record_yield(&self) {
+        self.yielded.set(true);
+    }
+
+    /// Returns true if any scope yielded while running with this budget.
+    pub fn yielded(&self) -> bool {
+        self.yielded.get()
+    }
+}
+
+impl Debug for Budget {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.debug_struct("Budget")
+            .field("limited", &self.should_yield.is_some())
+            .field("interruptible", &self.interrupted_by.is_some())
+            .field("yielded", &self.yielded.get())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        restartable,
+        runtime::{Revision, RunLoop},
+        state,
+    };
+    use std::{cell::Cell, rc::Rc, task::Poll};
+
+    #[test]
+    fn yielded_revisions_resume_without_repeating_work() {
+        let runs = Rc::new(Cell::new(0));
+        let rows_runs = runs.clone();
+        let mut rt = RunLoop::new(move || {
+            let (_, version) = state(|| 0u32);
+            let rows = (0..4)
+                .map(|i| {
+                    topo::call_in_slot(&i, || {
+                        restartable(&(i + *version), |n| {
+                            rows_runs.set(rows_runs.get() + 1);
+                            *n
+                        })
+                    })
+                })
+                .collect::<Vec<_>>();
+            (rows, version)
+        });
+
+        let (rows, version) = rt.run_once();
+        assert_eq!(rows, [0, 1, 2, 3]);
+        assert_eq!(runs.take(), 4);
+
+        version.set(10);
+        // only enough budget for the first scope in each attempt
+        let budget = || {
+            let remaining = Rc::new(Cell::new(1u8));
+            move || remaining.replace(remaining.get().saturating_sub(1)) == 0
+        };
+        let revision = rt.revision();
+
+        for attempt in 1..4 {
+            assert_eq!(rt.run_until(budget()).map(|(rows, _)| rows), Poll::Pending);
+            assert_eq!(runs.take(), 1, "attempt {} ran one scope", attempt);
+            assert_eq!(rt.in_progress(), Some(Revision(revision.0 + 1)));
+        }
+
+        let finished = rt.run_until(budget()).map(|(rows, _)| rows);
+        assert_eq!(finished, Poll::Ready(vec![10, 11, 12, 13]));
+        assert_eq!(runs.take(), 1, "each scope only ran once");
+        assert_eq!(rt.in_progress(), None);
+        assert_eq!(rt.revision(), Revision(revision.0 + 1));
+    }
+
+    #[test]
+    fn yields_invalidate_enclosing_scopes() {
+        let mut rt = RunLoop::new(|| {
+            let (_, count) = state(|| 0u32);
+            let doubled = restartable(&*count, |n| restartable(n, |n| n * 2));
+            (doubled, count)
+        });
+        let (_, count) = rt.run_once();
+
+        count.set(1);
+        // the outer scope starts running but the inner one yields
+        let checked = Cell::new(false);
+        assert!(rt.run_until(move || checked.replace(true)).is_pending());
+        assert_eq!(rt.run_once().0, 2, "outer scope reran with the inner scope's new output");
+    }
+
+    #[test]
+    fn scopes_without_output_cant_yield() {
+        let mut rt = RunLoop::new(|| restartable(&(), |()| 1));
+        assert_eq!(rt.run_until(|| true), Poll::Ready(1));
+    }
+}
@@ -1,10 +1,21 @@
-use super::{Revision, Spawner, Var};
+use super::boundary::Fresh;
+use super::{
+    load::{self, Loader, Running},
+    memo::record_stale,
+    Budget, Caught, EffectCell, Effects, Histories, Inspector, Lanes, Memo, Persist, Persistence,
+    Priority, Revision, Spawner, Suspense, Var,
+};
 use crate::{Commit, Key};
 use dyn_cache::local::SharedLocalCache;
-use futures::future::abortable;
+use futures::{future::AbortHandle, FutureExt, Stream};
+use parking_lot::Mutex;
 use std::{
     borrow::Borrow,
+    cell::RefCell,
     future::Future,
+    panic::{catch_unwind, AssertUnwindSafe},
+    rc::Rc,
+    sync::Arc,
     task::{Poll, Waker},
 };
 
@@ -14,7 +25,14 @@ use std::{
 #[derive(Debug)]
 pub(crate) struct Context {
     revision: Revision,
+    applied_priority: Priority,
+    budget: Budget,
     pub cache: SharedLocalCache,
+    effects: Rc<Effects>,
+    histories: Rc<Histories>,
+    inspector: Rc<Inspector>,
+    lanes: Arc<Lanes>,
+    persistence: Rc<Persistence>,
     spawner: Spawner,
     waker: Waker,
 }
@@ -25,6 +43,122 @@ impl Context {
         self.revision
     }
 
+    /// Returns the lowest priority of the commits applied in this revision.
+    pub fn applied_priority(&self) -> Priority {
+        self.applied_priority
+    }
+
+    /// Cache the result of `init`, re-running it whenever a state variable it
+    /// read has received a commit.
+    pub fn memo<Output>(&self, id: &topo::CallId, init: impl FnOnce() -> Output) -> Output
+    where
+        Output: Clone + 'static,
+    {
+        let memo = self.memo_dependencies(id);
+        let generation = memo.borrow_mut().generation();
+        self.memo_output(id, &memo, generation, init)
+    }
+
+    /// Cache the result of `body`, re-running it whenever `arg` changes or a
+    /// state variable it read has received a commit.
+    ///
+    /// If the revision's budget is exhausted and `body` needs to run again, its
+    /// previous output is returned instead and the revision is left
+    /// incomplete. Enclosing scopes are marked as stale because they've read
+    /// an outdated output.
+    pub fn restartable<Arg, Input, Output>(
+        &self,
+        id: &topo::CallId,
+        arg: &Arg,
+        body: impl FnOnce(&Arg) -> Output,
+    ) -> Output
+    where
+        Arg: PartialEq<Input> + ToOwned<Owned = Input> + ?Sized,
+        Input: Borrow<Arg> + 'static,
+        Output: Clone + 'static,
+    {
+        let memo = self.memo_dependencies(id);
+        self.cache.hold(id, arg, |_| memo.borrow_mut().invalidate());
+        let last: Rc<RefCell<Option<(u64, Output)>>> =
+            self.cache.cache(id, &(), |()| Default::default());
+        let generation = memo.borrow_mut().generation();
+
+        let outdated = match &*RefCell::borrow(&last) {
+            Some((computed, output)) if *computed != generation => Some(output.clone()),
+            _ => None,
+        };
+        if let Some(outdated) = outdated {
+            if self.budget.is_exhausted() {
+                self.budget.record_yield();
+                record_stale();
+                RefCell::borrow(&memo).report();
+                return outdated;
+            }
+        }
+
+        let output = self.memo_output(id, &memo, generation, || body(arg));
+        *last.borrow_mut() = Some((generation, output.clone()));
+        output
+    }
+
+    fn memo_dependencies(&self, id: &topo::CallId) -> Rc<RefCell<Memo>> {
+        self.cache.cache(id, &(), |()| Default::default())
+    }
+
+    fn memo_output<Output>(
+        &self,
+        id: &topo::CallId,
+        memo: &RefCell<Memo>,
+        generation: u64,
+        init: impl FnOnce() -> Output,
+    ) -> Output
+    where
+        Output: Clone + 'static,
+    {
+        let output = self.cache.cache(id, &generation, |_| Memo::track(memo, init));
+        RefCell::borrow(memo).report();
+        output
+    }
+
+    /// Run `child`, catching its errors and panics. The values `child` cached
+    /// in its last successful run are kept alive until it succeeds again.
+    pub fn error_boundary<Output, E>(
+        &self,
+        id: &topo::CallId,
+        child: impl FnOnce() -> Result<Output, E>,
+    ) -> Result<Output, Caught<E>> {
+        // the subtree's values depend on a cell which is only replaced when
+        // it succeeds, so a failed run reads the cell without storing to it
+        let mut output = None;
+        catch_unwind(AssertUnwindSafe(|| {
+            let succeed = |succeeded| output = Some(succeeded);
+            self.cache.try_cache_with(id, &Fresh, |_| child().map(succeed), |()| ())
+        }))
+        .map_err(Caught::Panic)?
+        .map_err(Caught::Error)?;
+        Ok(output.expect("the child succeeded"))
+    }
+
+    /// Schedule `op` to run after this revision if `arg` has changed since it
+    /// last ran, cleaning up its previous run first.
+    pub fn effect<Arg, Input, Cleanup>(
+        &self,
+        id: &topo::CallId,
+        arg: &Arg,
+        op: impl FnOnce(&Input) -> Cleanup + 'static,
+    ) where
+        Arg: PartialEq<Input> + ToOwned<Owned = Input> + ?Sized,
+        Input: Borrow<Arg> + 'static,
+        Cleanup: FnOnce() + 'static,
+    {
+        let cell: Rc<EffectCell<Input>> =
+            self.cache.cache(id, &(), |()| Rc::new(EffectCell::new(&self.effects)));
+        self.effects.declare(&cell, self.revision);
+        if cell.update(arg) {
+            self.effects.enqueue(move || cell.run(op));
+        }
+    }
+
     /// Load a [`crate::state::Var`] with the provided argument and initializer.
     /// Re-initializes the `Var` whenever `arg` changes.
     pub fn cache_state<Arg, Input, Output>(
@@ -38,17 +172,60 @@ impl Context {
         Input: Borrow<Arg> + 'static,
         Output: 'static,
     {
-        let var = self
-            .cache
-            .cache(id, arg, |arg| Var::new(topo::CallId::current(), self.waker.clone(), init(arg)));
+        let var = self.cache.cache(id, arg, |arg| self.new_var(init(arg)));
+        Var::root(var)
+    }
+
+    /// Load a [`crate::state::Var`] which is included in the runtime's
+    /// snapshots, initializing it from a restored snapshot if one is available.
+    pub fn persisted_state<Output>(
+        &self,
+        id: &topo::CallId,
+        init: impl FnOnce() -> Output,
+    ) -> (Commit<Output>, Key<Output>)
+    where
+        Output: Persist + 'static,
+    {
+        let var = self.cache.cache(id, &(), |()| {
+            let initial = self.persistence.take_restored(id).unwrap_or_else(init);
+            let var = self.new_var(initial);
+            self.persistence.register(*id, &var);
+            var
+        });
+        Var::root(var)
+    }
+
+    /// Load a [`crate::state::Var`] which records its previous commits,
+    /// allowing them to be undone and reverted to.
+    pub fn history_state<Output>(
+        &self,
+        id: &topo::CallId,
+        init: impl FnOnce() -> Output,
+    ) -> (Commit<Output>, Key<Output>)
+    where
+        Output: 'static,
+    {
+        let var = self.cache.cache(id, &(), |()| {
+            let var = self.new_var(init());
+            var.lock().enable_history(self.revision, self.histories.capacity());
+            self.histories.register(*id, &var);
+            var
+        });
         Var::root(var)
     }
 
+    fn new_var<State: 'static>(&self, inner: State) -> Arc<Mutex<Var<State>>> {
+        let var = Var::new(topo::CallId::current(), self.waker.clone(), self.lanes.clone(), inner);
+        self.inspector.register(&var);
+        var
+    }
+
     /// Load a value from the future returned by `init` whenever `capture`
     /// changes, returning the result of calling `with` with the loaded
     /// value. Cancels the running future if there's no longer interest
     /// in its output, indicated by a revision in which this was not called with
-    /// the given `id`.
+    /// the given `id`. Suspends the enclosing [`crate::suspense`] boundary, if
+    /// any, while the future is pending.
     ///
     /// # Panics
     ///
@@ -68,40 +245,91 @@ impl Context {
         Output: 'static,
         Ret: 'static,
     {
-        let (_, set_result): (_, Key<Poll<Output>>) = self.cache_state(id, &(), |()| Poll::Pending);
-        let mut set_result2 = set_result.clone();
-        self.cache.hold(id, arg, |arg| {
-            // before we spawn the new task we need to mark it pending
-            set_result.force(Poll::Pending);
-
-            let (fut, aborter) = abortable(init(arg));
-            let task = async move {
-                if let Ok(to_store) = fut.await {
-                    set_result.update(|_| Some(Poll::Ready(to_store)));
-                }
-            };
-            self.spawner
-                .0
-                .spawn_local_obj(Box::pin(task).into())
-                .expect("that set_task_executor has been called");
-            scopeguard::guard(aborter, |a| a.abort())
-        });
-
-        set_result2.refresh();
+        self.subscribe_with(id, arg, |arg| init(arg).into_stream(), with)
+    }
 
-        match &*set_result2 {
-            Poll::Ready(ref stored) => Poll::Ready(with(stored)),
-            Poll::Pending => Poll::Pending,
+    /// Subscribe to the stream returned by `init` whenever `capture` changes,
+    /// returning the result of calling `with` with the latest item it
+    /// produced. Each item is committed to a state variable, waking the
+    /// runtime. Cancels the running stream if there's no longer interest in its
+    /// items, indicated by a revision in which this was not called with the
+    /// given `id`. Suspends the enclosing [`crate::suspense`] boundary, if any,
+    /// until the first item has been produced.
+    ///
+    /// # Panics
+    ///
+    /// If the [`super::Runtime`] from which `self` was created did not have
+    /// a valid call to `set_task_executor`.
+    pub fn subscribe_with<Arg, Input, S, Item, Ret>(
+        &self,
+        id: &topo::CallId,
+        arg: &Arg,
+        init: impl FnOnce(&Input) -> S,
+        with: impl FnOnce(&Item) -> Ret,
+    ) -> Poll<Ret>
+    where
+        Arg: PartialEq<Input> + ToOwned<Owned = Input> + ?Sized,
+        Input: Borrow<Arg> + 'static,
+        S: Stream<Item = Item> + 'static,
+        Item: 'static,
+        Ret: 'static,
+    {
+        let latest = load::subscribe_with(self, id, arg, init, with);
+        if latest.is_pending() {
+            Suspense::record_pending();
         }
+        latest
+    }
+
+    /// Load a value from the future returned by `init` like
+    /// [`Context::load_with`], without suspending the enclosing
+    /// [`crate::suspense`] boundary while it's pending.
+    pub fn load_unsuspended<Fut>(&self, id: &topo::CallId, init: impl FnOnce() -> Fut) -> Poll<()>
+    where
+        Fut: Future<Output = ()> + 'static,
+    {
+        load::subscribe_with(self, id, &(), |()| init().into_stream(), |()| ())
+    }
+}
+
+impl<Arg, Input, S> Loader<Arg, Input, S> for Context
+where
+    Arg: PartialEq<Input> + ToOwned<Owned = Input> + ?Sized,
+    Input: Borrow<Arg> + 'static,
+    S: Stream + 'static,
+    S::Item: 'static,
+{
+    fn latest(&self, id: &topo::CallId) -> Key<Poll<S::Item>> {
+        self.cache_state(id, &(), |()| Poll::Pending).1
+    }
+
+    fn hold(&self, id: &topo::CallId, arg: &Arg, init: impl FnOnce(&Input) -> Running) {
+        self.cache.hold(id, arg, init);
+    }
+
+    fn spawn(&self, stream: S, latest: Key<Poll<S::Item>>) -> AbortHandle {
+        let (task, aborter) = load::commit_each(stream, latest);
+        self.spawner
+            .0
+            .spawn_local_obj(Box::pin(task).into())
+            .expect("that set_task_executor has been called");
+        aborter
     }
 }
 
 impl super::Runtime {
-    pub(crate) fn context_handle(&self) -> Context {
+    pub(crate) fn context_handle(&self, applied_priority: Priority, budget: Budget) -> Context {
         Context {
             revision: self.revision,
+            applied_priority,
+            budget,
             spawner: self.spawner.clone(),
             cache: self.cache.clone(),
+            effects: self.effects.clone(),
+            histories: self.histories.clone(),
+            inspector: self.inspector.clone(),
+            lanes: self.lanes.clone(),
+            persistence: self.persistence.clone(),
             waker: self.wk.clone(),
         }
     }
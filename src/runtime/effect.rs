@@ -0,0 +1,202 @@
+use super::Revision;
+use std::{
+    cell::{Cell, RefCell},
+    fmt::{Debug, Formatter, Result as FmtResult},
+    rc::{Rc, Weak},
+};
+
+type Callback = Box<dyn FnOnce()>;
+
+/// The position of an effect's most recent declaration, used to order
+/// cleanups of effects which went unused.
+type Order = (Revision, usize);
+
+/// Effects which are waiting to run after the current revision, and cleanups
+/// from effects whose callsites went unused.
+#[derive(Default)]
+pub(crate) struct Effects {
+    declared: Cell<usize>,
+    pending: RefCell<Vec<Callback>>,
+    dropped: RefCell<Vec<(Order, Callback)>>,
+}
+
+impl Effects {
+    /// Mark `cell` as declared at the next position in `revision`.
+    pub fn declare<Input>(&self, cell: &EffectCell<Input>, revision: Revision) {
+        let position = self.declared.get();
+        self.declared.set(position + 1);
+        cell.order.set((revision, position));
+    }
+
+    /// Run `op` after the current revision's root function returns.
+    pub fn enqueue(&self, op: impl FnOnce() + 'static) {
+        self.pending.borrow_mut().push(Box::new(op));
+    }
+
+    /// Run the cleanups of any effects which were dropped in the order they
+    /// were last declared, then run this revision's effects in the order they
+    /// were declared.
+    pub fn flush(&self) {
+        let mut dropped = self.dropped.take();
+        dropped.sort_by_key(|(order, _)| *order);
+        for (_, cleanup) in dropped {
+            cleanup();
+        }
+
+        for effect in self.pending.take() {
+            effect();
+        }
+        self.declared.set(0);
+    }
+}
+
+impl Debug for Effects {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.debug_struct("Effects")
+            .field("pending", &self.pending.borrow().len())
+            .field("dropped", &self.dropped.borrow().len())
+            .finish()
+    }
+}
+
+impl Drop for Effects {
+    fn drop(&mut self) {
+        // effects dropped along with the runtime still need to be cleaned up
+        self.pending.get_mut().clear();
+        self.flush();
+    }
+}
+
+/// The storage for a single effect callsite, cached by the runtime.
+pub(crate) struct EffectCell<Input> {
+    input: RefCell<Option<Input>>,
+    cleanup: RefCell<Option<Callback>>,
+    order: Cell<Order>,
+    effects: Weak<Effects>,
+}
+
+impl<Input> EffectCell<Input> {
+    pub fn new(effects: &Rc<Effects>) -> Self {
+        Self {
+            input: RefCell::new(None),
+            cleanup: RefCell::new(None),
+            order: Cell::new(Default::default()),
+            effects: Rc::downgrade(effects),
+        }
+    }
+
+    /// Store `input` if it differs from the input of the last run, returning
+    /// whether it changed.
+    pub fn update<Arg>(&self, arg: &Arg) -> bool
+    where
+        Arg: PartialEq<Input> + ToOwned<Owned = Input> + ?Sized,
+    {
+        let mut input = self.input.borrow_mut();
+        match &*input {
+            Some(prev) if arg == prev => false,
+            _ => {
+                *input = Some(arg.to_owned());
+                true
+            }
+        }
+    }
+
+    /// Clean up the previous run, if any, and run `op` with the current input.
+    pub fn run<Cleanup>(&self, op: impl FnOnce(&Input) -> Cleanup)
+    where
+        Cleanup: FnOnce() + 'static,
+    {
+        if let Some(cleanup) = self.cleanup.take() {
+            cleanup();
+        }
+        let input = self.input.borrow();
+        let cleanup = op(input.as_ref().expect("effects only run after storing an input"));
+        self.cleanup.replace(Some(Box::new(cleanup)));
+    }
+}
+
+impl<Input> Drop for EffectCell<Input> {
+    fn drop(&mut self) {
+        if let Some(cleanup) = self.cleanup.take() {
+            if let Some(effects) = self.effects.upgrade() {
+                effects.dropped.borrow_mut().push((self.order.get(), cleanup));
+            } else {
+                cleanup();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{effect, runtime::RunLoop};
+    use std::{cell::RefCell, rc::Rc};
+
+    type Log = Rc<RefCell<Vec<String>>>;
+
+    fn logged(log: &Log, name: &'static str, arg: &u32) {
+        let log = log.clone();
+        effect(arg, move |value| {
+            log.borrow_mut().push(format!("run {} {}", name, value));
+            move || log.borrow_mut().push(format!("cleanup {}", name))
+        });
+    }
+
+    #[test]
+    fn effects_run_after_root_in_declaration_order() {
+        let log = Log::default();
+        let args = Rc::new(RefCell::new([1, 2, 3]));
+        let (root_log, root_args) = (log.clone(), args.clone());
+        let mut rt = RunLoop::new(move || {
+            let args = root_args.borrow();
+            logged(&root_log, "a", &args[0]);
+            logged(&root_log, "b", &args[1]);
+            logged(&root_log, "c", &args[2]);
+            root_log.borrow_mut().push(String::from("root"));
+        });
+        let mut revision = || {
+            rt.run_once();
+            log.take()
+        };
+
+        assert_eq!(revision(), ["root", "run a 1", "run b 2", "run c 3"]);
+        assert_eq!(revision(), ["root"], "effects only run when their arg changes");
+
+        *args.borrow_mut() = [4, 2, 5];
+        assert_eq!(revision(), ["root", "cleanup a", "run a 4", "cleanup c", "run c 5"]);
+    }
+
+    #[test]
+    fn unused_effects_are_cleaned_up_in_declaration_order() {
+        let log = Log::default();
+        let shown = Rc::new(RefCell::new(vec!["a", "b", "c", "d"]));
+        let (root_log, root_shown) = (log.clone(), shown.clone());
+        let mut rt = RunLoop::new(move || {
+            for name in root_shown.borrow().iter() {
+                topo::call_in_slot(name, || logged(&root_log, name, &0));
+            }
+        });
+        let mut revision = || {
+            rt.run_once();
+            log.take()
+        };
+
+        assert_eq!(revision(), ["run a 0", "run b 0", "run c 0", "run d 0"]);
+
+        *shown.borrow_mut() = vec!["d", "b", "e"];
+        assert_eq!(revision(), ["cleanup a", "cleanup c", "run e 0"]);
+
+        shown.borrow_mut().clear();
+        assert_eq!(revision(), ["cleanup d", "cleanup b", "cleanup e"]);
+    }
+
+    #[test]
+    fn cleanups_run_when_runtime_is_dropped() {
+        let log = Log::default();
+        let root_log = log.clone();
+        let mut rt = RunLoop::new(move || logged(&root_log, "a", &0));
+        rt.run_once();
+        drop(rt);
+        assert_eq!(log.take(), ["run a 0", "cleanup a"]);
+    }
+}
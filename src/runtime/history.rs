@@ -0,0 +1,248 @@
+use super::{Registry, Revision, Var};
+use crate::Commit;
+use dyn_cache::local::SharedLocalCache;
+use parking_lot::Mutex;
+use std::{cell::Cell, collections::VecDeque, sync::Arc};
+
+/// The number of commits each state variable's history retains by default.
+pub(crate) const DEFAULT_HISTORY_CAPACITY: usize = 100;
+
+/// The previous commits to a state variable declared with
+/// [`crate::history_state`], retaining at most `capacity` of the most recent
+/// updates and rooted commits.
+pub(crate) struct History<State> {
+    capacity: usize,
+    undo: VecDeque<Commit<State>>,
+    redo: Vec<Commit<State>>,
+    /// The commits which were rooted by each revision, oldest first.
+    timeline: VecDeque<(Revision, Commit<State>)>,
+}
+
+impl<State> History<State> {
+    pub fn new(revision: Revision, initial: &Commit<State>, capacity: usize) -> Self {
+        let mut timeline = VecDeque::new();
+        if capacity > 0 {
+            timeline.push_back((revision, initial.clone()));
+        }
+        Self { capacity, undo: VecDeque::new(), redo: vec![], timeline }
+    }
+
+    /// Record that `latest` is being replaced by a new update, which discards
+    /// any undone commits.
+    pub fn record_update(&mut self, latest: &Commit<State>) {
+        push_bounded(&mut self.undo, latest.clone(), self.capacity);
+        self.redo.clear();
+    }
+
+    /// Record that `commit` was rooted during `revision`.
+    pub fn record_rooted(&mut self, revision: Revision, commit: &Commit<State>) {
+        push_bounded(&mut self.timeline, (revision, commit.clone()), self.capacity);
+    }
+
+    /// Returns the commit before `latest`, if any.
+    pub fn undo(&mut self, latest: &Commit<State>) -> Option<Commit<State>> {
+        let previous = self.undo.pop_back()?;
+        self.redo.push(latest.clone());
+        Some(previous)
+    }
+
+    /// Returns the commit most recently undone, if any.
+    pub fn redo(&mut self, latest: &Commit<State>) -> Option<Commit<State>> {
+        let next = self.redo.pop()?;
+        push_bounded(&mut self.undo, latest.clone(), self.capacity);
+        Some(next)
+    }
+
+    /// Returns the commit which was rooted during `revision`, if the state
+    /// variable existed then and the commit is still retained.
+    pub fn at(&self, revision: Revision) -> Option<&Commit<State>> {
+        self.timeline.iter().rev().find(|(rooted, _)| *rooted <= revision).map(|(_, c)| c)
+    }
+}
+
+/// Push `item` onto the back of `items`, dropping the oldest items beyond
+/// `capacity`.
+fn push_bounded<T>(items: &mut VecDeque<T>, item: T, capacity: usize) {
+    items.push_back(item);
+    while items.len() > capacity {
+        items.pop_front();
+    }
+}
+
+/// A state variable with a history which can be reverted.
+trait RevertVar {
+    fn revert_to(&self, revision: Revision);
+}
+
+impl<State> RevertVar for Mutex<Var<State>> {
+    fn revert_to(&self, revision: Revision) {
+        self.lock().revert_to(revision);
+    }
+}
+
+/// Tracks the state variables with histories in a runtime.
+#[derive(Debug)]
+pub(crate) struct Histories {
+    capacity: Cell<usize>,
+    vars: Registry<dyn RevertVar>,
+}
+
+impl Default for Histories {
+    fn default() -> Self {
+        Self { capacity: Cell::new(DEFAULT_HISTORY_CAPACITY), vars: Default::default() }
+    }
+}
+
+impl Histories {
+    /// Returns the number of commits retained by newly created histories.
+    pub fn capacity(&self) -> usize {
+        self.capacity.get()
+    }
+
+    pub fn set_capacity(&self, capacity: usize) {
+        self.capacity.set(capacity);
+    }
+
+    /// Track a newly created state variable with a history.
+    pub fn register<State: 'static>(&self, id: topo::CallId, var: &Arc<Mutex<Var<State>>>) {
+        let var: Arc<dyn RevertVar> = var.clone();
+        self.vars.register(id, &var);
+    }
+
+    /// Revert each state variable which is still cached to its value at
+    /// `revision`.
+    pub fn revert_to(&self, cache: &SharedLocalCache, revision: Revision) {
+        for (_, var) in self.vars.live(cache) {
+            var.revert_to(revision);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{history_state, restartable, runtime::RunLoop, state};
+
+    #[test]
+    fn undo_and_redo() {
+        let mut rt = RunLoop::new(|| history_state(|| 0u8));
+        let (_, key) = rt.run_once();
+        assert!(!key.undo(), "nothing to undo yet");
+
+        key.set(1);
+        rt.run_once();
+        key.set(2);
+        key.set(3);
+        assert_eq!(*rt.run_once().0, 3);
+
+        assert!(key.undo());
+        assert_eq!(*rt.run_once().0, 2, "updates between revisions are recorded");
+        assert!(key.undo());
+        assert!(key.undo());
+        assert!(!key.undo());
+        assert_eq!(*rt.run_once().0, 0);
+
+        assert!(key.redo());
+        assert_eq!(*rt.run_once().0, 1);
+
+        key.set(5);
+        assert!(!key.redo(), "new updates discard undone commits");
+        assert!(key.undo());
+        assert_eq!(*rt.run_once().0, 1);
+    }
+
+    #[test]
+    fn undo_restores_the_same_commit() {
+        let mut rt = RunLoop::new(|| history_state(|| String::from("first")));
+        let (first, key) = rt.run_once();
+        key.set(String::from("second"));
+        rt.run_once();
+
+        key.undo();
+        let (restored, _) = rt.run_once();
+        assert_eq!(restored, first);
+        assert!(std::ptr::eq(&*restored, &*first), "commits are reused, not cloned");
+    }
+
+    #[test]
+    fn plain_state_has_no_history() {
+        let mut rt = RunLoop::new(|| state(|| 0u8));
+        let (_, key) = rt.run_once();
+        key.set(1);
+        rt.run_once();
+        assert!(!key.undo());
+        assert_eq!(*rt.run_once().0, 1);
+    }
+
+    #[test]
+    fn revert_all_state_to_revision() {
+        let mut rt = RunLoop::new(|| (history_state(|| 0u32), history_state(|| 'a'), state(|| 0)));
+        let ((_, count), (_, letter), (_, plain)) = rt.run_once();
+        let start = rt.revision();
+
+        count.set(1);
+        rt.run_once();
+        let middle = rt.revision();
+
+        letter.set('b');
+        count.set(2);
+        plain.set(1);
+        rt.run_once();
+
+        rt.revert_to(middle);
+        let ((count, _), (letter, _), (plain, _)) = rt.run_once();
+        assert_eq!((*count, *letter, *plain), (1, 'a', 1), "only histories are reverted");
+
+        rt.revert_to(start);
+        let ((count, count_key), (letter, _), _) = rt.run_once();
+        assert_eq!((*count, *letter), (0, 'a'));
+
+        assert!(count_key.undo(), "reverting can be undone");
+        assert_eq!(*rt.run_once().0 .0, 1);
+    }
+
+    #[test]
+    fn revert_state_in_skipped_scopes() {
+        let mut rt = RunLoop::new(|| {
+            let (_, outer) = state(|| 0u8);
+            let (count, count_key) = restartable(&(), |()| history_state(|| 0u8));
+            (outer, *count, count_key)
+        });
+        let (outer, _, count) = rt.run_once();
+        let start = rt.revision();
+
+        count.set(5);
+        assert_eq!(rt.run_once().1, 5);
+
+        outer.set(1);
+        rt.run_once();
+
+        rt.revert_to(start);
+        assert_eq!(rt.run_once().1, 0, "the skipped scope's state is reverted");
+    }
+
+    #[test]
+    fn histories_drop_their_oldest_commits() {
+        let mut rt = RunLoop::new(|| history_state(|| 0u8));
+        rt.set_history_capacity(2);
+        let (_, key) = rt.run_once();
+        let start = rt.revision();
+
+        let mut rooted = vec![];
+        for i in 1..=4 {
+            key.set(i);
+            rt.run_once();
+            rooted.push(rt.revision());
+        }
+
+        assert!(key.undo());
+        assert!(key.undo());
+        assert!(!key.undo(), "only two updates are retained");
+        assert_eq!(*rt.run_once().0, 2);
+
+        rt.revert_to(start);
+        assert_eq!(*rt.run_once().0, 2, "the initial commit was dropped");
+
+        rt.revert_to(rooted[3]);
+        assert_eq!(*rt.run_once().0, 4, "recently rooted commits are retained");
+    }
+}
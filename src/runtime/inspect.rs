@@ -0,0 +1,482 @@
+use super::{Priority, Registry, Revision, Var};
+use dyn_cache::local::SharedLocalCache;
+use parking_lot::Mutex;
+use std::{
+    any::{type_name, Any, TypeId},
+    cell::RefCell,
+    collections::{BTreeMap, HashMap},
+    error::Error,
+    fmt::{Debug, Display, Formatter, Result as FmtResult},
+    sync::Arc,
+};
+use topo::{CallId, CallPath, PathSegment};
+
+/// A read-only view of a [`super::Runtime`]'s call tree as of its most recent
+/// revision, returned by [`super::Runtime::inspect`].
+///
+/// Each [`CallNode`] lists the values cached for that call and the state
+/// variables it declared. Calls which didn't cache anything themselves are only
+/// included as the ancestors of calls which did. The `Display` impl writes the
+/// tree as an indented outline, for logging or dumping to a file.
+#[derive(Clone, Debug)]
+pub struct Inspection {
+    revision: Revision,
+    calls: Vec<CallNode>,
+}
+
+impl Inspection {
+    /// Returns the revision the runtime had completed when this was taken.
+    pub fn revision(&self) -> Revision {
+        self.revision
+    }
+
+    /// Returns the outermost calls in the tree.
+    pub fn calls(&self) -> &[CallNode] {
+        &self.calls
+    }
+
+    /// Returns every state variable in the tree, parents before children.
+    pub fn states(&self) -> Vec<&StateValue> {
+        fn collect<'a>(calls: &'a [CallNode], states: &mut Vec<&'a StateValue>) {
+            for call in calls {
+                states.extend(&call.states);
+                collect(&call.children, states);
+            }
+        }
+        let mut states = vec![];
+        collect(&self.calls, &mut states);
+        states
+    }
+}
+
+impl Display for Inspection {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        fn write_calls(f: &mut Formatter, calls: &[CallNode], depth: usize) -> FmtResult {
+            for call in calls {
+                writeln!(f, "{:indent$}{}", "", call.segment, indent = depth * 2)?;
+                let indent = (depth + 1) * 2;
+                for cached in &call.cached {
+                    writeln!(f, "{:indent$}cached {}", "", cached, indent = indent)?;
+                }
+                for state in &call.states {
+                    writeln!(f, "{:indent$}state {}", "", state, indent = indent)?;
+                }
+                write_calls(f, &call.children, depth + 1)?;
+            }
+            Ok(())
+        }
+        writeln!(f, "revision {:?}", self.revision)?;
+        write_calls(f, &self.calls, 0)
+    }
+}
+
+/// A call in an [`Inspection`].
+#[derive(Clone, Debug)]
+pub struct CallNode {
+    segment: PathSegment,
+    cached: Vec<CachedValue>,
+    states: Vec<StateValue>,
+    children: Vec<CallNode>,
+}
+
+impl CallNode {
+    /// Returns the location and slot of this call within its parent.
+    pub fn segment(&self) -> &PathSegment {
+        &self.segment
+    }
+
+    /// Returns the values cached for this call, including those backing its
+    /// state variables.
+    pub fn cached(&self) -> &[CachedValue] {
+        &self.cached
+    }
+
+    /// Returns the state variables declared by this call.
+    pub fn states(&self) -> &[StateValue] {
+        &self.states
+    }
+
+    /// Returns the calls made within this one, ordered by location.
+    pub fn children(&self) -> &[CallNode] {
+        &self.children
+    }
+}
+
+/// A value cached for a [`CallNode`], described by its types.
+#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub struct CachedValue {
+    output_type: &'static str,
+    input_type: &'static str,
+}
+
+impl CachedValue {
+    /// Returns the name of the cached value's type.
+    pub fn output_type(&self) -> &'static str {
+        self.output_type
+    }
+
+    /// Returns the name of the type of the argument the value was cached for.
+    pub fn input_type(&self) -> &'static str {
+        self.input_type
+    }
+}
+
+impl Display for CachedValue {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "{} from {}", self.output_type, self.input_type)
+    }
+}
+
+/// A state variable in an [`Inspection`].
+///
+/// Values are only shown for types registered with
+/// [`super::Runtime::inspect_with_debug`], which includes primitives and
+/// strings.
+#[derive(Clone, Debug)]
+pub struct StateValue {
+    path: CallPath,
+    type_name: &'static str,
+    value: Option<String>,
+    pending: Option<(Option<String>, Priority)>,
+}
+
+impl StateValue {
+    /// Returns the path of the call which declared the state variable, which
+    /// can be passed to [`super::Runtime::set_state`].
+    pub fn path(&self) -> &CallPath {
+        &self.path
+    }
+
+    /// Returns the name of the state variable's type.
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+
+    /// Returns the `Debug` representation of the current value.
+    pub fn value(&self) -> Option<&str> {
+        self.value.as_deref()
+    }
+
+    /// Returns the priority of the commit waiting to be applied, if any.
+    pub fn pending(&self) -> Option<Priority> {
+        self.pending.as_ref().map(|(_, priority)| *priority)
+    }
+
+    /// Returns the `Debug` representation of the commit waiting to be applied.
+    pub fn pending_value(&self) -> Option<&str> {
+        self.pending.as_ref().and_then(|(value, _)| value.as_deref())
+    }
+}
+
+impl Display for StateValue {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.write_str(self.type_name)?;
+        if let Some(value) = &self.value {
+            write!(f, " = {}", value)?;
+        }
+        if let Some((value, priority)) = &self.pending {
+            write!(f, " (pending {:?}", priority)?;
+            if let Some(value) = value {
+                write!(f, ": {}", value)?;
+            }
+            f.write_str(")")?;
+        }
+        Ok(())
+    }
+}
+
+/// Returned by [`super::Runtime::set_state`] when the commit couldn't be made.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum SetStateError {
+    /// No state variable declared at the path was live in the most recent
+    /// revision.
+    NotFound(CallPath),
+    /// The state variable declared at the path has a different type.
+    WrongType {
+        /// The path of the state variable.
+        path: CallPath,
+        /// The type of the value which was passed.
+        expected: &'static str,
+        /// The type of the state variable.
+        found: &'static str,
+    },
+}
+
+impl Display for SetStateError {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            SetStateError::NotFound(path) => write!(f, "no live state variable at `{}`", path),
+            SetStateError::WrongType { path, expected, found } => {
+                write!(f, "state variable at `{}` has type `{}`, not `{}`", path, found, expected)
+            }
+        }
+    }
+}
+
+impl Error for SetStateError {}
+
+type DebugFn = fn(&dyn Any) -> String;
+
+fn debug_any<T: Debug + 'static>(value: &dyn Any) -> String {
+    format!("{:?}", value.downcast_ref::<T>().unwrap())
+}
+
+/// Tracks the state variables created by a runtime and how to show their
+/// values, so that they can be listed and set by debugging tools.
+pub(crate) struct Inspector {
+    vars: Registry<dyn InspectVar>,
+    debug: RefCell<HashMap<TypeId, DebugFn>>,
+}
+
+impl Default for Inspector {
+    fn default() -> Self {
+        let inspector = Self { vars: Default::default(), debug: Default::default() };
+        macro_rules! register_debug {
+            ($($ty:ty),+) => {$(
+                inspector.register_debug::<$ty>();
+            )+};
+        }
+        register_debug!(
+            (),
+            bool,
+            char,
+            f32,
+            f64,
+            i8,
+            i16,
+            i32,
+            i64,
+            i128,
+            isize,
+            u8,
+            u16,
+            u32,
+            u64,
+            u128,
+            usize,
+            String,
+            &'static str
+        );
+        inspector
+    }
+}
+
+impl Inspector {
+    /// Track a newly created state variable for as long as it's alive.
+    pub fn register<State: 'static>(&self, var: &Arc<Mutex<Var<State>>>) {
+        let id = var.lock().id();
+        let var: Arc<dyn InspectVar> = var.clone();
+        self.vars.register(id, &var);
+    }
+
+    /// Show the `Debug` representation of state variables with type `T`.
+    pub fn register_debug<T: Debug + 'static>(&self) {
+        self.debug.borrow_mut().insert(TypeId::of::<T>(), debug_any::<T>);
+    }
+
+    pub fn inspect(&self, revision: Revision, cache: &SharedLocalCache) -> Inspection {
+        let mut root = Builder::default();
+        cache.inspect(|stored| {
+            if let Some(id) = stored.scope::<CallId>() {
+                root.at(&id.path()).cached.push(CachedValue {
+                    output_type: stored.output_type(),
+                    input_type: stored.input_type(),
+                });
+            }
+        });
+
+        let debug = self.debug.borrow();
+        for (_, var) in self.vars.live(cache) {
+            let state = var.describe(&debug);
+            root.at(&state.path).states.push(state);
+        }
+
+        Inspection { revision, calls: root.into_calls() }
+    }
+
+    /// Enqueue a commit to the live state variable declared at `path`.
+    pub fn set_state<State: 'static>(
+        &self,
+        cache: &SharedLocalCache,
+        path: &CallPath,
+        state: State,
+    ) -> Result<(), SetStateError> {
+        let mut state: Box<dyn Any> = Box::new(state);
+        let mut found = None;
+        for (id, var) in self.vars.live(cache).into_iter().rev() {
+            if id.path() != *path {
+                continue;
+            }
+            match var.set(state) {
+                Ok(()) => return Ok(()),
+                Err(rejected) => {
+                    found = Some(var.type_name());
+                    state = rejected;
+                }
+            }
+        }
+
+        Err(match found {
+            Some(found) => SetStateError::WrongType {
+                path: path.clone(),
+                expected: type_name::<State>(),
+                found,
+            },
+            None => SetStateError::NotFound(path.clone()),
+        })
+    }
+}
+
+impl Debug for Inspector {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.debug_struct("Inspector")
+            .field("vars", &self.vars)
+            .field("debug", &self.debug.borrow().len())
+            .finish()
+    }
+}
+
+/// A state variable with its type erased.
+trait InspectVar {
+    fn type_name(&self) -> &'static str;
+    fn describe(&self, debug: &HashMap<TypeId, DebugFn>) -> StateValue;
+    /// Enqueue a commit of `state`, returning it if it has the wrong type.
+    fn set(&self, state: Box<dyn Any>) -> Result<(), Box<dyn Any>>;
+}
+
+impl<State: 'static> InspectVar for Mutex<Var<State>> {
+    fn type_name(&self) -> &'static str {
+        type_name::<State>()
+    }
+
+    fn describe(&self, debug: &HashMap<TypeId, DebugFn>) -> StateValue {
+        let var = self.lock();
+        let format = debug.get(&TypeId::of::<State>());
+        let show = |state: &State| format.map(|format| format(state));
+        let (current, pending) = var.commits();
+        StateValue {
+            path: var.id().path(),
+            type_name: type_name::<State>(),
+            value: show(current),
+            pending: pending.map(|(commit, priority)| (show(commit), *priority)),
+        }
+    }
+
+    fn set(&self, state: Box<dyn Any>) -> Result<(), Box<dyn Any>> {
+        let state = state.downcast::<State>()?;
+        self.lock().enqueue_commit(*state);
+        Ok(())
+    }
+}
+
+/// A call in an [`Inspection`] which is still being assembled.
+#[derive(Default)]
+struct Builder {
+    cached: Vec<CachedValue>,
+    states: Vec<StateValue>,
+    children: BTreeMap<PathSegment, Builder>,
+}
+
+impl Builder {
+    /// Returns the descendant at `path`, adding any missing calls along it.
+    fn at(&mut self, path: &CallPath) -> &mut Builder {
+        path.segments()
+            .iter()
+            .fold(self, |node, segment| node.children.entry(segment.clone()).or_default())
+    }
+
+    fn into_calls(self) -> Vec<CallNode> {
+        self.children
+            .into_iter()
+            .map(|(segment, mut builder)| {
+                builder.cached.sort();
+                CallNode {
+                    segment,
+                    cached: std::mem::take(&mut builder.cached),
+                    states: std::mem::take(&mut builder.states),
+                    children: builder.into_calls(),
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{cache, runtime::RunLoop, state, Key};
+
+    #[topo::nested]
+    fn counter(label: &'static str) -> Key<u32> {
+        cache(label, |label| label.len());
+        state(|| 0u32).1
+    }
+
+    #[test]
+    fn calls_list_their_cached_values_and_states() {
+        let mut rt = RunLoop::new(|| (counter("first"), counter("second")));
+        let (first, _) = rt.run_once();
+        first.set(1);
+
+        let inspection = rt.inspect();
+        assert_eq!(inspection.revision(), Revision(1));
+        let counters = inspection.calls()[0].children();
+        assert_eq!(counters.len(), 2);
+
+        let (cached, state) = (&counters[0].children()[0], &counters[0].children()[1]);
+        let expected = CachedValue { output_type: "usize", input_type: "alloc::string::String" };
+        assert_eq!(cached.cached(), &[expected]);
+        assert!(state.cached()[0].output_type().contains("Var<u32>"));
+
+        let first = &state.states()[0];
+        assert_eq!(first.type_name(), "u32");
+        assert_eq!((first.value(), first.pending()), (Some("0"), Some(Priority::Urgent)));
+        assert_eq!(first.pending_value(), Some("1"));
+        assert_eq!(inspection.states().len(), 2);
+
+        let dump = inspection.to_string();
+        assert!(dump.starts_with("revision r1\n"));
+        assert!(dump.contains("\n      state u32 = 0 (pending Urgent: 1)\n"), "{}", dump);
+        assert!(dump.contains("\n      state u32 = 0\n"), "{}", dump);
+    }
+
+    #[test]
+    fn values_are_shown_for_registered_types() {
+        let mut rt = RunLoop::new(|| state(|| vec![1u8, 2]));
+        rt.run_once();
+        assert_eq!(rt.inspect().states()[0].value(), None);
+
+        rt.inspect_with_debug::<Vec<u8>>();
+        assert_eq!(rt.inspect().states()[0].value(), Some("[1, 2]"));
+    }
+
+    #[test]
+    fn set_state_commits_to_live_variables() {
+        let mut rt = RunLoop::new(|| {
+            let (shown, key) = state(|| true);
+            if *shown {
+                state(|| String::from("first"));
+            }
+            key
+        });
+        let shown = rt.run_once();
+        let path = rt.inspect().states()[1].path().clone();
+
+        let expected = "alloc::string::String";
+        assert_eq!(
+            rt.set_state(&path, 1u8),
+            Err(SetStateError::WrongType { path: path.clone(), expected: "u8", found: expected })
+        );
+        rt.set_state(&path, String::from("second")).unwrap();
+        rt.run_once();
+        assert_eq!(rt.inspect().states()[1].value(), Some("\"second\""));
+
+        shown.set(false);
+        rt.run_once();
+        assert_eq!(rt.inspect().states().len(), 1);
+        assert_eq!(
+            rt.set_state(&path, String::from("third")),
+            Err(SetStateError::NotFound(path.clone()))
+        );
+    }
+}
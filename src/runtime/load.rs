@@ -0,0 +1,73 @@
+use crate::Key;
+use futures::{
+    future::{self, abortable, AbortHandle},
+    Future, FutureExt, Stream, StreamExt,
+};
+use scopeguard::ScopeGuard;
+use std::task::Poll;
+
+/// A spawned task which is aborted when it's dropped from the cache.
+pub(crate) type Running = ScopeGuard<AbortHandle, fn(AbortHandle)>;
+
+/// The cache and executor of a [`super::Runtime`] or [`super::SendRuntime`],
+/// which load the items of streams into state variables.
+pub(crate) trait Loader<Arg: ?Sized, Input, S: Stream> {
+    /// Load the state variable which holds the latest item of the stream
+    /// loaded at `id`.
+    fn latest(&self, id: &topo::CallId) -> Key<Poll<S::Item>>;
+
+    /// Run `init` whenever `arg` changes, keeping the returned task running
+    /// until `arg` changes again or `id` isn't loaded in a revision.
+    fn hold(&self, id: &topo::CallId, arg: &Arg, init: impl FnOnce(&Input) -> Running);
+
+    /// Spawn a task which commits each item of `stream` to `latest`.
+    ///
+    /// # Panics
+    ///
+    /// If the runtime doesn't have a valid task executor.
+    fn spawn(&self, stream: S, latest: Key<Poll<S::Item>>) -> AbortHandle;
+}
+
+/// Subscribe to the stream returned by `init` whenever `arg` changes, returning
+/// the result of calling `with` with the latest item it produced. Shared by
+/// [`super::Context::subscribe_with`] and [`super::SendContext::load_with`].
+pub(crate) fn subscribe_with<Arg, Input, S, Ret>(
+    loader: &impl Loader<Arg, Input, S>,
+    id: &topo::CallId,
+    arg: &Arg,
+    init: impl FnOnce(&Input) -> S,
+    with: impl FnOnce(&S::Item) -> Ret,
+) -> Poll<Ret>
+where
+    Arg: ?Sized,
+    S: Stream,
+{
+    let set_latest = loader.latest(id);
+    let mut latest = set_latest.clone();
+    loader.hold(id, arg, |arg| {
+        // before we spawn the new task we need to mark it pending
+        set_latest.force(Poll::Pending);
+        let aborter = loader.spawn(init(arg), set_latest);
+        scopeguard::guard(aborter, |a| a.abort())
+    });
+
+    latest.refresh();
+
+    match &*latest {
+        Poll::Ready(ref stored) => Poll::Ready(with(stored)),
+        Poll::Pending => Poll::Pending,
+    }
+}
+
+/// Returns a task which commits each item of `stream` to `latest`, and a
+/// handle to abort it.
+pub(crate) fn commit_each<S: Stream>(
+    stream: S,
+    latest: Key<Poll<S::Item>>,
+) -> (impl Future<Output = ()>, AbortHandle) {
+    let (task, aborter) = abortable(stream.for_each(move |item| {
+        latest.update(|_| Some(Poll::Ready(item)));
+        future::ready(())
+    }));
+    (task.map(drop), aborter)
+}
@@ -0,0 +1,188 @@
+use illicit::AsContext;
+use std::{
+    cell::RefCell,
+    rc::Rc,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Weak,
+    },
+};
+
+/// The state variables read while computing a memoized value, and how many
+/// times the value has been invalidated.
+#[derive(Debug, Default)]
+pub(crate) struct Memo {
+    generation: u64,
+    reads: Vec<Read>,
+}
+
+impl Memo {
+    /// Returns a number which changes whenever a state variable read during the
+    /// last computation has received a commit.
+    pub fn generation(&mut self) -> u64 {
+        if self.reads.iter().any(Read::is_stale) {
+            self.generation += 1;
+        }
+        self.generation
+    }
+
+    /// Force the value to be recomputed the next time it's read.
+    pub fn invalidate(&mut self) {
+        self.generation += 1;
+    }
+
+    /// Runs `op`, recording the state variables it reads as dependencies.
+    pub fn track<R>(memo: &RefCell<Self>, op: impl FnOnce() -> R) -> R {
+        let tracker = Tracker::default();
+        let ret = tracker.clone().offer(op);
+        memo.borrow_mut().reads = tracker.0.take();
+        ret
+    }
+
+    /// Add this memo's dependencies to those of the enclosing memo, if any.
+    pub fn report(&self) {
+        if let Ok(tracker) = illicit::get::<Tracker>() {
+            tracker.0.borrow_mut().extend(self.reads.iter().cloned());
+        }
+    }
+}
+
+/// Record a read of the state variable with the provided version counter and
+/// the count which was read if a memoized value is currently being computed.
+pub(crate) fn record_read(version: impl FnOnce() -> (Arc<AtomicU64>, u64)) {
+    if let Ok(tracker) = illicit::get::<Tracker>() {
+        let (version, seen) = version();
+        tracker.0.borrow_mut().push(Read { version: Arc::downgrade(&version), seen });
+    }
+}
+
+/// Record a read which is always stale if a memoized value is currently being
+/// computed, ensuring that it's recomputed the next time it's read.
+pub(crate) fn record_stale() {
+    if let Ok(tracker) = illicit::get::<Tracker>() {
+        tracker.0.borrow_mut().push(Read { version: Weak::new(), seen: 0 });
+    }
+}
+
+/// Collects the reads made while computing a memoized value.
+#[derive(Clone, Debug, Default)]
+struct Tracker(Rc<RefCell<Vec<Read>>>);
+
+#[derive(Clone, Debug)]
+struct Read {
+    version: Weak<AtomicU64>,
+    seen: u64,
+}
+
+impl Read {
+    fn is_stale(&self) -> bool {
+        match self.version.upgrade() {
+            Some(version) => version.load(Ordering::Acquire) != self.seen,
+            // the state variable was dropped, it can't be what we read before
+            None => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{memo, runtime::RunLoop, state};
+    use std::cell::Cell;
+
+    #[test]
+    fn recomputes_after_dependency_commits() {
+        let computed = Cell::new(0);
+        let mut rt = RunLoop::new(|| {
+            let (_, first) = state(|| 1);
+            let (_, second) = state(|| 10);
+            let (_, unread) = state(|| 100);
+            let sum = memo(|| {
+                computed.set(computed.get() + 1);
+                *first + *second
+            });
+            (sum, first, second, unread)
+        });
+
+        let (sum, first, second, unread) = rt.run_once();
+        assert_eq!((sum, computed.get()), (11, 1));
+
+        unread.set(200);
+        assert_eq!((rt.run_once().0, computed.get()), (11, 1), "unread state doesn't invalidate");
+
+        first.set(2);
+        assert_eq!((rt.run_once().0, computed.get()), (12, 2));
+        assert_eq!((rt.run_once().0, computed.get()), (12, 2), "only recomputed once");
+
+        second.set(20);
+        assert_eq!((rt.run_once().0, computed.get()), (22, 3));
+    }
+
+    #[test]
+    fn dependencies_are_recorded_on_each_computation() {
+        let mut rt = RunLoop::new(|| {
+            let (_, use_first) = state(|| true);
+            let (_, first) = state(|| 1);
+            let (_, second) = state(|| 2);
+            let value = memo(|| if *use_first { *first } else { *second });
+            (value, use_first, first, second)
+        });
+
+        let (value, use_first, first, second) = rt.run_once();
+        assert_eq!(value, 1);
+
+        second.set(3);
+        assert_eq!(rt.run_once().0, 1, "second hasn't been read yet");
+
+        use_first.set(false);
+        assert_eq!(rt.run_once().0, 3);
+
+        first.set(4);
+        second.set(5);
+        assert_eq!(rt.run_once().0, 5, "first is no longer a dependency");
+    }
+
+    #[test]
+    fn nested_memos_propagate_dependencies() {
+        let outer_computed = Cell::new(0);
+        let mut rt = RunLoop::new(|| {
+            let (_, count) = state(|| 1);
+            let doubled_plus_one = memo(|| {
+                outer_computed.set(outer_computed.get() + 1);
+                memo(|| *count * 2) + 1
+            });
+            (doubled_plus_one, count)
+        });
+
+        let (value, count) = rt.run_once();
+        assert_eq!((value, outer_computed.get()), (3, 1));
+        assert_eq!((rt.run_once().0, outer_computed.get()), (3, 1));
+
+        count.set(2);
+        assert_eq!((rt.run_once().0, outer_computed.get()), (5, 2));
+    }
+
+    #[test]
+    fn state_declared_inside_is_a_dependency() {
+        let mut rt = RunLoop::new(|| memo(|| state(|| 0u8)));
+        let (commit, key) = rt.run_once();
+        assert_eq!(*commit, 0);
+
+        key.set(1);
+        let (commit, _) = rt.run_once();
+        assert_eq!(*commit, 1);
+    }
+
+    #[test]
+    fn commits_captured_outside_are_dependencies() {
+        let mut rt = RunLoop::new(|| {
+            let (count, key) = state(|| 1u32);
+            (memo(|| *count * 2), key)
+        });
+        let (doubled, key) = rt.run_once();
+        assert_eq!(doubled, 2);
+
+        key.set(5);
+        assert_eq!(rt.run_once().0, 10);
+        assert_eq!(rt.run_once().0, 10);
+    }
+}
@@ -0,0 +1,233 @@
+use super::{Context, SendContext};
+use illicit::AsContext;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// How urgently a commit to a state variable should be shown.
+///
+/// Commits are [`Priority::Urgent`] by default. Updates made inside
+/// [`Priority::enter`] with [`Priority::Background`] are deferred while there
+/// are urgent commits waiting: the runtime's next revision applies only the
+/// urgent commits, leaving background commits pending until a later revision.
+/// This keeps revisions which respond to direct input, like typing, from
+/// waiting on expensive work which the background commits cause, like
+/// filtering a long list.
+///
+/// A background commit which is followed by an urgent one to the same state
+/// variable is applied with the urgent one, because the urgent update was
+/// computed from the background commit's value.
+///
+/// Revisions which apply background commits are also interrupted by urgent
+/// ones. If a revision run with [`super::Runtime::run_until`] is applying
+/// background commits when an urgent commit is made, it yields at its next
+/// restartable scope which needs to run, as if its budget was exhausted. The
+/// urgent commit is applied when the revision is resumed.
+///
+/// # Example
+///
+/// ```
+/// use moxie::{runtime::{Priority, RunLoop}, state};
+///
+/// let mut rt = RunLoop::new(|| (state(String::new), state(String::new)));
+/// let ((_, input), (_, filter)) = rt.run_once();
+///
+/// input.set(String::from("moxie"));
+/// Priority::Background.enter(|| filter.set(String::from("moxie")));
+///
+/// let ((input, _), (filter, _)) = rt.run_once();
+/// assert_eq!((input.as_str(), filter.as_str()), ("moxie", ""), "filter was deferred");
+///
+/// let ((input, _), (filter, _)) = rt.run_once();
+/// assert_eq!((input.as_str(), filter.as_str()), ("moxie", "moxie"));
+/// ```
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Priority {
+    /// Commits which can wait for urgent commits to be shown first.
+    Background,
+    /// Commits which should be shown in the next revision.
+    #[default]
+    Urgent,
+}
+
+impl Priority {
+    /// Returns the priority which will be given to commits made on this thread.
+    pub fn current() -> Self {
+        illicit::get::<Priority>().map(|p| *p).unwrap_or_default()
+    }
+
+    /// Runs `op`, giving this priority to any commits it makes to state
+    /// variables.
+    pub fn enter<R>(self, op: impl FnOnce() -> R) -> R {
+        self.offer(op)
+    }
+}
+
+/// Returns the lowest priority of commits which should be applied in the
+/// current revision.
+pub(crate) fn applied_priority() -> Priority {
+    if let Ok(rt) = illicit::get::<Context>() {
+        rt.applied_priority()
+    } else if let Ok(rt) = illicit::get::<SendContext>() {
+        rt.applied_priority()
+    } else {
+        Priority::Background
+    }
+}
+
+/// The priorities of the commits waiting to be applied in a runtime.
+#[derive(Debug, Default)]
+pub(crate) struct Lanes {
+    urgent: AtomicBool,
+    background: AtomicBool,
+    /// Set by urgent commits so that revisions applying background commits
+    /// can yield to them.
+    interrupt: AtomicBool,
+    /// Whether the current revision applies background commits.
+    interruptible: AtomicBool,
+}
+
+impl Lanes {
+    /// Record that a commit with `priority` is waiting to be applied.
+    pub fn mark(&self, priority: Priority) {
+        if priority == Priority::Urgent {
+            self.interrupt.store(true, Ordering::Release);
+        }
+        match priority {
+            Priority::Urgent => &self.urgent,
+            Priority::Background => &self.background,
+        }
+        .store(true, Ordering::Release);
+    }
+
+    /// Returns true if an urgent commit has been made since this was last
+    /// called and the current revision applies background commits.
+    pub fn take_interrupt(&self) -> bool {
+        self.interrupt.swap(false, Ordering::AcqRel) && self.interruptible.load(Ordering::Acquire)
+    }
+
+    /// Returns the lowest priority to apply in the next revision, clearing the
+    /// lanes which it will apply.
+    pub fn begin_revision(&self) -> Priority {
+        let urgent = self.urgent.swap(false, Ordering::AcqRel);
+        if urgent && self.background.load(Ordering::Acquire) {
+            self.interruptible.store(false, Ordering::Release);
+            Priority::Urgent
+        } else {
+            let background = self.background.swap(false, Ordering::AcqRel);
+            self.interruptible.store(background, Ordering::Release);
+            Priority::Background
+        }
+    }
+
+    /// Returns true if there are background commits which have been deferred.
+    pub fn has_deferred(&self) -> bool {
+        self.background.load(Ordering::Acquire)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{memo, restartable, runtime::RunLoop, state, testing::BoolWaker, Key};
+    use futures::task::waker;
+    use std::{cell::RefCell, rc::Rc, task::Poll};
+
+    #[test]
+    fn urgent_commits_are_applied_first() {
+        let mut rt = RunLoop::new(|| (state(|| 0u8), state(|| 0u8)));
+        let track_wakes = BoolWaker::new();
+        rt.set_state_change_waker(waker(track_wakes.clone()));
+        let ((_, urgent), (_, background)) = rt.run_once();
+
+        Priority::Background.enter(|| background.set(1));
+        urgent.set(1);
+        assert!(track_wakes.is_woken());
+
+        let ((urgent_value, _), (background_value, _)) = rt.run_once();
+        assert_eq!((*urgent_value, *background_value), (1, 0));
+        assert!(track_wakes.is_woken(), "runtime is woken again for deferred commits");
+        assert!(rt.has_deferred());
+
+        let ((urgent_value, _), (background_value, _)) = rt.run_once();
+        assert_eq!((*urgent_value, *background_value), (1, 1));
+        assert!(!track_wakes.is_woken());
+        assert!(!rt.has_deferred());
+    }
+
+    #[test]
+    fn background_commits_apply_without_urgent_ones() {
+        let mut rt = RunLoop::new(|| state(|| 0u8));
+        let (_, key) = rt.run_once();
+        Priority::Background.enter(|| key.set(1));
+        assert_eq!(*rt.run_once().0, 1);
+    }
+
+    #[test]
+    fn urgent_updates_carry_earlier_background_ones() {
+        let mut rt = RunLoop::new(|| (state(|| 0u8), state(|| 0u8)));
+        let ((_, first), (_, second)) = rt.run_once();
+
+        Priority::Background.enter(|| {
+            first.update(|n| Some(n + 1));
+            second.set(1);
+        });
+        first.update(|n| Some(n + 10));
+
+        let ((first, _), (second, _)) = rt.run_once();
+        assert_eq!((*first, *second), (11, 0));
+    }
+
+    #[test]
+    fn memos_recompute_after_deferred_commits() {
+        let mut rt = RunLoop::new(|| {
+            let (_, urgent) = state(|| 0u8);
+            let (_, background) = state(|| 0u8);
+            let sum = memo(|| *urgent + *background);
+            (sum, urgent, background)
+        });
+        let (_, urgent, background) = rt.run_once();
+
+        Priority::Background.enter(|| background.set(10));
+        urgent.set(1);
+        assert_eq!(rt.run_once().0, 1);
+        assert_eq!(rt.run_once().0, 11);
+    }
+
+    #[test]
+    fn urgent_commits_interrupt_background_revisions() {
+        let interrupt: Rc<RefCell<Option<Key<u8>>>> = Default::default();
+        let root_interrupt = interrupt.clone();
+        let mut rt = RunLoop::new(move || {
+            let (_, background) = state(|| 0u8);
+            let (urgent, urgent_key) = state(|| 0u8);
+            let first = restartable(&*background, |n| {
+                if let Some(key) = &*root_interrupt.borrow() {
+                    key.set(1);
+                }
+                *n
+            });
+            let second = restartable(&*background, |n| *n);
+            ((first, second, *urgent), background, urgent_key)
+        });
+        let (_, background, urgent) = rt.run_once();
+        interrupt.replace(Some(urgent));
+
+        Priority::Background.enter(|| background.set(1));
+        assert_eq!(rt.run_until(|| false).map(|(out, ..)| out), Poll::Pending);
+        assert_eq!(rt.run_until(|| false).map(|(out, ..)| out), Poll::Ready((1, 1, 1)));
+    }
+
+    #[test]
+    fn urgent_revisions_arent_interrupted() {
+        let mut rt = RunLoop::new(|| {
+            let (_, count) = state(|| 0u8);
+            let doubled = restartable(&*count, |n| {
+                count.set(2);
+                n * 2
+            });
+            (restartable(&doubled, |n| *n), count)
+        });
+        let (_, count) = rt.run_once();
+        count.set(1);
+        assert_eq!(rt.run_until(|| false).map(|(out, _)| out), Poll::Ready(2));
+    }
+}
@@ -0,0 +1,55 @@
+use dyn_cache::local::SharedLocalCache;
+use std::{
+    cell::RefCell,
+    collections::HashSet,
+    fmt::{Debug, Formatter, Result as FmtResult},
+    sync::{Arc, Weak},
+};
+use topo::CallId;
+
+/// State variables which are tracked from their creation until they're dropped
+/// from the cache.
+///
+/// Variables aren't registered again in each revision, so those declared in
+/// [`crate::restartable`] scopes which were skipped are still found.
+pub(crate) struct Registry<V: ?Sized> {
+    vars: RefCell<Vec<(CallId, Weak<V>)>>,
+}
+
+impl<V: ?Sized> Default for Registry<V> {
+    fn default() -> Self {
+        Self { vars: Default::default() }
+    }
+}
+
+impl<V: ?Sized> Registry<V> {
+    /// Track a newly created state variable declared at `id`.
+    pub fn register(&self, id: CallId, var: &Arc<V>) {
+        let mut vars = self.vars.borrow_mut();
+        if vars.len() == vars.capacity() {
+            // forget dropped variables before growing
+            vars.retain(|(_, var)| var.strong_count() > 0);
+        }
+        vars.push((id, Arc::downgrade(var)));
+    }
+
+    /// Returns the tracked variables which are still cached, in the order they
+    /// were created. Variables which are only kept alive by their keys were
+    /// dropped from the call tree and aren't included.
+    pub fn live(&self, cache: &SharedLocalCache) -> Vec<(CallId, Arc<V>)> {
+        let mut cached = HashSet::<CallId>::new();
+        cache.inspect(|stored| cached.extend(stored.scope::<CallId>()));
+
+        let vars = self.vars.borrow();
+        vars.iter()
+            .filter(|(id, _)| cached.contains(id))
+            .filter_map(|(id, var)| Some((*id, var.upgrade()?)))
+            .collect()
+    }
+}
+
+impl<V: ?Sized> Debug for Registry<V> {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.debug_struct("Registry").field("vars", &self.vars.borrow().len()).finish()
+    }
+}
@@ -1,11 +1,13 @@
-use super::{Revision, Runtime};
+use super::{Inspection, Revision, Runtime, SetStateError, Snapshot};
 use futures::{
     stream::{Stream, StreamExt},
     task::LocalSpawn,
 };
 use std::{
+    fmt::Debug,
     pin::Pin,
     task::{Context as FutContext, Poll, Waker},
+    time::Duration,
 };
 
 /// A [`Runtime`] that is bound with a particular root function.
@@ -54,12 +56,82 @@ where
         self.inner.set_task_executor(sp);
     }
 
+    /// Sets the number of commits retained by newly declared state variables'
+    /// histories. See [`Runtime::set_history_capacity`].
+    pub fn set_history_capacity(&mut self, capacity: usize) {
+        self.inner.set_history_capacity(capacity);
+    }
+
+    /// Returns the persisted state variables which were live in the most recent
+    /// revision. See [`Runtime::snapshot`].
+    pub fn snapshot(&self) -> Snapshot {
+        self.inner.snapshot()
+    }
+
+    /// Restores persisted state variables before the first revision. See
+    /// [`Runtime::restore`].
+    pub fn restore(&mut self, snapshot: Snapshot) {
+        self.inner.restore(snapshot);
+    }
+
+    /// Reverts state variables with histories to their values during
+    /// `revision`. See [`Runtime::revert_to`].
+    pub fn revert_to(&self, revision: Revision) {
+        self.inner.revert_to(revision);
+    }
+
+    /// Returns a read-only view of the call tree as of the most recent
+    /// revision. See [`Runtime::inspect`].
+    pub fn inspect(&self) -> Inspection {
+        self.inner.inspect()
+    }
+
+    /// Shows the `Debug` representation of state variables with type `State`
+    /// in inspections. See [`Runtime::inspect_with_debug`].
+    pub fn inspect_with_debug<State: Debug + 'static>(&self) {
+        self.inner.inspect_with_debug::<State>();
+    }
+
+    /// Enqueues a commit to the state variable declared at `path`. See
+    /// [`Runtime::set_state`].
+    pub fn set_state<State: 'static>(
+        &self,
+        path: &topo::CallPath,
+        state: State,
+    ) -> Result<(), SetStateError> {
+        self.inner.set_state(path, state)
+    }
+
+    /// Returns true if background commits are waiting to be applied. See
+    /// [`Runtime::has_deferred`].
+    pub fn has_deferred(&self) -> bool {
+        self.inner.has_deferred()
+    }
+
     /// Run the root function once within this runtime's context, returning the
     /// result.
     pub fn run_once(&mut self) -> Out {
         self.inner.run_once(&mut self.root)
     }
 
+    /// Run the root function with a time budget, returning its result if the
+    /// revision completed. See [`Runtime::run_with_budget`].
+    pub fn run_with_budget(&mut self, budget: Duration) -> Poll<Out> {
+        self.inner.run_with_budget(budget, &mut self.root)
+    }
+
+    /// Run the root function until `should_yield` returns true, returning its
+    /// result if the revision completed. See [`Runtime::run_until`].
+    pub fn run_until(&mut self, should_yield: impl Fn() -> bool + 'static) -> Poll<Out> {
+        self.inner.run_until(should_yield, &mut self.root)
+    }
+
+    /// Returns the revision which yielded before completing, if any. See
+    /// [`Runtime::in_progress`].
+    pub fn in_progress(&self) -> Option<Revision> {
+        self.inner.in_progress()
+    }
+
     /// Poll this runtime without exiting. Discards any value returned from the
     /// root function. The future yields in between revisions and is woken on
     /// state changes.
@@ -0,0 +1,409 @@
+use super::{
+    load::{self, Loader, Running},
+    JunkSpawner, Lanes, Priority, Revision, Var,
+};
+use crate::{Commit, Key};
+use dyn_cache::sync::SharedSendCache;
+use futures::{
+    future::{AbortHandle, FutureExt, FutureObj},
+    stream::{Stream, StreamExt},
+    task::{noop_waker, Spawn},
+};
+use illicit::AsContext;
+use std::{
+    borrow::Borrow,
+    fmt::{Debug, Formatter, Result as FmtResult},
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context as FutContext, Poll, Waker},
+};
+
+/// A [`super::Runtime`] which can be sent between threads, storing its cache
+/// in a [`dyn_cache::sync::SharedSendCache`] and spawning tasks onto a
+/// [`futures::task::Spawn`] executor like a thread pool.
+///
+/// Revisions of a `SendRuntime` are run with the functions in the
+/// [`crate::sync`] module, which mirror those in the crate root but require
+/// that cached values and state variables be thread-safe. Other features which
+/// depend on thread-local storage, like effects, memos, and persisted or
+/// historical state, are only available in a `Runtime`.
+///
+/// Each revision still runs on a single thread, but the runtime can be moved
+/// to another thread in between revisions.
+///
+/// # Example
+///
+/// ```
+/// use moxie::{runtime::SendRuntime, sync::state};
+/// use std::thread;
+///
+/// let root = || state(|| 0u64);
+/// let mut rt = SendRuntime::new();
+/// let (count, key) = rt.run_once(root);
+/// assert_eq!(*count, 0);
+///
+/// let (mut rt, key) = thread::spawn(move || {
+///     key.set(1);
+///     (rt, key)
+/// })
+/// .join()
+/// .unwrap();
+///
+/// let (count, _) = rt.run_once(root);
+/// assert_eq!(*count, 1);
+/// assert_eq!(*key, 0, "keys hold the commit from their own revision");
+/// ```
+pub struct SendRuntime {
+    revision: Revision,
+    cache: SharedSendCache,
+    lanes: Arc<Lanes>,
+    spawner: SendSpawner,
+    wk: Waker,
+}
+
+impl Default for SendRuntime {
+    fn default() -> SendRuntime {
+        SendRuntime::new()
+    }
+}
+
+impl SendRuntime {
+    /// Construct a new [`SendRuntime`] with blank storage and no external
+    /// waker or task executor.
+    pub fn new() -> Self {
+        Self {
+            spawner: SendSpawner(Arc::new(JunkSpawner)),
+            revision: Revision(0),
+            cache: SharedSendCache::default(),
+            lanes: Arc::new(Lanes::default()),
+            wk: noop_waker(),
+        }
+    }
+
+    /// The current revision of the runtime, or how many times `run_once` has
+    /// been invoked.
+    pub fn revision(&self) -> Revision {
+        self.revision
+    }
+
+    /// Runs the root closure once with access to the runtime context,
+    /// increments the runtime's `Revision`, and drops any cached values
+    /// which were not marked alive. Defers background commits like
+    /// [`super::Runtime::run_once`].
+    pub fn run_once<Out>(&mut self, op: impl FnOnce() -> Out) -> Out {
+        self.revision.0 += 1;
+        let applied_priority = self.lanes.begin_revision();
+        let span = tracing::info_span!(
+            "revision",
+            revision = self.revision.0,
+            priority = ?applied_priority,
+        );
+        let _entered = span.enter();
+
+        let ret = self.context_handle(applied_priority).offer(|| topo::call(op));
+
+        tracing::debug_span!("gc").in_scope(|| self.cache.gc());
+        if self.lanes.has_deferred() {
+            self.wk.wake_by_ref();
+        }
+        ret
+    }
+
+    /// Returns true if background commits are waiting to be applied. See
+    /// [`super::Runtime::has_deferred`].
+    pub fn has_deferred(&self) -> bool {
+        self.lanes.has_deferred()
+    }
+
+    /// Sets the [`std::task::Waker`] which will be called when state variables
+    /// receive commits. See [`super::Runtime::set_state_change_waker`].
+    pub fn set_state_change_waker(&mut self, wk: Waker) {
+        self.wk = wk;
+    }
+
+    /// Sets the executor that will be used to spawn tasks.
+    pub fn set_task_executor(&mut self, sp: impl Spawn + Send + Sync + 'static) {
+        self.spawner = SendSpawner(Arc::new(sp));
+    }
+
+    /// Returns this runtime bound with a specific root function it will run in
+    /// a loop.
+    pub fn looped<Root, Out>(self, root: Root) -> SendRunLoop<Root>
+    where
+        Root: FnMut() -> Out,
+    {
+        SendRunLoop { inner: self, root }
+    }
+
+    fn context_handle(&self, applied_priority: Priority) -> SendContext {
+        SendContext {
+            revision: self.revision,
+            applied_priority,
+            lanes: self.lanes.clone(),
+            spawner: self.spawner.clone(),
+            cache: self.cache.clone(),
+            waker: self.wk.clone(),
+        }
+    }
+}
+
+impl Debug for SendRuntime {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.debug_struct("SendRuntime")
+            .field("revision", &self.revision)
+            .field("cache", &self.cache)
+            .finish()
+    }
+}
+
+/// A [`SendRuntime`] that is bound with a particular root function.
+///
+/// Like [`super::RunLoop`], it can be consumed as a [`futures::Stream`] of
+/// [`Revision`]s, and because it can be sent between threads it can be
+/// spawned onto a multi-threaded executor if its root function is `Send`.
+pub struct SendRunLoop<Root> {
+    inner: SendRuntime,
+    root: Root,
+}
+
+impl<Root, Out> SendRunLoop<Root>
+where
+    Root: FnMut() -> Out + Unpin,
+{
+    /// Creates a new `SendRuntime` attached to the provided root function.
+    pub fn new(root: Root) -> SendRunLoop<Root> {
+        SendRunLoop { root, inner: SendRuntime::new() }
+    }
+
+    /// Returns the runtime's current Revision.
+    pub fn revision(&self) -> Revision {
+        self.inner.revision()
+    }
+
+    /// Returns true if background commits are waiting to be applied.
+    pub fn has_deferred(&self) -> bool {
+        self.inner.has_deferred()
+    }
+
+    /// Sets the [`std::task::Waker`] which will be called when state variables
+    /// change.
+    pub fn set_state_change_waker(&mut self, wk: Waker) {
+        self.inner.set_state_change_waker(wk);
+    }
+
+    /// Sets the executor that will be used to spawn tasks.
+    pub fn set_task_executor(&mut self, sp: impl Spawn + Send + Sync + 'static) {
+        self.inner.set_task_executor(sp);
+    }
+
+    /// Run the root function once within this runtime's context, returning the
+    /// result.
+    pub fn run_once(&mut self) -> Out {
+        self.inner.run_once(&mut self.root)
+    }
+
+    /// Poll this runtime without exiting. Discards any value returned from the
+    /// root function. The future yields in between revisions and is woken on
+    /// state changes.
+    pub async fn run_on_state_changes(mut self) {
+        loop {
+            self.next().await;
+        }
+    }
+
+    /// Unbinds the runtime from its current root function, returning both.
+    pub fn unloop(self) -> (SendRuntime, Root) {
+        (self.inner, self.root)
+    }
+}
+
+impl<Root, Out> Stream for SendRunLoop<Root>
+where
+    Root: FnMut() -> Out + Unpin,
+{
+    type Item = (Revision, Out);
+
+    /// This `Stream` implementation runs a single revision for each call to
+    /// `poll_next`, always returning `Poll::Ready(Some(...))`.
+    fn poll_next(self: Pin<&mut Self>, cx: &mut FutContext<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        this.inner.set_state_change_waker(cx.waker().clone());
+        let out = this.run_once();
+        Poll::Ready(Some((this.inner.revision, out)))
+    }
+}
+
+/// A handle to the current [`SendRuntime`] which is offered via [`illicit`]
+/// contexts, the counterpart of [`super::Context`].
+#[derive(Debug)]
+pub(crate) struct SendContext {
+    revision: Revision,
+    applied_priority: Priority,
+    lanes: Arc<Lanes>,
+    pub cache: SharedSendCache,
+    spawner: SendSpawner,
+    waker: Waker,
+}
+
+impl SendContext {
+    /// Returns the revision for which this context was created.
+    pub fn revision(&self) -> Revision {
+        self.revision
+    }
+
+    /// Returns the lowest priority of the commits applied in this revision.
+    pub fn applied_priority(&self) -> Priority {
+        self.applied_priority
+    }
+
+    /// Load a thread-safe [`crate::state::Var`] with the provided argument and
+    /// initializer. Re-initializes the `Var` whenever `arg` changes.
+    pub fn cache_state<Arg, Input, Output>(
+        &self,
+        id: &topo::CallId,
+        arg: &Arg,
+        init: impl FnOnce(&Input) -> Output,
+    ) -> (Commit<Output>, Key<Output>)
+    where
+        Arg: PartialEq<Input> + ToOwned<Owned = Input> + ?Sized,
+        Input: Borrow<Arg> + Send + 'static,
+        Output: Send + Sync + 'static,
+    {
+        let var = self.cache.cache(id, arg, |arg| {
+            let lanes = self.lanes.clone();
+            Var::new(topo::CallId::current(), self.waker.clone(), lanes, init(arg))
+        });
+        Var::root(var)
+    }
+
+    /// Load a value from the future returned by `init` whenever `arg` changes,
+    /// spawning it onto the runtime's thread-safe executor. See
+    /// [`super::Context::load_with`].
+    ///
+    /// # Panics
+    ///
+    /// If the [`SendRuntime`] from which `self` was created did not have
+    /// a valid call to `set_task_executor`.
+    pub fn load_with<Arg, Input, Fut, Output, Ret>(
+        &self,
+        id: &topo::CallId,
+        arg: &Arg,
+        init: impl FnOnce(&Input) -> Fut,
+        with: impl FnOnce(&Output) -> Ret,
+    ) -> Poll<Ret>
+    where
+        Arg: PartialEq<Input> + ToOwned<Owned = Input> + ?Sized,
+        Input: Borrow<Arg> + Send + 'static,
+        Fut: Future<Output = Output> + Send + 'static,
+        Output: Send + Sync + 'static,
+        Ret: 'static,
+    {
+        load::subscribe_with(self, id, arg, |arg| init(arg).into_stream(), with)
+    }
+}
+
+impl<Arg, Input, S> Loader<Arg, Input, S> for SendContext
+where
+    Arg: PartialEq<Input> + ToOwned<Owned = Input> + ?Sized,
+    Input: Borrow<Arg> + Send + 'static,
+    S: Stream + Send + 'static,
+    S::Item: Send + Sync + 'static,
+{
+    fn latest(&self, id: &topo::CallId) -> Key<Poll<S::Item>> {
+        self.cache_state(id, &(), |()| Poll::Pending).1
+    }
+
+    fn hold(&self, id: &topo::CallId, arg: &Arg, init: impl FnOnce(&Input) -> Running) {
+        self.cache.hold(id, arg, init);
+    }
+
+    fn spawn(&self, stream: S, latest: Key<Poll<S::Item>>) -> AbortHandle {
+        let (task, aborter) = load::commit_each(stream, latest);
+        self.spawner
+            .0
+            .spawn_obj(FutureObj::new(Box::new(task)))
+            .expect("that set_task_executor has been called");
+        aborter
+    }
+}
+
+#[derive(Clone)]
+struct SendSpawner(Arc<dyn Spawn + Send + Sync>);
+
+impl Debug for SendSpawner {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.write_fmt(format_args!("{:p}", &self.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sync::{cache, load, state};
+    use futures::{channel::oneshot, executor::block_on, task::SpawnError};
+    use parking_lot::Mutex;
+    use std::{sync::mpsc::channel, thread};
+
+    fn assert_send<T: Send>(_: &T) {}
+
+    #[test]
+    fn runtime_can_move_between_threads() {
+        let mut rt = SendRunLoop::new(|| {
+            let (count, key) = state(|| 0u32);
+            let doubled = cache(&*count, |c| c * 2);
+            (doubled, key)
+        });
+        assert_send(&rt);
+
+        let (doubled, key) = rt.run_once();
+        assert_eq!(doubled, 0);
+        key.set(4);
+
+        let (doubled, key) = thread::spawn(move || rt.run_once()).join().unwrap();
+        assert_eq!(doubled, 8);
+        assert_eq!(*key, 4);
+    }
+
+    /// Collects spawned tasks so they can be run on another thread.
+    #[derive(Clone, Default)]
+    struct Tasks(Arc<Mutex<Vec<FutureObj<'static, ()>>>>);
+
+    impl Spawn for Tasks {
+        fn spawn_obj(&self, future: FutureObj<'static, ()>) -> Result<(), SpawnError> {
+            self.0.lock().push(future);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn loads_futures_on_other_threads() {
+        let (send_futs, recv_futs) = channel();
+        let mut rt = SendRunLoop::new(move || {
+            load(&(), |()| {
+                let (sender, receiver) = oneshot::channel::<u8>();
+                send_futs.send(sender).unwrap();
+                receiver
+            })
+        });
+        let tasks = Tasks::default();
+        rt.set_task_executor(tasks.clone());
+
+        assert_eq!(rt.run_once(), Poll::Pending);
+        recv_futs.recv().unwrap().send(5).unwrap();
+
+        let spawned = tasks.0.lock().drain(..).collect::<Vec<_>>();
+        assert_eq!(spawned.len(), 1);
+        thread::spawn(move || spawned.into_iter().for_each(block_on)).join().unwrap();
+
+        assert_eq!(rt.run_once(), Poll::Ready(Ok(5)));
+        assert!(recv_futs.try_recv().is_err(), "only one future is loaded");
+    }
+
+    #[test]
+    fn revision_is_available_in_send_runtimes() {
+        let mut rt = SendRunLoop::new(Revision::current);
+        assert_eq!(rt.run_once(), Revision(1));
+        assert_eq!(rt.run_once(), Revision(2));
+    }
+}
@@ -0,0 +1,300 @@
+use super::{Registry, Var};
+use dyn_cache::local::SharedLocalCache;
+use parking_lot::Mutex;
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fmt::{Debug, Formatter, Result as FmtResult},
+    iter::FromIterator,
+    sync::Arc,
+};
+use topo::{CallId, CallPath};
+
+/// A type whose values can be written into a [`Snapshot`] and read back out of
+/// one, allowing state variables declared with [`crate::persisted_state`] to
+/// survive across [`super::Runtime`]s.
+///
+/// Implementations are provided for primitives and `String`. Other types can
+/// implement it by hand or by delegating to a serialization library:
+///
+/// ```
+/// use moxie::runtime::Persist;
+///
+/// #[derive(Debug, PartialEq)]
+/// struct Point {
+///     x: i32,
+///     y: i32,
+/// }
+///
+/// impl Persist for Point {
+///     fn persist(&self) -> String {
+///         format!("{},{}", self.x, self.y)
+///     }
+///
+///     fn restore(persisted: &str) -> Option<Self> {
+///         let (x, y) = persisted.split_once(',')?;
+///         Some(Point { x: x.parse().ok()?, y: y.parse().ok()? })
+///     }
+/// }
+///
+/// let point = Point { x: 1, y: -2 };
+/// assert_eq!(Point::restore(&point.persist()), Some(point));
+/// ```
+pub trait Persist: Sized {
+    /// Returns an encoding of this value which can be passed to
+    /// [`Persist::restore`].
+    fn persist(&self) -> String;
+
+    /// Decodes a value returned by [`Persist::persist`], returning `None` if
+    /// `persisted` is invalid, e.g. because it was written by an older version
+    /// of the type.
+    fn restore(persisted: &str) -> Option<Self>;
+}
+
+macro_rules! persist_from_str {
+    ($($ty:ty),+) => {$(
+        impl Persist for $ty {
+            fn persist(&self) -> String {
+                self.to_string()
+            }
+
+            fn restore(persisted: &str) -> Option<Self> {
+                persisted.parse().ok()
+            }
+        }
+    )+};
+}
+
+persist_from_str!(
+    bool, char, f32, f64, i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize, String
+);
+
+/// The persisted values of a [`super::Runtime`]'s live state variables, taken
+/// with [`super::Runtime::snapshot`] and restored with
+/// [`super::Runtime::restore`].
+///
+/// Only state variables declared with [`crate::persisted_state`] are included.
+/// Values are keyed by the [`topo::CallPath`] of the state variable, so a
+/// snapshot can be restored in a different process running the same binary.
+/// Snapshots can be written out with [`Snapshot::iter`] and read back in with
+/// their `FromIterator` impl:
+///
+/// ```
+/// use moxie::{persisted_state, runtime::{RunLoop, Snapshot}};
+///
+/// let root = || persisted_state(|| String::from("hello"));
+/// let mut rt = RunLoop::new(root);
+/// rt.run_once().1.set(String::from("goodbye"));
+///
+/// let saved = rt
+///     .snapshot()
+///     .iter()
+///     .map(|(path, state)| format!("{}={}\n", path, state))
+///     .collect::<String>();
+///
+/// let loaded = saved
+///     .lines()
+///     .map(|line| {
+///         let (path, state) = line.split_once('=').unwrap();
+///         (path.parse().unwrap(), state.to_owned())
+///     })
+///     .collect::<Snapshot>();
+///
+/// let mut restarted = RunLoop::new(root);
+/// restarted.restore(loaded);
+/// assert_eq!(*restarted.run_once().0, "goodbye");
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Snapshot {
+    states: HashMap<CallPath, String>,
+}
+
+impl Snapshot {
+    /// Returns the path and persisted value of each state variable in the
+    /// snapshot.
+    pub fn iter(&self) -> impl Iterator<Item = (&CallPath, &str)> + '_ {
+        self.states.iter().map(|(path, state)| (path, state.as_str()))
+    }
+
+    /// Returns the number of state variables in the snapshot.
+    pub fn len(&self) -> usize {
+        self.states.len()
+    }
+
+    /// Returns true if the snapshot contains no state variables.
+    pub fn is_empty(&self) -> bool {
+        self.states.is_empty()
+    }
+}
+
+impl FromIterator<(CallPath, String)> for Snapshot {
+    fn from_iter<I: IntoIterator<Item = (CallPath, String)>>(iter: I) -> Self {
+        Self { states: iter.into_iter().collect() }
+    }
+}
+
+/// A persisted state variable with its type erased.
+trait PersistVar {
+    fn persist(&self) -> String;
+}
+
+impl<State: Persist> PersistVar for Mutex<Var<State>> {
+    fn persist(&self) -> String {
+        self.lock().latest().persist()
+    }
+}
+
+/// Tracks the persisted state variables which are live in a runtime, and the
+/// states waiting to be restored into them.
+#[derive(Default)]
+pub(crate) struct Persistence {
+    vars: Registry<dyn PersistVar>,
+    restoring: RefCell<HashMap<CallPath, String>>,
+}
+
+impl Persistence {
+    /// Drop any restored states which weren't claimed during the revision.
+    pub fn end_revision(&self) {
+        self.restoring.borrow_mut().clear();
+    }
+
+    /// Track a newly created state variable so that it's included in snapshots
+    /// for as long as it's cached.
+    pub fn register<State>(&self, id: CallId, var: &Arc<Mutex<Var<State>>>)
+    where
+        State: Persist + 'static,
+    {
+        let var: Arc<dyn PersistVar> = var.clone();
+        self.vars.register(id, &var);
+    }
+
+    /// Returns the restored state for `id`, if any.
+    pub fn take_restored<State: Persist>(&self, id: &CallId) -> Option<State> {
+        let mut restoring = self.restoring.borrow_mut();
+        if restoring.is_empty() {
+            return None;
+        }
+        let persisted = restoring.remove(&id.path())?;
+        State::restore(&persisted)
+    }
+
+    pub fn snapshot(&self, cache: &SharedLocalCache) -> Snapshot {
+        let live = self.vars.live(cache).into_iter();
+        Snapshot { states: live.map(|(id, var)| (id.path(), var.persist())).collect() }
+    }
+
+    pub fn restore(&self, snapshot: Snapshot) {
+        *self.restoring.borrow_mut() = snapshot.states;
+    }
+}
+
+impl Debug for Persistence {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.debug_struct("Persistence")
+            .field("vars", &self.vars)
+            .field("restoring", &self.restoring.borrow().len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{persisted_state, restartable, runtime::RunLoop, state};
+
+    #[test]
+    fn restores_into_fresh_runtime() {
+        let root = || {
+            let (count, count_key) = persisted_state(|| 0u32);
+            let (name, _) = persisted_state(|| String::from("anonymous"));
+            let (unpersisted, _) = state(|| 0u32);
+            (*count, (*name).clone(), *unpersisted, count_key)
+        };
+
+        let mut first = RunLoop::new(root);
+        let (.., key) = first.run_once();
+        key.set(5);
+        let snapshot = first.snapshot();
+        assert_eq!(snapshot.len(), 2, "only persisted state variables are included");
+
+        let mut second = RunLoop::new(root);
+        second.restore(snapshot.clone());
+        let (count, name, unpersisted, _) = second.run_once();
+        assert_eq!((count, name.as_str(), unpersisted), (5, "anonymous", 0));
+        assert_eq!(second.snapshot(), snapshot);
+    }
+
+    #[test]
+    fn only_live_states_are_included() {
+        let mut show = true;
+        let mut rt = RunLoop::new(|| {
+            if show {
+                persisted_state(|| 1u8);
+            }
+            show = false;
+        });
+        assert!(rt.snapshot().is_empty(), "nothing is live before the first revision");
+
+        rt.run_once();
+        assert_eq!(rt.snapshot().len(), 1);
+
+        rt.run_once();
+        assert!(rt.snapshot().is_empty(), "state variable was dropped");
+    }
+
+    #[test]
+    fn skipped_scopes_keep_their_states() {
+        let mut rt = RunLoop::new(|| {
+            let (count, key) = state(|| 0u8);
+            restartable(&(), |()| persisted_state(|| 1u8).1);
+            (*count, key)
+        });
+        let (_, key) = rt.run_once();
+        assert_eq!(rt.snapshot().len(), 1);
+
+        key.set(1);
+        assert_eq!(rt.run_once().0, 1);
+        assert_eq!(rt.snapshot().len(), 1, "the skipped scope's state is still live");
+    }
+
+    #[test]
+    fn unclaimed_states_are_dropped_after_first_revision() {
+        let counter = || persisted_state(|| 1u8);
+        let mut first = RunLoop::new(counter);
+        let (_, key) = first.run_once();
+        key.set(2);
+        let snapshot = first.snapshot();
+
+        let mut declare = false;
+        let mut second = RunLoop::new(|| {
+            let count = if declare { Some(*counter().0) } else { None };
+            declare = true;
+            count
+        });
+        second.restore(snapshot);
+        assert_eq!(second.run_once(), None);
+        assert_eq!(second.run_once(), Some(1), "restored state is only offered once");
+    }
+
+    #[test]
+    fn invalid_states_are_reinitialized() {
+        let root = || *persisted_state(|| 7u8).0;
+        let mut first = RunLoop::new(root);
+        first.run_once();
+        let mut snapshot = first.snapshot();
+        for value in snapshot.states.values_mut() {
+            *value = String::from("not a number");
+        }
+
+        let mut second = RunLoop::new(root);
+        second.restore(snapshot);
+        assert_eq!(second.run_once(), 7);
+    }
+
+    #[test]
+    #[should_panic(expected = "restored before the first revision")]
+    fn restore_after_running_panics() {
+        let mut rt = RunLoop::new(|| ());
+        rt.run_once();
+        rt.restore(Default::default());
+    }
+}
@@ -0,0 +1,177 @@
+use super::memo::record_stale;
+use illicit::AsContext;
+use std::{cell::Cell, rc::Rc};
+
+/// Counts the loads which are pending in the subtree of a
+/// [`crate::suspense`] boundary.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Suspense(Rc<Cell<usize>>);
+
+impl Suspense {
+    /// Runs `op`, returning its output and whether any loads it made were
+    /// pending.
+    pub fn run<R>(op: impl FnOnce() -> R) -> (R, bool) {
+        let suspense = Suspense::default();
+        let ret = suspense.clone().offer(op);
+        (ret, suspense.0.get() > 0)
+    }
+
+    /// Record a pending load with the enclosing boundary, if any.
+    ///
+    /// Enclosing memoized scopes are marked as stale so that they run again in
+    /// the next revision and report the load to the boundary until it's ready.
+    pub fn record_pending() {
+        if let Ok(suspense) = illicit::get::<Suspense>() {
+            suspense.0.set(suspense.0.get() + 1);
+            record_stale();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{load_once, restartable, runtime::RunLoop, suspense, suspense_timeout};
+    use futures::{channel::oneshot, executor::LocalPool};
+    use std::{cell::RefCell, rc::Rc, task::Poll};
+
+    type Senders = Rc<RefCell<Vec<oneshot::Sender<u8>>>>;
+
+    #[topo::nested]
+    fn loaded(senders: &Senders) -> Poll<u8> {
+        load_once(|| {
+            let (sender, receiver) = oneshot::channel();
+            senders.borrow_mut().push(sender);
+            async move { receiver.await.unwrap_or_default() }
+        })
+    }
+
+    #[test]
+    fn children_are_shown_once_all_loads_resolve() {
+        let senders = Senders::default();
+        let root_senders = senders.clone();
+        let mut rt = RunLoop::new(move || {
+            suspense(|| None, || Some((loaded(&root_senders), loaded(&root_senders))))
+        });
+        let mut exec = LocalPool::new();
+        rt.set_task_executor(exec.spawner());
+
+        assert_eq!(rt.run_once(), None);
+        exec.run_until_stalled();
+        assert_eq!(senders.borrow().len(), 2, "both loads start together");
+
+        let second = senders.borrow_mut().pop().unwrap();
+        second.send(2).unwrap();
+        exec.run_until_stalled();
+        assert_eq!(rt.run_once(), None, "first load is still pending");
+
+        let first = senders.borrow_mut().pop().unwrap();
+        first.send(1).unwrap();
+        exec.run_until_stalled();
+        assert_eq!(rt.run_once(), Some((Poll::Ready(1), Poll::Ready(2))));
+    }
+
+    #[test]
+    fn loads_in_skipped_scopes_keep_the_boundary_suspended() {
+        let senders = Senders::default();
+        let root_senders = senders.clone();
+        let mut rt = RunLoop::new(move || {
+            suspense(
+                || String::from("loading"),
+                || restartable(&(), |()| format!("{:?}", loaded(&root_senders))),
+            )
+        });
+        let mut exec = LocalPool::new();
+        rt.set_task_executor(exec.spawner());
+
+        assert_eq!(rt.run_once(), "loading");
+        assert_eq!(rt.run_once(), "loading", "scope ran again to check its load");
+
+        senders.borrow_mut().pop().unwrap().send(1).unwrap();
+        exec.run_until_stalled();
+        assert_eq!(rt.run_once(), "Ready(1)");
+    }
+
+    #[test]
+    fn nested_boundaries_suspend_separately() {
+        let senders = Senders::default();
+        let root_senders = senders.clone();
+        let mut rt = RunLoop::new(move || {
+            suspense(
+                || String::from("outer fallback"),
+                || {
+                    suspense(
+                        || String::from("inner fallback"),
+                        || format!("{:?}", loaded(&root_senders)),
+                    )
+                },
+            )
+        });
+        let exec = LocalPool::new();
+        rt.set_task_executor(exec.spawner());
+
+        assert_eq!(rt.run_once(), "inner fallback");
+    }
+
+    #[test]
+    fn timeouts_show_children_before_loads_resolve() {
+        let senders = Senders::default();
+        let timeouts = Rc::new(RefCell::new(vec![]));
+        let (root_senders, root_timeouts) = (senders.clone(), timeouts.clone());
+        let mut rt = RunLoop::new(move || {
+            suspense_timeout(
+                || {
+                    let (sender, receiver) = oneshot::channel();
+                    root_timeouts.borrow_mut().push(sender);
+                    async move {
+                        receiver.await.ok();
+                    }
+                },
+                || Err("loading"),
+                || Ok(loaded(&root_senders)),
+            )
+        });
+        let mut exec = LocalPool::new();
+        rt.set_task_executor(exec.spawner());
+
+        assert_eq!(rt.run_once(), Err("loading"));
+        exec.run_until_stalled();
+        assert_eq!(timeouts.borrow().len(), 1);
+
+        timeouts.borrow_mut().pop().unwrap().send(()).unwrap();
+        exec.run_until_stalled();
+        assert_eq!(rt.run_once(), Ok(Poll::Pending), "timed out");
+
+        senders.borrow_mut().pop().unwrap().send(1).unwrap();
+        exec.run_until_stalled();
+        assert_eq!(rt.run_once(), Ok(Poll::Ready(1)));
+        assert!(timeouts.borrow().is_empty(), "timeout is only started while suspended");
+    }
+
+    #[test]
+    fn timeouts_dont_suspend_enclosing_boundaries() {
+        let senders = Senders::default();
+        let root_senders = senders.clone();
+        let mut rt = RunLoop::new(move || {
+            suspense(
+                || String::from("outer fallback"),
+                || {
+                    suspense_timeout(
+                        futures::future::pending,
+                        || String::from("inner fallback"),
+                        || format!("{:?}", loaded(&root_senders)),
+                    )
+                },
+            )
+        });
+        let mut exec = LocalPool::new();
+        rt.set_task_executor(exec.spawner());
+
+        assert_eq!(rt.run_once(), "inner fallback");
+        exec.run_until_stalled();
+        assert_eq!(rt.run_once(), "inner fallback", "the timeout is still pending");
+
+        senders.borrow_mut().pop().unwrap().send(1).unwrap();
+        exec.run_until_stalled();
+        assert_eq!(rt.run_once(), "Ready(1)");
+    }
+}
@@ -0,0 +1,350 @@
+use super::Var;
+use crate::{Commit, Key};
+use parking_lot::{Mutex, MutexGuard};
+use std::{
+    any::Any,
+    error::Error,
+    fmt::{Debug, Display, Formatter, Result as FmtResult},
+    sync::Arc,
+    task::Waker,
+};
+use topo::CallId;
+
+/// A group of updates to state variables which are committed together.
+///
+/// Updating several [`Key`]s one at a time wakes the runtime once per update,
+/// and a second update to a variable made before the next revision replaces
+/// the first without observing it. A `Transaction` instead stages updates to
+/// any number of variables and applies all of them at once in
+/// [`Transaction::commit`], waking each affected runtime a single time.
+///
+/// Updates made to the same variable within a transaction are applied in
+/// order, each one receiving the value produced by the last.
+///
+/// # Compare-and-set
+///
+/// A transaction can be made conditional on a variable still holding a
+/// particular [`Commit`] with [`Transaction::expect`] or
+/// [`Transaction::compare_and_set`]. If another update has been made since
+/// that commit was read, the whole transaction is rejected with a [`Conflict`]
+/// and none of its updates are applied. This lets event handlers which
+/// computed their updates from an older revision detect that they would
+/// otherwise overwrite a newer value.
+///
+/// # Example
+///
+/// ```
+/// use moxie::{runtime::{RunLoop, Transaction}, state};
+///
+/// let mut rt = RunLoop::new(|| (state(|| 100u32), state(|| 0u32)));
+/// let ((checking, checking_key), (_, savings_key)) = rt.run_once();
+///
+/// let mut transfer = Transaction::new();
+/// transfer.compare_and_set(&checking_key, &checking, *checking - 30);
+/// transfer.update(&savings_key, |savings| Some(savings + 30));
+/// transfer.commit().unwrap();
+///
+/// let ((checking, checking_key), (savings, _)) = rt.run_once();
+/// assert_eq!((*checking, *savings), (70, 30));
+///
+/// // an update made by another handler since `checking` was read
+/// checking_key.set(50);
+///
+/// let mut stale = Transaction::new();
+/// stale.compare_and_set(&checking_key, &checking, *checking - 30);
+/// stale.update(&savings_key, |savings| Some(savings + 30));
+/// assert!(stale.commit().is_err());
+///
+/// let ((checking, _), (savings, _)) = rt.run_once();
+/// assert_eq!((*checking, *savings), (50, 30), "no updates from the failed transaction");
+/// ```
+#[derive(Default)]
+pub struct Transaction {
+    writes: Vec<Box<dyn Write>>,
+}
+
+impl Transaction {
+    /// Returns a new transaction with no updates.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stage a call to `updater` with the latest value of the state variable,
+    /// committing its result if it returns `Some`. Has the same semantics as
+    /// [`Key::update`] once the transaction is committed.
+    ///
+    /// Updaters run while the transaction's state variables are locked, so
+    /// they must not read from or write to any of the transaction's `Key`s.
+    pub fn update<State: 'static>(
+        &mut self,
+        key: &Key<State>,
+        updater: impl FnOnce(&State) -> Option<State> + 'static,
+    ) {
+        self.staged(key).updaters.push(Box::new(updater));
+    }
+
+    /// Stage a new value for the state variable, committing it if it is
+    /// unequal to the variable's latest value.
+    pub fn set<State>(&mut self, key: &Key<State>, new: State)
+    where
+        State: PartialEq + 'static,
+    {
+        self.update(key, |prev| if prev == &new { None } else { Some(new) });
+    }
+
+    /// Require that `expected` is still the latest commit to the state
+    /// variable, rejecting the whole transaction if it is not.
+    ///
+    /// Commits are compared by identity rather than by value, so an
+    /// intervening update which produced an equal value is still a conflict.
+    pub fn expect<State: 'static>(&mut self, key: &Key<State>, expected: &Commit<State>) {
+        self.staged(key).expected = Some(expected.clone());
+    }
+
+    /// Stage a new value for the state variable if `expected` is still its
+    /// latest commit, rejecting the whole transaction if it is not.
+    pub fn compare_and_set<State>(&mut self, key: &Key<State>, expected: &Commit<State>, new: State)
+    where
+        State: PartialEq + 'static,
+    {
+        self.expect(key, expected);
+        self.set(key, new);
+    }
+
+    /// Returns the number of state variables affected by this transaction.
+    pub fn len(&self) -> usize {
+        self.writes.len()
+    }
+
+    /// Returns true if this transaction has no updates or expectations.
+    pub fn is_empty(&self) -> bool {
+        self.writes.is_empty()
+    }
+
+    /// Apply all of the staged updates, waking the runtimes which own the
+    /// updated state variables once each.
+    ///
+    /// All of the affected variables are locked for the duration of the
+    /// commit, so no other updates can be interleaved with the transaction's.
+    /// Expectations are checked before any updaters run, and if any of them
+    /// fail no updates are made.
+    pub fn commit(mut self) -> Result<(), Conflict> {
+        // lock in a consistent order so concurrent transactions can't deadlock
+        self.writes.sort_by_key(|write| write.address());
+        let mut locked = self.writes.iter_mut().map(|write| write.lock()).collect::<Vec<_>>();
+
+        if let Some(conflict) = locked.iter().find_map(|write| write.conflict()) {
+            return Err(conflict);
+        }
+
+        let mut wakers: Vec<Waker> = vec![];
+        for waker in locked.iter_mut().filter_map(|write| write.apply()) {
+            if !wakers.iter().any(|w| w.will_wake(&waker)) {
+                wakers.push(waker);
+            }
+        }
+
+        // release the variables before their runtimes can try to read them
+        drop(locked);
+        wakers.iter().for_each(Waker::wake_by_ref);
+        Ok(())
+    }
+
+    /// Returns the staged writes for `key`'s state variable, adding them if
+    /// this is the first time it's been used in the transaction.
+    fn staged<State: 'static>(&mut self, key: &Key<State>) -> &mut Staged<State> {
+        let address = Arc::as_ptr(&key.var) as usize;
+        let index = match self.writes.iter().position(|w| w.address() == address) {
+            Some(index) => index,
+            None => {
+                let var = key.var.clone();
+                self.writes.push(Box::new(Staged { var, expected: None, updaters: vec![] }));
+                self.writes.len() - 1
+            }
+        };
+        self.writes[index].as_any().downcast_mut().expect("writes are keyed by variable")
+    }
+}
+
+impl Debug for Transaction {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.debug_struct("Transaction").field("writes", &self.writes.len()).finish()
+    }
+}
+
+/// Returned when a [`Transaction`] is rejected because a state variable's
+/// latest commit was not the one expected.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Conflict {
+    id: CallId,
+}
+
+impl Conflict {
+    /// Returns the `topo::CallId` at which the conflicting state variable is
+    /// bound.
+    pub fn id(&self) -> CallId {
+        self.id
+    }
+}
+
+impl Display for Conflict {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "state variable at {:?} was updated after the expected commit", self.id)
+    }
+}
+
+impl Error for Conflict {}
+
+type Updater<State> = Box<dyn FnOnce(&State) -> Option<State>>;
+
+/// The updates and expectation for a single state variable in a transaction.
+struct Staged<State> {
+    var: Arc<Mutex<Var<State>>>,
+    expected: Option<Commit<State>>,
+    updaters: Vec<Updater<State>>,
+}
+
+/// The staged writes for a state variable of any type.
+trait Write {
+    /// Returns the address of the state variable, identifying it within the
+    /// transaction.
+    fn address(&self) -> usize;
+    fn as_any(&mut self) -> &mut dyn Any;
+    fn lock(&mut self) -> Box<dyn LockedWrite + '_>;
+}
+
+impl<State: 'static> Write for Staged<State> {
+    fn address(&self) -> usize {
+        Arc::as_ptr(&self.var) as usize
+    }
+
+    fn as_any(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn lock(&mut self) -> Box<dyn LockedWrite + '_> {
+        let Staged { var, expected, updaters } = self;
+        Box::new(Locked { var: var.lock(), expected, updaters })
+    }
+}
+
+/// Staged writes for a state variable which is locked for the commit.
+trait LockedWrite {
+    /// Returns a conflict if the variable's latest commit isn't the expected
+    /// one.
+    fn conflict(&self) -> Option<Conflict>;
+
+    /// Runs the staged updaters, returning the variable's waker if a commit
+    /// was staged.
+    fn apply(&mut self) -> Option<Waker>;
+}
+
+struct Locked<'a, State> {
+    var: MutexGuard<'a, Var<State>>,
+    expected: &'a Option<Commit<State>>,
+    updaters: &'a mut Vec<Updater<State>>,
+}
+
+impl<State> LockedWrite for Locked<'_, State> {
+    fn conflict(&self) -> Option<Conflict> {
+        let expected = self.expected.as_ref()?;
+        let latest = self.var.latest_commit();
+        if Arc::ptr_eq(&expected.inner, &latest.inner) {
+            None
+        } else {
+            Some(Conflict { id: latest.id })
+        }
+    }
+
+    fn apply(&mut self) -> Option<Waker> {
+        let Locked { var, updaters, .. } = self;
+        let mut staged: Option<State> = None;
+        for updater in updaters.drain(..) {
+            if let Some(new) = updater(staged.as_ref().unwrap_or_else(|| var.latest())) {
+                staged = Some(new);
+            }
+        }
+        var.stage_commit(staged?);
+        Some(var.waker().clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{runtime::RunLoop, state};
+    use futures::task::{waker, ArcWake};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[derive(Default)]
+    struct CountWakes(AtomicUsize);
+
+    impl ArcWake for CountWakes {
+        fn wake_by_ref(arc_self: &Arc<Self>) {
+            arc_self.0.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    #[test]
+    fn commits_together_and_wakes_once() {
+        let mut rt = RunLoop::new(|| (state(|| 1u8), state(|| 'a'), state(|| 10i32)));
+        let wakes = Arc::new(CountWakes::default());
+        rt.set_state_change_waker(waker(wakes.clone()));
+        let ((_, first), (_, second), (_, unchanged)) = rt.run_once();
+
+        let mut tx = Transaction::new();
+        tx.set(&first, 2);
+        tx.set(&second, 'b');
+        tx.set(&unchanged, 10);
+        assert_eq!(tx.len(), 3);
+        assert_eq!(wakes.0.load(Ordering::Relaxed), 0, "nothing happens until committed");
+
+        tx.commit().unwrap();
+        assert_eq!(wakes.0.load(Ordering::Relaxed), 1);
+
+        let ((first, _), (second, _), (unchanged, _)) = rt.run_once();
+        assert_eq!((*first, *second, *unchanged), (2, 'b', 10));
+    }
+
+    #[test]
+    fn updates_to_one_variable_compose() {
+        let mut rt = RunLoop::new(|| state(|| 1u32));
+        let (_, key) = rt.run_once();
+
+        let mut tx = Transaction::new();
+        tx.update(&key, |n| Some(n + 1));
+        tx.update(&key, |_| None);
+        tx.update(&key, |n| Some(n * 10));
+        assert_eq!(tx.len(), 1);
+        tx.commit().unwrap();
+
+        assert_eq!(*rt.run_once().0, 20);
+    }
+
+    #[test]
+    fn conflicts_are_detected_by_commit_identity() {
+        let mut rt = RunLoop::new(|| (state(|| 0u32), state(|| 0u32)));
+        let ((read, key), (_, other)) = rt.run_once();
+
+        let mut first_handler = Transaction::new();
+        first_handler.compare_and_set(&key, &read, *read + 1);
+        first_handler.commit().unwrap();
+
+        // a second handler which read the same commit must not overwrite the first
+        let mut second_handler = Transaction::new();
+        second_handler.compare_and_set(&key, &read, *read + 1);
+        second_handler.set(&other, 1);
+        assert_eq!(second_handler.commit(), Err(Conflict { id: key.id() }));
+
+        // even an update to an equal value is a new commit
+        let ((read, key), _) = rt.run_once();
+        assert_eq!(*read, 1);
+        key.update(|n| Some(*n));
+        let mut stale = Transaction::new();
+        stale.expect(&key, &read);
+        assert!(stale.commit().is_err());
+
+        let ((value, _), (other, _)) = rt.run_once();
+        assert_eq!((*value, *other), (1, 0));
+    }
+}
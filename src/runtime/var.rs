@@ -1,46 +1,185 @@
+use super::{priority::applied_priority, History, Lanes, Priority, Revision};
 use crate::{Commit, Key};
 use parking_lot::Mutex;
-use std::{sync::Arc, task::Waker};
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    task::Waker,
+};
 
 /// The underlying container of state variables. Vends copies of the latest
 /// [`Commit`] for [`Key`]s.
 pub(crate) struct Var<State> {
     current: Commit<State>,
+    history: Option<History<State>>,
     id: topo::CallId,
-    pending: Option<Commit<State>>,
+    lanes: Arc<Lanes>,
+    pending: Option<(Commit<State>, Priority)>,
+    /// Incremented with each commit, allowing memoized readers to check for
+    /// changes. Shared with each of the variable's commits.
+    version: Arc<AtomicU64>,
     waker: Waker,
 }
 
 impl<State> Var<State> {
-    pub fn new(id: topo::CallId, waker: Waker, inner: State) -> Arc<Mutex<Self>> {
-        let current = Commit { id, inner: Arc::new(inner) };
-        Arc::new(Mutex::new(Var { id, current, waker, pending: None }))
+    pub fn new(
+        id: topo::CallId,
+        waker: Waker,
+        lanes: Arc<Lanes>,
+        inner: State,
+    ) -> Arc<Mutex<Self>> {
+        let version = Arc::new(AtomicU64::new(0));
+        let current = Commit { id, inner: Arc::new(inner), version: version.clone(), seen: 0 };
+        Arc::new(Mutex::new(Var {
+            id,
+            current,
+            waker,
+            lanes,
+            history: None,
+            pending: None,
+            version,
+        }))
     }
 
-    /// Attach this `Var` to its callsite, performing any pending commit and
-    /// returning the resulting latest commit.
+    /// Start recording up to `capacity` of the commits made to this variable,
+    /// beginning with the current one rooted in `revision`.
+    pub fn enable_history(&mut self, revision: Revision, capacity: usize) {
+        self.history = Some(History::new(revision, &self.current, capacity));
+    }
+
+    /// Attach this `Var` to its callsite, performing any pending commit whose
+    /// priority is applied in the current revision and returning the resulting
+    /// current commit.
     pub fn root(var: Arc<Mutex<Self>>) -> (Commit<State>, Key<State>) {
         let (id, commit_at_root) = {
             let mut var = var.lock();
-            if let Some(pending) = var.pending.take() {
+            if matches!(var.pending, Some((_, priority)) if priority >= applied_priority()) {
+                let (mut pending, _) = var.pending.take().unwrap();
+                pending.seen = var.version.load(Ordering::Acquire);
+                if let Some(history) = &mut var.history {
+                    history.record_rooted(Revision::current(), &pending);
+                }
                 var.current = pending;
             }
+            super::memo::record_read(|| var.version());
             (var.id, var.current.clone())
         };
 
         (commit_at_root.clone(), Key { id, commit_at_root, var })
     }
 
+    /// Returns the counter of commits made to this variable and its value
+    /// when the current commit was applied.
+    pub fn version(&self) -> (Arc<AtomicU64>, u64) {
+        (self.version.clone(), self.current.seen)
+    }
+
     /// Returns a reference to the latest value, pending or committed.
     pub fn latest(&self) -> &State {
-        self.pending.as_ref().unwrap_or(&self.current)
+        self.latest_commit()
+    }
+
+    /// Returns the latest commit, pending or completed.
+    pub fn latest_commit(&self) -> &Commit<State> {
+        self.pending.as_ref().map(|(pending, _)| pending).unwrap_or(&self.current)
+    }
+
+    /// Returns the id of the callsite which declared this variable.
+    pub fn id(&self) -> topo::CallId {
+        self.id
+    }
+
+    /// Returns the current commit and the pending one, if any, with its
+    /// priority.
+    pub fn commits(&self) -> (&Commit<State>, Option<&(Commit<State>, Priority)>) {
+        (&self.current, self.pending.as_ref())
+    }
+
+    /// Returns the waker for the runtime which owns this variable.
+    pub fn waker(&self) -> &Waker {
+        &self.waker
     }
 
     /// Initiate a commit to the state variable. The commit will actually
     /// complete asynchronously when the state variable is next rooted in a
     /// topological function, flushing the pending commit.
     pub fn enqueue_commit(&mut self, state: State) {
-        self.pending = Some(Commit { inner: Arc::new(state), id: self.id });
+        self.stage_commit(state);
+        self.waker.wake_by_ref();
+    }
+
+    /// Initiate a commit to the state variable without waking the runtime.
+    pub fn stage_commit(&mut self, state: State) {
+        let commit =
+            Commit { inner: Arc::new(state), id: self.id, version: self.version.clone(), seen: 0 };
+        self.stage_update(commit);
+    }
+
+    /// Return to the commit before the latest one, if this variable has a
+    /// history. Returns whether a commit was enqueued.
+    pub fn undo(&mut self) -> bool {
+        let latest = self.latest_commit().clone();
+        match self.history.as_mut().and_then(|h| h.undo(&latest)) {
+            Some(previous) => {
+                self.set_pending(previous);
+                self.waker.wake_by_ref();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Return to the commit most recently undone, if this variable has a
+    /// history. Returns whether a commit was enqueued.
+    pub fn redo(&mut self) -> bool {
+        let latest = self.latest_commit().clone();
+        match self.history.as_mut().and_then(|h| h.redo(&latest)) {
+            Some(next) => {
+                self.set_pending(next);
+                self.waker.wake_by_ref();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Enqueue the commit which was rooted during `revision` as a new update,
+    /// if this variable has a history which includes `revision`.
+    pub fn revert_to(&mut self, revision: Revision) {
+        let past = match self.history.as_ref().and_then(|h| h.at(revision)) {
+            Some(past) if !Arc::ptr_eq(&past.inner, &self.latest_commit().inner) => past.clone(),
+            _ => return,
+        };
+        self.stage_update(past);
         self.waker.wake_by_ref();
     }
+
+    /// Make `commit` pending as a new update, recording it in the history.
+    fn stage_update(&mut self, commit: Commit<State>) {
+        let latest = self.latest_commit().clone();
+        if let Some(history) = &mut self.history {
+            history.record_update(&latest);
+        }
+        self.set_pending(commit);
+    }
+
+    /// Make `commit` pending with the current priority, or with the priority
+    /// of the commit it replaces if that is more urgent.
+    fn set_pending(&mut self, commit: Commit<State>) {
+        let mut priority = Priority::current();
+        if let Some((_, replaced)) = &self.pending {
+            priority = priority.max(*replaced);
+        }
+        tracing::debug!(
+            key = ?self.id,
+            location = %self.id.location(),
+            ?priority,
+            "state variable received a commit",
+        );
+        self.lanes.mark(priority);
+        self.pending = Some((commit, priority));
+        self.version.fetch_add(1, Ordering::Release);
+    }
 }
@@ -0,0 +1,190 @@
+//! Thread-safe counterparts of the caching, state, and loading functions in the
+//! crate root, for use within a [`SendRuntime`].
+//!
+//! Each function here behaves like the function of the same name in the crate
+//! root, but stores its values in the [`SendRuntime`]'s thread-safe cache and
+//! so requires that they implement `Send`. State variables must also be `Sync`
+//! because their [`Commit`]s are shared between the runtime and its [`Key`]s.
+//! Futures passed to the loading functions must be `Send` so they can be
+//! spawned onto multi-threaded executors.
+//!
+//! These functions must be called within a [`SendRuntime`]'s revision and the
+//! crate root's functions must be called within a [`crate::runtime::Runtime`]'s.
+//!
+//! # Example
+//!
+//! ```
+//! use moxie::{runtime::SendRunLoop, sync::{cache, state}};
+//! use std::thread;
+//!
+//! let mut rt = SendRunLoop::new(|| {
+//!     let (count, key) = state(|| 1u64);
+//!     let factorial = cache(&*count, |n| (1..=*n).product::<u64>());
+//!     (factorial, key)
+//! });
+//!
+//! let (factorial, key) = rt.run_once();
+//! assert_eq!(factorial, 1);
+//! key.set(5);
+//!
+//! let worker = thread::spawn(move || rt.run_once().0);
+//! assert_eq!(worker.join().unwrap(), 120);
+//! ```
+//!
+//! [`SendRuntime`]: crate::runtime::SendRuntime
+
+use crate::{runtime::SendContext, Commit, Key};
+use std::{borrow::Borrow, future::Future, task::Poll};
+use topo::CallId;
+
+/// Cache the return of the `init` function, calling `with` on the stored
+/// `Output`. See [`crate::cache_with`].
+#[topo::nested]
+#[illicit::from_env(rt: &SendContext)]
+pub fn cache_with<Arg, Input, Output, Ret>(
+    arg: &Arg,
+    init: impl FnOnce(&Input) -> Output,
+    with: impl FnOnce(&Output) -> Ret,
+) -> Ret
+where
+    Arg: PartialEq<Input> + ToOwned<Owned = Input> + ?Sized,
+    Input: Borrow<Arg> + Send + 'static,
+    Output: Send + 'static,
+    Ret: Send + 'static,
+{
+    rt.cache.cache_with(&CallId::current(), arg, init, with)
+}
+
+/// Caches `init` once in the current [`topo::CallId`]. Runs `with` on every
+/// [`crate::runtime::Revision`]. See [`crate::once_with`].
+#[topo::nested]
+#[illicit::from_env(rt: &SendContext)]
+pub fn once_with<Output, Ret>(
+    init: impl FnOnce() -> Output,
+    with: impl FnOnce(&Output) -> Ret,
+) -> Ret
+where
+    Output: Send + 'static,
+    Ret: Send + 'static,
+{
+    rt.cache.cache_with(&CallId::current(), &(), |&()| init(), with)
+}
+
+/// Memoizes `init` at this callsite, cloning a cached `Output` if it exists and
+/// `Input` is the same as when the stored value was created. See
+/// [`crate::cache`].
+#[topo::nested]
+#[illicit::from_env(rt: &SendContext)]
+pub fn cache<Arg, Input, Output>(arg: &Arg, init: impl FnOnce(&Input) -> Output) -> Output
+where
+    Arg: PartialEq<Input> + ToOwned<Owned = Input> + ?Sized,
+    Input: Borrow<Arg> + Send + 'static,
+    Output: Clone + Send + 'static,
+{
+    rt.cache.cache(&CallId::current(), arg, init)
+}
+
+/// Runs `init` once per [`topo::CallId`], cloning the stored value on
+/// subsequent calls. See [`crate::once`].
+#[topo::nested]
+#[illicit::from_env(rt: &SendContext)]
+pub fn once<Output>(init: impl FnOnce() -> Output) -> Output
+where
+    Output: Clone + Send + 'static,
+{
+    rt.cache.cache(&CallId::current(), &(), |()| init())
+}
+
+/// Root a thread-safe state variable at this callsite, returning a [`Key`] to
+/// the state variable. See [`crate::state`].
+#[topo::nested]
+#[illicit::from_env(rt: &SendContext)]
+pub fn state<Output>(init: impl FnOnce() -> Output) -> (Commit<Output>, Key<Output>)
+where
+    Output: Send + Sync + 'static,
+{
+    rt.cache_state(&CallId::current(), &(), |_| init())
+}
+
+/// Root a thread-safe state variable at this callsite, re-initializing it if
+/// the capture `arg` changes. See [`crate::cache_state`].
+#[topo::nested]
+#[illicit::from_env(rt: &SendContext)]
+pub fn cache_state<Arg, Input, Output>(
+    arg: &Arg,
+    init: impl FnOnce(&Input) -> Output,
+) -> (Commit<Output>, Key<Output>)
+where
+    Arg: PartialEq<Input> + ToOwned<Owned = Input> + ?Sized,
+    Input: Borrow<Arg> + Send + 'static,
+    Output: Send + Sync + 'static,
+{
+    rt.cache_state(&CallId::current(), arg, init)
+}
+
+/// Load a value from the future returned by `init` whenever `arg` changes,
+/// returning the result of calling `with` with the loaded value. See
+/// [`crate::load_with`].
+#[topo::nested]
+#[illicit::from_env(rt: &SendContext)]
+pub fn load_with<Arg, Input, Fut, Output, Ret>(
+    arg: &Arg,
+    init: impl FnOnce(&Input) -> Fut,
+    with: impl FnOnce(&Output) -> Ret,
+) -> Poll<Ret>
+where
+    Arg: PartialEq<Input> + ToOwned<Owned = Input> + ?Sized,
+    Input: Borrow<Arg> + Send + 'static,
+    Fut: Future<Output = Output> + Send + 'static,
+    Output: Send + Sync + 'static,
+    Ret: 'static,
+{
+    rt.load_with(&CallId::current(), arg, init, with)
+}
+
+/// Calls [`load_with`] but never re-initializes the loading future. See
+/// [`crate::load_once_with`].
+#[topo::nested]
+#[illicit::from_env(rt: &SendContext)]
+pub fn load_once_with<Fut, Output, Ret>(
+    init: impl FnOnce() -> Fut,
+    with: impl FnOnce(&Output) -> Ret,
+) -> Poll<Ret>
+where
+    Fut: Future<Output = Output> + Send + 'static,
+    Output: Send + Sync + 'static,
+    Ret: 'static,
+{
+    rt.load_with(&CallId::current(), &(), |()| init(), with)
+}
+
+/// Calls [`load_with`], never re-initializes the loading future, and clones the
+/// returned value on each revision once the future has completed. See
+/// [`crate::load_once`].
+#[topo::nested]
+#[illicit::from_env(rt: &SendContext)]
+pub fn load_once<Fut, Output>(init: impl FnOnce() -> Fut) -> Poll<Output>
+where
+    Fut: Future<Output = Output> + Send + 'static,
+    Output: Clone + Send + Sync + 'static,
+{
+    rt.load_with(&CallId::current(), &(), |()| init(), Clone::clone)
+}
+
+/// Load a value from a future, cloning it on subsequent revisions after it is
+/// first returned and re-initializing the future if the capture argument
+/// changes. See [`crate::load`].
+#[topo::nested]
+#[illicit::from_env(rt: &SendContext)]
+pub fn load<Arg, Input, Fut, Output>(
+    capture: &Arg,
+    init: impl FnOnce(&Input) -> Fut,
+) -> Poll<Output>
+where
+    Arg: PartialEq<Input> + ToOwned<Owned = Input> + ?Sized,
+    Input: Borrow<Arg> + Send + 'static,
+    Fut: Future<Output = Output> + Send + 'static,
+    Output: Clone + Send + Sync + 'static,
+{
+    rt.load_with(&CallId::current(), capture, init, Clone::clone)
+}
@@ -74,6 +74,13 @@
 //!
 //! Internally, slots are interned in a global [`dyn-cache`].
 //!
+//! ## Stable paths
+//!
+//! Because slots are interned in the order they're first seen, `CallId`s
+//! can't be compared between processes. [`CallId::path`] returns a
+//! [`CallPath`] which describes the same position in the callgraph
+//! deterministically, for use in persisted or transmitted data.
+//!
 //! [Incremental Computing]: https://en.wikipedia.org/wiki/Incremental_computing
 //! [caching problem]: https://en.wikipedia.org/wiki/Cache_(computing)
 
@@ -132,9 +139,18 @@
 #[doc(inline)]
 pub use topo_macro::nested;
 
-use slot::{OpaqueSlot, Slot};
-use std::{borrow::Borrow, cell::RefCell, hash::Hash, panic::Location};
+pub use path::{CallPath, ParseCallPathError, PathSegment};
 
+use slot::{OpaqueSlot, Slot};
+use std::{
+    borrow::Borrow,
+    cell::RefCell,
+    fmt::{Debug, Formatter, Result as FmtResult},
+    hash::{Hash, Hasher},
+    panic::Location,
+};
+
+mod path;
 mod slot;
 
 /// Calls the provided function as a child of [`CallId::current`], using for a
@@ -149,6 +165,9 @@ mod slot;
 ///
 /// See [`CallId`], [`root`], [`call_in_slot`], and [`nested`].
 ///
+/// Each call runs within a trace-level [`tracing`] span named `call`, tagged
+/// with the new `CallId` and the source location of the call.
+///
 /// # Example
 ///
 /// ```
@@ -188,7 +207,7 @@ where
 
     let callsite = Callsite::here();
     let count = CallCount(callsite.current_count());
-    Scope::with_current(|p| p.make_child(callsite, &count)).enter(op)
+    Scope::with_current(|p| p.make_child(callsite, &count)).enter(|| callsite.traced(op))
 }
 
 /// Calls the provided function as a child of [`CallId::current`], using `slot`
@@ -265,7 +284,8 @@ where
     Q: Eq + Hash + ToOwned<Owned = S> + ?Sized,
     S: Borrow<Q> + Eq + Hash + Send + 'static,
 {
-    Scope::with_current(|p| p.make_child(Callsite::here(), slot)).enter(op)
+    let caller = Callsite::here();
+    Scope::with_current(|p| p.make_child(Callsite::here(), slot)).enter(|| caller.traced(op))
 }
 
 /// Calls the provided function as the root of a new call tree, ignoring the
@@ -420,6 +440,23 @@ impl CallId {
         Scope::with_current(|current| current.id)
     }
 
+    /// Returns the source location of the call which created this `CallId`.
+    ///
+    /// Calls made with [`call_in_slot`] are identified by their slot rather
+    /// than their location, so their `CallId`s share a location within this
+    /// crate.
+    pub fn location(&self) -> &'static Location<'static> {
+        self.callsite.location
+    }
+
+    /// Returns a description of this `CallId`'s position in the callgraph
+    /// which, unlike the `CallId` itself, is stable across processes.
+    ///
+    /// See [`CallPath`] for details.
+    pub fn path(&self) -> CallPath {
+        CallPath::of(*self)
+    }
+
     pub(crate) fn child<Q, S>(&self, callsite: Callsite, slot: &Q) -> Self
     where
         Q: Eq + Hash + ToOwned<Owned = S> + ?Sized,
@@ -430,9 +467,9 @@ impl CallId {
 }
 
 /// A value unique to the source location where it is created.
-#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[derive(Clone, Copy)]
 struct Callsite {
-    location: usize,
+    location: &'static Location<'static>,
 }
 
 impl Callsite {
@@ -443,6 +480,19 @@ impl Callsite {
         Location::caller().into()
     }
 
+    /// Runs `op` within a trace-level `call` span tagged with the current
+    /// [`CallId`] and this callsite's location.
+    #[inline(always)]
+    fn traced<R>(self, op: impl FnOnce() -> R) -> R {
+        let _entered = self.span().entered();
+        op()
+    }
+
+    #[inline(never)] // keep the span's setup out of each generic caller
+    fn span(self) -> tracing::Span {
+        tracing::trace_span!("call", id = ?CallId::current(), location = %self.location)
+    }
+
     /// Returns the number of times this callsite has been seen in the current
     /// call.
     pub fn current_count(self) -> u32 {
@@ -459,12 +509,29 @@ impl Callsite {
 
 impl From<&'static Location<'static>> for Callsite {
     fn from(location: &'static Location<'static>) -> Self {
-        Self {
-            // the pointer value for a given location is enough to differentiate it from all others
-            location: location as *const _ as usize,
-        }
+        Self { location }
+    }
+}
+
+// the pointer value for a given location is enough to differentiate it from all others
+impl Debug for Callsite {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.debug_struct("Callsite").field("location", &(self.location as *const _)).finish()
+    }
+}
+
+impl Hash for Callsite {
+    fn hash<H: Hasher>(&self, hasher: &mut H) {
+        (self.location as *const Location).hash(hasher)
+    }
+}
+
+impl PartialEq for Callsite {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self.location, other.location)
     }
 }
+impl Eq for Callsite {}
 
 /// The root of a sub-graph within the overall topology.
 ///
@@ -0,0 +1,318 @@
+use super::{
+    slot::{OpaqueSlot, Slot},
+    CallId,
+};
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use std::{
+    any::{type_name, Any, TypeId},
+    collections::{hash_map::DefaultHasher, HashMap},
+    error::Error,
+    fmt::{Display, Formatter, Result as FmtResult},
+    hash::{Hash, Hasher},
+    str::FromStr,
+};
+
+/// The stable hashes of interned slot values and the interned parent
+/// `CallId`s, from which paths are built when they're requested.
+static INTERNED: Lazy<Mutex<Interned>> = Lazy::new(Default::default);
+
+#[derive(Default)]
+struct Interned {
+    slot_hashes: HashMap<OpaqueSlot, u64>,
+    parents: HashMap<Slot<CallId>, CallId>,
+}
+
+/// Record what's needed to build paths through a value which has just been
+/// interned as a slot. Only called the first time each value is interned, so
+/// calls which reuse a slot don't pay for it.
+pub(crate) fn interned<Q, S>(slot: Slot<S>, value: &Q)
+where
+    Q: Hash + ToOwned<Owned = S> + ?Sized,
+    S: 'static,
+{
+    let mut interned = INTERNED.lock();
+    interned.slot_hashes.insert(slot.into(), hash_slot::<Q, S>(value));
+    if TypeId::of::<S>() == TypeId::of::<CallId>() {
+        let owned = value.to_owned();
+        let parent = *(&owned as &dyn Any).downcast_ref::<CallId>().unwrap();
+        interned.parents.insert(slot.retype(), parent);
+    }
+}
+
+/// Hash a slot value with a fixed-key hasher so that the result doesn't vary
+/// between runs. The owned slot type's name is included to distinguish equal
+/// values of different types, as `CallId` does.
+fn hash_slot<Q, S>(slot: &Q) -> u64
+where
+    Q: Hash + ?Sized,
+{
+    let mut hasher = DefaultHasher::new();
+    type_name::<S>().hash(&mut hasher);
+    slot.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// A deterministic description of a [`CallId`]'s position in the callgraph,
+/// returned by [`CallId::path`].
+///
+/// Where `CallId`s are only comparable within a single process, a `CallPath`
+/// is identical for the same chain of calls across runs of the same binary.
+/// This makes it suitable for identifying state which is persisted across
+/// processes or sent between a server and a client.
+///
+/// A path has a [`PathSegment`] for each call between the root and the
+/// `CallId`, and can be serialized with its `Display` and `FromStr` impls:
+///
+/// ```
+/// use topo::{call_in_slot, root, CallId, CallPath};
+///
+/// let (first, second) = root(|| {
+///     let first = call_in_slot("first", CallId::current);
+///     let second = call_in_slot("second", || topo::call(CallId::current));
+///     (first, second)
+/// });
+///
+/// let (first, second) = (first.path(), second.path());
+/// assert_eq!(first.segments().len(), 2, "root's call and call_in_slot");
+/// assert_eq!(second.segments().len(), 3, "root's call, call_in_slot, and call");
+/// assert_eq!(first.segments()[0], second.segments()[0], "same parent");
+/// assert_ne!(first.segments()[1].slot(), second.segments()[1].slot(), "different slots");
+///
+/// let serialized = second.to_string();
+/// assert_eq!(serialized.parse::<CallPath>().unwrap(), second);
+/// ```
+///
+/// # Stability
+///
+/// Slot values are hashed with [`std::hash::Hash`], so slots whose hashes
+/// depend on memory addresses or randomized state will produce different paths
+/// in each run. The hashes used may also change between Rust versions and
+/// target platforms, so paths should only be compared between builds from the
+/// same toolchain and target.
+///
+/// # Memory Usage
+///
+/// Paths are built on request from topo's interned slots, which already retain
+/// every parent `CallId` and slot value. Each slot additionally retains its
+/// hash, computed once when the slot is first interned.
+#[derive(Clone, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct CallPath {
+    segments: Vec<PathSegment>,
+}
+
+impl CallPath {
+    /// Returns the path of `id`, following its parents through the slots they
+    /// were interned in.
+    pub(crate) fn of(mut id: CallId) -> Self {
+        let interned = INTERNED.lock();
+        let mut segments = vec![];
+        while let Some(parent) = interned.parents.get(&id.parent) {
+            segments.push(PathSegment {
+                file: id.callsite.location.file().to_owned(),
+                line: id.callsite.location.line(),
+                column: id.callsite.location.column(),
+                slot: interned.slot_hashes[&id.slot],
+            });
+            id = *parent;
+        }
+        segments.reverse();
+        Self { segments }
+    }
+
+    /// Returns the calls which make up this path, starting with the outermost.
+    pub fn segments(&self) -> &[PathSegment] {
+        &self.segments
+    }
+}
+
+impl Display for CallPath {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        for (i, segment) in self.segments.iter().enumerate() {
+            if i > 0 {
+                f.write_str(";")?;
+            }
+            write!(f, "{}", segment)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for CallPath {
+    type Err = ParseCallPathError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Ok(Self::default());
+        }
+        let segments = s.split(';').map(str::parse).collect::<Result<_, _>>()?;
+        Ok(Self { segments })
+    }
+}
+
+/// A single call in a [`CallPath`]: the source location of the call and the
+/// hash of its slot.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct PathSegment {
+    file: String,
+    line: u32,
+    column: u32,
+    slot: u64,
+}
+
+impl PathSegment {
+    /// Returns the name of the source file containing the call.
+    pub fn file(&self) -> &str {
+        &self.file
+    }
+
+    /// Returns the line number of the call.
+    pub fn line(&self) -> u32 {
+        self.line
+    }
+
+    /// Returns the column of the call.
+    pub fn column(&self) -> u32 {
+        self.column
+    }
+
+    /// Returns the hash of the call's slot.
+    pub fn slot(&self) -> u64 {
+        self.slot
+    }
+}
+
+impl Display for PathSegment {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        // `;` separates segments, so it's percent-encoded along with `%` itself
+        let file = self.file.replace('%', "%25").replace(';', "%3B");
+        write!(f, "{}:{}:{}#{:016x}", file, self.line, self.column, self.slot)
+    }
+}
+
+impl FromStr for PathSegment {
+    type Err = ParseCallPathError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || ParseCallPathError { segment: s.to_owned() };
+        let (callsite, slot) = s.rsplit_once('#').ok_or_else(invalid)?;
+        let (callsite, column) = callsite.rsplit_once(':').ok_or_else(invalid)?;
+        let (file, line) = callsite.rsplit_once(':').ok_or_else(invalid)?;
+        Ok(Self {
+            file: decode_file(file).ok_or_else(invalid)?,
+            line: line.parse().map_err(|_| invalid())?,
+            column: column.parse().map_err(|_| invalid())?,
+            slot: u64::from_str_radix(slot, 16).map_err(|_| invalid())?,
+        })
+    }
+}
+
+/// Decodes a file name written by `PathSegment`'s `Display` impl.
+fn decode_file(encoded: &str) -> Option<String> {
+    let mut decoded = String::with_capacity(encoded.len());
+    let mut parts = encoded.split('%');
+    decoded.push_str(parts.next()?);
+    for part in parts {
+        let (escape, rest) = (part.get(..2)?, &part[2..]);
+        decoded.push(match escape {
+            "25" => '%',
+            "3B" | "3b" => ';',
+            _ => return None,
+        });
+        decoded.push_str(rest);
+    }
+    Some(decoded)
+}
+
+/// Returned when parsing a [`CallPath`] from an invalid string.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ParseCallPathError {
+    segment: String,
+}
+
+impl Display for ParseCallPathError {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "invalid call path segment `{}`", self.segment)
+    }
+}
+
+impl Error for ParseCallPathError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{call, call_in_slot, root};
+
+    #[test]
+    fn paths_follow_calls() {
+        let (outer, inner, sibling) = root(|| {
+            let (outer, inner) = call(|| (CallId::current(), call(CallId::current)));
+            (outer, inner, call(CallId::current))
+        });
+        let (outer, inner, sibling) = (outer.path(), inner.path(), sibling.path());
+
+        assert_eq!(inner.segments()[..2], outer.segments()[..]);
+        assert_eq!(outer.segments().len(), sibling.segments().len());
+        assert_ne!(outer, sibling);
+        assert!(outer.segments()[1..].iter().all(|s| s.file() == file!()));
+    }
+
+    #[test]
+    fn repeated_calls_have_distinct_paths() {
+        let paths = root(|| (0..3).map(|_| call(CallId::current).path()).collect::<Vec<_>>());
+        let callsite = |p: &CallPath| {
+            let last = p.segments().last().unwrap();
+            (last.line(), last.column())
+        };
+        assert_eq!(callsite(&paths[0]), callsite(&paths[1]));
+        assert_ne!(paths[0], paths[1]);
+        assert_ne!(paths[1], paths[2]);
+    }
+
+    #[test]
+    fn slots_are_hashed_by_value_and_type() {
+        let slot = |id: CallId| id.path().segments().last().unwrap().slot();
+        let (borrowed, owned, small, large) = root(|| {
+            (
+                call_in_slot("a", CallId::current),
+                call_in_slot(&String::from("a"), CallId::current),
+                call_in_slot(&1u8, CallId::current),
+                call_in_slot(&1u16, CallId::current),
+            )
+        });
+        assert_eq!(slot(borrowed), slot(owned));
+        assert_ne!(slot(small), slot(large));
+    }
+
+    #[test]
+    fn slots_dont_depend_on_interning_order() {
+        let slot = |name: &str| root(|| call_in_slot(name, CallId::current)).path();
+        let second = slot("interned second").segments()[1].slot();
+        let first = slot("interned first").segments()[1].slot();
+        assert_eq!(first, hash_slot::<str, String>("interned first"));
+        assert_eq!(second, hash_slot::<str, String>("interned second"));
+    }
+
+    #[test]
+    fn round_trip() {
+        let path = root(|| call_in_slot("key", || call(CallId::current))).path();
+        assert_eq!(path.to_string().parse::<CallPath>(), Ok(path));
+        assert_eq!("".parse::<CallPath>(), Ok(CallPath::default()));
+
+        let segment = "C:\\src\\main.rs:10:5#00000000000000ff".parse::<PathSegment>().unwrap();
+        assert_eq!(segment.file(), "C:\\src\\main.rs");
+        assert_eq!((segment.line(), segment.column(), segment.slot()), (10, 5, 255));
+
+        let segment = "a%3Bb%25.rs:1:2#0".parse::<PathSegment>().unwrap();
+        assert_eq!(segment.file(), "a;b%.rs");
+        assert_eq!(segment.to_string(), "a%3Bb%25.rs:1:2#0000000000000000");
+        let path = format!("{};{}", segment, segment).parse::<CallPath>().unwrap();
+        assert_eq!(path.segments(), [segment.clone(), segment]);
+
+        for invalid in
+            &["main.rs", "main.rs:1#0", "main.rs:a:1#0", "main.rs:1:1#xyz", "50%.rs:1:1#0"]
+        {
+            assert!(invalid.parse::<CallPath>().is_err(), "{} must not parse", invalid);
+        }
+    }
+}
@@ -57,6 +57,7 @@ where
                     (new_token, new_token)
                 });
                 existing_tokens.store(to_store);
+                crate::path::interned(new_token, value);
                 new_token
             }
         }
@@ -68,6 +69,13 @@ where
     }
 }
 
+impl<T> Slot<T> {
+    /// Returns a token with the same index for `U`, which must be `T`.
+    pub(crate) fn retype<U>(self) -> Slot<U> {
+        Slot { index: self.index, ty: PhantomData }
+    }
+}
+
 impl<T> Clone for Slot<T> {
     fn clone(&self) -> Self {
         Self { index: self.index, ty: PhantomData }